@@ -10,12 +10,12 @@ extern crate openssl_sys;
 extern crate winapi;
 
 use libc::{c_int, c_char, c_uint, c_long, c_double, c_void, size_t, time_t};
-use libc::c_ulong;
+use libc::{c_ulong, c_short, c_uchar};
 
 #[cfg(unix)]
-use libc::fd_set;
+use libc::{fd_set, sockaddr};
 #[cfg(windows)]
-use winapi::fd_set;
+use winapi::{fd_set, SOCKADDR as sockaddr};
 
 #[cfg(target_env = "msvc")]
 #[doc(hidden)]
@@ -155,6 +155,19 @@ pub type curl_seek_callback = extern fn(*mut c_void,
                                         curl_off_t,
                                         c_int) -> c_int;
 
+pub const CURL_TRAILERFUNC_OK: c_int = 0;
+pub const CURL_TRAILERFUNC_ABORT: c_int = 1;
+pub type curl_trailer_callback = extern fn(*mut *mut curl_slist,
+                                           *mut c_void) -> c_int;
+
+pub const CURL_PREREQFUNC_OK: c_int = 0;
+pub const CURL_PREREQFUNC_ABORT: c_int = 1;
+pub type curl_prereq_callback = extern fn(*mut c_void,
+                                          *mut c_char,
+                                          *mut c_char,
+                                          c_int,
+                                          c_int) -> c_int;
+
 pub const CURL_READFUNC_ABORT: size_t = 0x10000000;
 pub const CURL_READFUNC_PAUSE: size_t = 0x10000001;
 pub type curl_read_callback = extern fn(*mut c_char,
@@ -162,29 +175,72 @@ pub type curl_read_callback = extern fn(*mut c_char,
                                         size_t,
                                         *mut c_void) -> size_t;
 
-// pub type curlsocktype = __enum_ty;
-// pub const CURLSOCKTYPE_IPCXN: curlsocktype = 0;
-// pub const CURLSOCKTYPE_ACCEPT: curlsocktype = 1;
-// pub const CURL_SOCKOPT_OK: c_int = 0;
-// pub const CURL_SOCKOPT_ERROR: c_int = 1;
-// pub const CURL_SOCKOPT_ALREADY_CONNECTED: c_int = 2;
-// pub type curl_sockopt_callback = extern fn(*mut c_void,
-//                                            curl_socket_t,
-//                                            curlsocktype) -> c_int;
-
-// TODO: sort out libc::sockaddr on windows
-// #[repr(C)]
-// pub struct curl_sockaddr {
-//     pub family: c_int,
-//     pub socktype: c_int,
-//     pub protocol: c_int,
-//     pub addrlen: c_uint,
-//     pub addr: libc::sockaddr,
-// }
-//
-// pub type curl_opensocket_callback = extern fn(*mut c_void,
-//                                               curlsocktype,
-//                                               *mut curl_sockaddr) -> curl_socket_t;
+pub type curlsocktype = __enum_ty;
+pub const CURLSOCKTYPE_IPCXN: curlsocktype = 0;
+pub const CURLSOCKTYPE_ACCEPT: curlsocktype = 1;
+pub const CURL_SOCKOPT_OK: c_int = 0;
+pub const CURL_SOCKOPT_ERROR: c_int = 1;
+pub const CURL_SOCKOPT_ALREADY_CONNECTED: c_int = 2;
+pub type curl_sockopt_callback = extern fn(*mut c_void,
+                                           curl_socket_t,
+                                           curlsocktype) -> c_int;
+
+#[repr(C)]
+pub struct curl_sockaddr {
+    pub family: c_int,
+    pub socktype: c_int,
+    pub protocol: c_int,
+    pub addrlen: c_uint,
+    pub addr: sockaddr,
+}
+
+pub type curl_opensocket_callback = extern fn(*mut c_void,
+                                              curlsocktype,
+                                              *mut curl_sockaddr)
+                                              -> curl_socket_t;
+pub type curl_closesocket_callback = extern fn(*mut c_void,
+                                               curl_socket_t) -> c_int;
+
+pub const CURLALTSVC_READONLYFILE: c_long = 1 << 2;
+pub const CURLALTSVC_H1: c_long = 1 << 3;
+pub const CURLALTSVC_H2: c_long = 1 << 4;
+pub const CURLALTSVC_H3: c_long = 1 << 5;
+
+pub const CURLHSTS_ENABLE: c_long = 1 << 0;
+pub const CURLHSTS_READONLYFILE: c_long = 1 << 1;
+
+#[repr(C)]
+pub struct curl_hstsentry {
+    pub name: *mut c_char,
+    pub namelen: size_t,
+    /// A one bit wide bitfield in C, which most compilers pack into a single
+    /// byte together with the start of `expire`, so only that byte is
+    /// declared here.
+    #[cfg(not(target_env = "msvc"))]
+    pub includeSubDomains: c_uchar,
+    #[cfg(target_env = "msvc")]
+    pub includeSubDomains: c_uint,
+    pub expire: [c_char; 18],
+}
+
+#[repr(C)]
+pub struct curl_index {
+    pub index: size_t,
+    pub total: size_t,
+}
+
+pub type CURLSTScode = __enum_ty;
+pub const CURLSTS_OK: CURLSTScode = 0;
+pub const CURLSTS_DONE: CURLSTScode = 1;
+pub const CURLSTS_FAIL: CURLSTScode = 2;
+
+pub type curl_hstsread_callback = extern fn(*mut CURL,
+                                            *mut curl_hstsentry,
+                                            *mut c_void) -> CURLSTScode;
+pub type curl_hstswrite_callback = extern fn(*mut CURL,
+                                             *mut curl_hstsentry,
+                                             *mut curl_index,
+                                             *mut c_void) -> CURLSTScode;
 
 pub type curlioerr = __enum_ty;
 pub const CURLIOE_OK: curlioerr = 0;
@@ -222,7 +278,7 @@ pub const CURLE_OK: CURLcode = 0;
 pub const CURLE_UNSUPPORTED_PROTOCOL: CURLcode = 1;
 pub const CURLE_FAILED_INIT: CURLcode = 2;
 pub const CURLE_URL_MALFORMAT: CURLcode = 3;
-// pub const CURLE_NOT_BUILT_IN: CURLcode = 4;
+pub const CURLE_NOT_BUILT_IN: CURLcode = 4;
 pub const CURLE_COULDNT_RESOLVE_PROXY: CURLcode = 5;
 pub const CURLE_COULDNT_RESOLVE_HOST: CURLcode = 6;
 pub const CURLE_COULDNT_CONNECT: CURLcode = 7;
@@ -329,6 +385,7 @@ pub const CURLAUTH_GSSNEGOTIATE: c_ulong = 1 << 2;
 pub const CURLAUTH_NTLM: c_ulong = 1 << 3;
 pub const CURLAUTH_DIGEST_IE: c_ulong =  1 << 4;
 pub const CURLAUTH_NTLM_WB: c_ulong = 1 << 5;
+pub const CURLAUTH_BEARER: c_ulong = 1 << 6;
 // pub const CURLAUTH_ONLY: c_ulong = 1 << 31;
 pub const CURLAUTH_ANY: c_ulong = !CURLAUTH_DIGEST_IE;
 pub const CURLAUTH_ANYSAFE: c_ulong = !(CURLAUTH_BASIC | CURLAUTH_DIGEST_IE);
@@ -388,12 +445,33 @@ pub const CURLUSESSL_ALL: curl_usessl = 3;
 
 pub const CURLPROTO_HTTP: c_int = 1 << 0;
 pub const CURLPROTO_HTTPS: c_int = 1 << 1;
+pub const CURLPROTO_FTP: c_int = 1 << 2;
+pub const CURLPROTO_FTPS: c_int = 1 << 3;
+pub const CURLPROTO_SCP: c_int = 1 << 4;
+pub const CURLPROTO_SFTP: c_int = 1 << 5;
+pub const CURLPROTO_TELNET: c_int = 1 << 6;
+pub const CURLPROTO_LDAP: c_int = 1 << 7;
+pub const CURLPROTO_LDAPS: c_int = 1 << 8;
+pub const CURLPROTO_DICT: c_int = 1 << 9;
 pub const CURLPROTO_FILE: c_int = 1 << 10;
+pub const CURLPROTO_TFTP: c_int = 1 << 11;
+pub const CURLPROTO_IMAP: c_int = 1 << 12;
+pub const CURLPROTO_IMAPS: c_int = 1 << 13;
+pub const CURLPROTO_POP3: c_int = 1 << 14;
+pub const CURLPROTO_POP3S: c_int = 1 << 15;
+pub const CURLPROTO_SMTP: c_int = 1 << 16;
+pub const CURLPROTO_SMTPS: c_int = 1 << 17;
+pub const CURLPROTO_RTSP: c_int = 1 << 18;
+pub const CURLPROTO_GOPHER: c_int = 1 << 25;
+pub const CURLPROTO_SMB: c_int = 1 << 26;
+pub const CURLPROTO_SMBS: c_int = 1 << 27;
+pub const CURLPROTO_ALL: c_int = !0;
 
 pub const CURLOPTTYPE_LONG: CURLoption = 0;
 pub const CURLOPTTYPE_OBJECTPOINT: CURLoption = 10_000;
 pub const CURLOPTTYPE_FUNCTIONPOINT: CURLoption = 20_000;
 pub const CURLOPTTYPE_OFF_T: CURLoption = 30_000;
+pub const CURLOPTTYPE_BLOB: CURLoption = 40_000;
 
 pub const CURLOPT_FILE: CURLoption = CURLOPTTYPE_OBJECTPOINT + 1;
 pub const CURLOPT_URL: CURLoption = CURLOPTTYPE_OBJECTPOINT + 2;
@@ -587,20 +665,47 @@ pub const CURLOPT_TRANSFER_ENCODING: CURLoption = CURLOPTTYPE_LONG + 207;
 pub const CURLOPT_CLOSESOCKETFUNCTION: CURLoption = CURLOPTTYPE_FUNCTIONPOINT + 208;
 pub const CURLOPT_CLOSESOCKETDATA: CURLoption = CURLOPTTYPE_OBJECTPOINT + 209;
 pub const CURLOPT_GSSAPI_DELEGATION: CURLoption = CURLOPTTYPE_LONG + 210;
-// pub const CURLOPT_DNS_SERVERS: CURLoption = CURLOPTTYPE_OBJECTPOINT + 211;
+pub const CURLOPT_DNS_SERVERS: CURLoption = CURLOPTTYPE_OBJECTPOINT + 211;
 // pub const CURLOPT_ACCEPTTIMEOUT_MS: CURLoption = CURLOPTTYPE_LONG + 212;
-// pub const CURLOPT_TCP_KEEPALIVE: CURLoption = CURLOPTTYPE_LONG + 213;
-// pub const CURLOPT_TCP_KEEPIDLE: CURLoption = CURLOPTTYPE_LONG + 214;
-// pub const CURLOPT_TCP_KEEPINTVL: CURLoption = CURLOPTTYPE_LONG + 215;
+pub const CURLOPT_TCP_KEEPALIVE: CURLoption = CURLOPTTYPE_LONG + 213;
+pub const CURLOPT_TCP_KEEPIDLE: CURLoption = CURLOPTTYPE_LONG + 214;
+pub const CURLOPT_TCP_KEEPINTVL: CURLoption = CURLOPTTYPE_LONG + 215;
 // pub const CURLOPT_SSL_OPTIONS: CURLoption = CURLOPTTYPE_LONG + 216;
 // pub const CURLOPT_MAIL_AUTH: CURLoption = CURLOPTTYPE_OBJECTPOINT + 217;
-// pub const CURLOPT_SASL_IR: CURLoption = CURLOPTTYPE_LONG + 218;
+pub const CURLOPT_SASL_IR: CURLoption = CURLOPTTYPE_LONG + 218;
 // pub const CURLOPT_XFERINFOFUNCTION: CURLoption = CURLOPTTYPE_FUNCTIONPOINT + 219;
-// pub const CURLOPT_XOAUTH2_BEARER: CURLoption = CURLOPTTYPE_OBJECTPOINT + 220;
-// pub const CURLOPT_DNS_INTERFACE: CURLoption = CURLOPTTYPE_OBJECTPOINT + 221;
-// pub const CURLOPT_DNS_LOCAL_IP4: CURLoption = CURLOPTTYPE_OBJECTPOINT + 222;
-// pub const CURLOPT_DNS_LOCAL_IP6: CURLoption = CURLOPTTYPE_OBJECTPOINT + 223;
-// pub const CURLOPT_LOGIN_OPTIONS: CURLoption = CURLOPTTYPE_OBJECTPOINT + 224;
+pub const CURLOPT_XOAUTH2_BEARER: CURLoption = CURLOPTTYPE_OBJECTPOINT + 220;
+pub const CURLOPT_DNS_INTERFACE: CURLoption = CURLOPTTYPE_OBJECTPOINT + 221;
+pub const CURLOPT_DNS_LOCAL_IP4: CURLoption = CURLOPTTYPE_OBJECTPOINT + 222;
+pub const CURLOPT_DNS_LOCAL_IP6: CURLoption = CURLOPTTYPE_OBJECTPOINT + 223;
+pub const CURLOPT_LOGIN_OPTIONS: CURLoption = CURLOPTTYPE_OBJECTPOINT + 224;
+pub const CURLOPT_PATH_AS_IS: CURLoption = CURLOPTTYPE_LONG + 234;
+pub const CURLOPT_PIPEWAIT: CURLoption = CURLOPTTYPE_LONG + 237;
+pub const CURLOPT_TCP_FASTOPEN: CURLoption = CURLOPTTYPE_LONG + 244;
+pub const CURLOPT_KEEP_SENDING_ON_ERROR: CURLoption = CURLOPTTYPE_LONG + 245;
+pub const CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS: CURLoption = CURLOPTTYPE_LONG + 271;
+pub const CURLOPT_DOH_URL: CURLoption = CURLOPTTYPE_OBJECTPOINT + 279;
+pub const CURLOPT_UPLOAD_BUFFERSIZE: CURLoption = CURLOPTTYPE_LONG + 280;
+pub const CURLOPT_UPKEEP_INTERVAL_MS: CURLoption = CURLOPTTYPE_LONG + 281;
+pub const CURLOPT_TRAILERFUNCTION: CURLoption = CURLOPTTYPE_FUNCTIONPOINT + 283;
+pub const CURLOPT_TRAILERDATA: CURLoption = CURLOPTTYPE_OBJECTPOINT + 284;
+pub const CURLOPT_HTTP09_ALLOWED: CURLoption = CURLOPTTYPE_LONG + 285;
+pub const CURLOPT_ALTSVC_CTRL: CURLoption = CURLOPTTYPE_LONG + 286;
+pub const CURLOPT_ALTSVC: CURLoption = CURLOPTTYPE_OBJECTPOINT + 287;
+pub const CURLOPT_MAXAGE_CONN: CURLoption = CURLOPTTYPE_LONG + 288;
+pub const CURLOPT_SASL_AUTHZID: CURLoption = CURLOPTTYPE_OBJECTPOINT + 289;
+pub const CURLOPT_HSTS_CTRL: CURLoption = CURLOPTTYPE_LONG + 299;
+pub const CURLOPT_HSTS: CURLoption = CURLOPTTYPE_OBJECTPOINT + 300;
+pub const CURLOPT_HSTSREADFUNCTION: CURLoption = CURLOPTTYPE_FUNCTIONPOINT + 301;
+pub const CURLOPT_HSTSREADDATA: CURLoption = CURLOPTTYPE_OBJECTPOINT + 302;
+pub const CURLOPT_HSTSWRITEFUNCTION: CURLoption = CURLOPTTYPE_FUNCTIONPOINT + 303;
+pub const CURLOPT_HSTSWRITEDATA: CURLoption = CURLOPTTYPE_OBJECTPOINT + 304;
+pub const CURLOPT_DOH_SSL_VERIFYPEER: CURLoption = CURLOPTTYPE_LONG + 306;
+pub const CURLOPT_DOH_SSL_VERIFYHOST: CURLoption = CURLOPTTYPE_LONG + 307;
+pub const CURLOPT_DOH_SSL_VERIFYSTATUS: CURLoption = CURLOPTTYPE_LONG + 308;
+pub const CURLOPT_PREREQFUNCTION: CURLoption = CURLOPTTYPE_FUNCTIONPOINT + 312;
+pub const CURLOPT_PREREQDATA: CURLoption = CURLOPTTYPE_OBJECTPOINT + 313;
+pub const CURLOPT_MAXLIFETIME_CONN: CURLoption = CURLOPTTYPE_LONG + 314;
 
 pub const CURL_IPRESOLVE_WHATEVER: c_int = 0;
 pub const CURL_IPRESOLVE_V4: c_int = 1;
@@ -644,6 +749,11 @@ pub const CURL_TIMECOND_IFMODSINCE: curl_TimeCond = 1;
 pub const CURL_TIMECOND_IFUNMODSINCE: curl_TimeCond = 2;
 pub const CURL_TIMECOND_LASTMOD: curl_TimeCond = 3;
 
+pub type curl_ftpcreatedir = __enum_ty;
+pub const CURLFTP_CREATE_DIR_NONE: curl_ftpcreatedir = 0;
+pub const CURLFTP_CREATE_DIR: curl_ftpcreatedir = 1;
+pub const CURLFTP_CREATE_DIR_RETRY: curl_ftpcreatedir = 2;
+
 pub type CURLformoption = __enum_ty;
 pub const CURLFORM_NOTHING: CURLformoption = 0;
 pub const CURLFORM_COPYNAME: CURLformoption = 1;
@@ -765,6 +875,8 @@ pub const CURLINFO_PRIMARY_PORT: CURLINFO = CURLINFO_LONG + 40;
 pub const CURLINFO_LOCAL_IP: CURLINFO = CURLINFO_STRING + 41;
 pub const CURLINFO_LOCAL_PORT: CURLINFO = CURLINFO_LONG + 42;
 // pub const CURLINFO_TLS_SESSION: CURLINFO = CURLINFO_SLIST + 43;
+pub const CURLINFO_SCHEME: CURLINFO = CURLINFO_STRING + 49;
+pub const CURLINFO_EFFECTIVE_METHOD: CURLINFO = CURLINFO_STRING + 58;
 
 pub type curl_closepolicy = __enum_ty;
 pub const CURLCLOSEPOLICY_NONE: curl_closepolicy = 0;
@@ -859,7 +971,17 @@ pub const CURL_VERSION_CONV: c_int = 1 << 12;
 pub const CURL_VERSION_CURLDEBUG: c_int = 1 << 13;
 pub const CURL_VERSION_TLSAUTH_SRP: c_int = 1 << 14;
 pub const CURL_VERSION_NTLM_WB: c_int = 1 << 15;
-// pub const CURL_VERSION_HTTP2: c_int = 1 << 16;
+pub const CURL_VERSION_HTTP2: c_int = 1 << 16;
+pub const CURL_VERSION_GSSAPI: c_int = 1 << 17;
+pub const CURL_VERSION_KERBEROS5: c_int = 1 << 18;
+pub const CURL_VERSION_UNIX_SOCKETS: c_int = 1 << 19;
+pub const CURL_VERSION_PSL: c_int = 1 << 20;
+pub const CURL_VERSION_HTTPS_PROXY: c_int = 1 << 21;
+pub const CURL_VERSION_MULTI_SSL: c_int = 1 << 22;
+pub const CURL_VERSION_BROTLI: c_int = 1 << 23;
+pub const CURL_VERSION_ALTSVC: c_int = 1 << 24;
+pub const CURL_VERSION_HTTP3: c_int = 1 << 25;
+pub const CURL_VERSION_ZSTD: c_int = 1 << 26;
 
 pub const CURLPAUSE_RECV: c_int = 1 << 0;
 pub const CURLPAUSE_RECV_CONT: c_int = 0;
@@ -890,16 +1012,16 @@ pub struct CURLMsg {
     pub data: *mut c_void,
 }
 
-// pub const CURL_WAIT_POLLIN: c_short = 0x1;
-// pub const CURL_WAIT_POLLPRI: c_short = 0x2;
-// pub const CURL_WAIT_POLLOUT: c_short = 0x4;
+pub const CURL_WAIT_POLLIN: c_short = 0x1;
+pub const CURL_WAIT_POLLPRI: c_short = 0x2;
+pub const CURL_WAIT_POLLOUT: c_short = 0x4;
 
-// #[repr(C)]
-// pub struct curl_waitfd {
-//     pub fd: curl_socket_t,
-//     pub events: c_short,
-//     pub revents: c_short,
-// }
+#[repr(C)]
+pub struct curl_waitfd {
+    pub fd: curl_socket_t,
+    pub events: c_short,
+    pub revents: c_short,
+}
 
 pub const CURL_POLL_NONE: c_int = 0;
 pub const CURL_POLL_IN: c_int = 1;
@@ -926,14 +1048,19 @@ pub const CURLMOPT_SOCKETDATA: CURLMoption = CURLOPTTYPE_OBJECTPOINT + 2;
 pub const CURLMOPT_PIPELINING: CURLMoption = CURLOPTTYPE_LONG + 3;
 pub const CURLMOPT_TIMERFUNCTION: CURLMoption = CURLOPTTYPE_FUNCTIONPOINT + 4;
 pub const CURLMOPT_TIMERDATA: CURLMoption = CURLOPTTYPE_OBJECTPOINT + 5;
-// pub const CURLMOPT_MAXCONNECTS: CURLMoption = CURLOPTTYPE_LONG + 6;
-// pub const CURLMOPT_MAX_HOST_CONNECTIONS: CURLMoption = CURLOPTTYPE_LONG + 7;
+pub const CURLMOPT_MAXCONNECTS: CURLMoption = CURLOPTTYPE_LONG + 6;
+pub const CURLMOPT_MAX_HOST_CONNECTIONS: CURLMoption = CURLOPTTYPE_LONG + 7;
 // pub const CURLMOPT_MAX_PIPELINE_LENGTH: CURLMoption = CURLOPTTYPE_LONG + 8;
 // pub const CURLMOPT_CONTENT_LENGTH_PENALTY_SIZE: CURLMoption = CURLOPTTYPE_OFF_T + 9;
 // pub const CURLMOPT_CHUNK_LENGTH_PENALTY_SIZE: CURLMoption = CURLOPTTYPE_OFF_T + 10;
 // pub const CURLMOPT_PIPELINING_SITE_BL: CURLMoption = CURLOPTTYPE_OBJECTPOINT + 11;
 // pub const CURLMOPT_PIPELINING_SERVER_BL: CURLMoption = CURLOPTTYPE_OBJECTPOINT + 12;
-// pub const CURLMOPT_MAX_TOTAL_CONNECTIONS: CURLMoption = CURLOPTTYPE_LONG + 13;
+pub const CURLMOPT_MAX_TOTAL_CONNECTIONS: CURLMoption = CURLOPTTYPE_LONG + 13;
+pub const CURLMOPT_MAX_CONCURRENT_STREAMS: CURLMoption = CURLOPTTYPE_LONG + 16;
+
+pub const CURLPIPE_NOTHING: c_long = 0;
+pub const CURLPIPE_HTTP1: c_long = 1;
+pub const CURLPIPE_MULTIPLEX: c_long = 2;
 
 pub const CURL_ERROR_SIZE: usize = 256;
 
@@ -999,6 +1126,7 @@ extern {
                           buffer: *const c_void,
                           buflen: size_t,
                           n: *mut size_t) -> CURLcode;
+    pub fn curl_easy_upkeep(curl: *mut CURL) -> CURLcode;
 
     pub fn curl_multi_init() -> *mut CURLM;
     pub fn curl_multi_add_handle(multi_handle: *mut CURLM,
@@ -1010,11 +1138,11 @@ extern {
                             write_fd_set: *mut fd_set,
                             exc_fd_set: *mut fd_set,
                             max_fd: *mut c_int) -> CURLMcode;
-    // pub fn curl_multi_wait(multi_handle: *mut CURLM,
-    //                        extra_fds: *mut curl_waitfd,
-    //                        extra_nfds: c_uint,
-    //                        timeout_ms: c_int,
-    //                        ret: *mut c_int) -> CURLMcode;
+    pub fn curl_multi_wait(multi_handle: *mut CURLM,
+                           extra_fds: *mut curl_waitfd,
+                           extra_nfds: c_uint,
+                           timeout_ms: c_int,
+                           ret: *mut c_int) -> CURLMcode;
     pub fn curl_multi_perform(multi_handle: *mut CURLM,
                               running_handles: *mut c_int) -> CURLMcode;
     pub fn curl_multi_cleanup(multi_handle: *mut CURLM) -> CURLMcode;
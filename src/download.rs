@@ -0,0 +1,525 @@
+//! Downloading a single file over several connections at once
+//!
+//! Over high-latency links a single connection rarely uses all of the
+//! available bandwidth. A `SegmentedDownload` first asks the server for the
+//! size of the file with a `HEAD` request and then, if the server supports
+//! byte ranges, fetches a number of segments of the file concurrently
+//! through a `Multi` handle, writing each directly to its place on disk.
+//!
+//! The progress of each segment is recorded next to the destination in a file
+//! with a `.segments` suffix while the download is incomplete, so a download
+//! which failed or was interrupted picks up where it left off when it's run
+//! again. Servers which don't support ranges are downloaded from over a
+//! single connection instead.
+//!
+//! With `preserve_modified` enabled, the modification time of the
+//! downloaded file is set to the one reported by the server, so tools
+//! mirroring a remote directory can compare timestamps to detect changes.
+//!
+//! ```no_run
+//! use curl::download::SegmentedDownload;
+//!
+//! let summary = SegmentedDownload::new("https://example.com/big.iso")
+//!     .segments(8)
+//!     .download("big.iso")
+//!     .unwrap();
+//! println!("{} bytes in {} segments", summary.len(), summary.segments());
+//! ```
+
+use std::cmp;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use curl_sys;
+
+use Error;
+use easy::{filetime_to_system_time, Easy};
+use error::{error_with_extra, error_with_status, error_with_url};
+use multi::Multi;
+
+/// A download of a single URL split into concurrently fetched segments.
+///
+/// See the [module documentation](index.html) for more information.
+#[derive(Clone, Debug)]
+pub struct SegmentedDownload {
+    url: String,
+    segments: usize,
+    connect_timeout: Duration,
+    preserve_modified: bool,
+}
+
+/// The outcome of a completed `SegmentedDownload`.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    len: u64,
+    segments: usize,
+    resumed: u64,
+}
+
+/// The saved state of an incomplete download.
+#[derive(Debug, PartialEq)]
+struct State {
+    len: u64,
+    validator: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    start: u64,
+    end: u64,
+    done: u64,
+}
+
+/// What the `HEAD` request revealed about the file.
+#[derive(Clone, Default)]
+struct Probe {
+    code: u32,
+    len: Option<u64>,
+    ranges: bool,
+    validator: String,
+    modified: Option<SystemTime>,
+}
+
+impl SegmentedDownload {
+    /// Creates a new download of `url`, split into four segments.
+    pub fn new(url: &str) -> SegmentedDownload {
+        SegmentedDownload {
+            url: url.to_string(),
+            segments: 4,
+            connect_timeout: Duration::new(30, 0),
+            preserve_modified: false,
+        }
+    }
+
+    /// Configures how many segments the file is split into, and therefore
+    /// how many connections are used at most.
+    ///
+    /// This only affects new downloads, a resumed download keeps the
+    /// segments it started with. By default this is 4.
+    pub fn segments(&mut self, segments: usize) -> &mut SegmentedDownload {
+        self.segments = if segments == 0 {1} else {segments};
+        self
+    }
+
+    /// Configures the timeout for establishing each connection.
+    ///
+    /// By default this is 30 seconds.
+    pub fn connect_timeout(&mut self, timeout: Duration)
+                           -> &mut SegmentedDownload {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Configures whether the modification time of the downloaded file is
+    /// set to the one of the file on the server.
+    ///
+    /// The time is taken from the `Last-Modified` header, and the file keeps
+    /// the time it was written at if the server doesn't send one. This is
+    /// only supported on Unix and does nothing elsewhere. By default this is
+    /// `false`.
+    pub fn preserve_modified(&mut self, preserve: bool)
+                             -> &mut SegmentedDownload {
+        self.preserve_modified = preserve;
+        self
+    }
+
+    /// Downloads the file to `path`, resuming a previous incomplete download
+    /// to the same path if the file on the server hasn't changed since.
+    ///
+    /// If some segments fail, the progress made is saved for the next
+    /// attempt and the first error is returned.
+    pub fn download<P: AsRef<Path>>(&self, path: P) -> io::Result<Summary> {
+        let path = path.as_ref();
+        let probe = try!(self.probe());
+        if probe.code < 200 || probe.code >= 300 {
+            return Err(self.http_error(probe.code))
+        }
+        let len = match probe.len {
+            Some(len) if probe.ranges && len > 0 => len,
+            _ => return self.download_whole(path),
+        };
+
+        let state_path = state_path(path);
+        let saved = State::load(&state_path).ok().and_then(|state| {
+            let unchanged = state.len == len &&
+                state.validator == probe.validator;
+            if unchanged && path.exists() {Some(state)} else {None}
+        });
+        let resuming = saved.is_some();
+        let mut state = saved.unwrap_or_else(|| {
+            State::new(len, probe.validator.clone(), self.segments)
+        });
+        let resumed = state.segments.iter().map(|s| s.done).sum();
+
+        let file = try!(OpenOptions::new().write(true)
+                                          .create(true)
+                                          .truncate(!resuming)
+                                          .open(path));
+        try!(file.set_len(len));
+        try!(state.save(&state_path));
+
+        let result = self.fetch_segments(&file, &mut state);
+        if state.segments.iter().all(|s| s.is_complete()) {
+            drop(fs::remove_file(&state_path));
+        } else {
+            try!(state.save(&state_path));
+        }
+        try!(result);
+        if let Some(modified) = probe.modified {
+            try!(set_modified(&file, modified));
+        }
+        Ok(Summary {
+            len: len,
+            segments: state.segments.len(),
+            resumed: resumed,
+        })
+    }
+
+    fn probe(&self) -> Result<Probe, Error> {
+        let probe = Arc::new(Mutex::new(Probe::default()));
+        let mut easy = try!(self.easy());
+        try!(easy.nobody(true));
+        try!(easy.fetch_filetime(self.preserve_modified));
+        let headers = probe.clone();
+        try!(easy.header_function(move |line| {
+            headers.lock().unwrap().parse_header(line);
+            true
+        }));
+        try!(easy.perform());
+        let mut probe = probe.lock().unwrap().clone();
+        probe.code = try!(easy.response_code());
+        probe.modified = try!(easy.filetime()).map(filetime_to_system_time);
+        Ok(probe)
+    }
+
+    fn download_whole(&self, path: &Path) -> io::Result<Summary> {
+        let mut file = try!(File::create(path));
+        let mut easy = try!(self.easy());
+        try!(easy.fetch_filetime(self.preserve_modified));
+        let mut len = 0;
+        {
+            let mut transfer = easy.transfer();
+            try!(transfer.write_function(|data| {
+                match file.write_all(data) {
+                    Ok(()) => {
+                        len += data.len() as u64;
+                        Ok(data.len())
+                    }
+                    Err(_) => Ok(0),
+                }
+            }));
+            try!(transfer.perform());
+        }
+        let code = try!(easy.response_code());
+        if code < 200 || code >= 300 {
+            return Err(self.http_error(code))
+        }
+        if let Some(secs) = try!(easy.filetime()) {
+            try!(set_modified(&file, filetime_to_system_time(secs)));
+        }
+        Ok(Summary { len: len, segments: 1, resumed: 0 })
+    }
+
+    fn fetch_segments(&self, file: &File, state: &mut State)
+                      -> io::Result<()> {
+        let multi = Multi::new();
+        let mut handles = Vec::new();
+        for (i, segment) in state.segments.iter().enumerate() {
+            if segment.is_complete() {
+                continue
+            }
+            let progress = Arc::new(Mutex::new(segment.clone()));
+            let easy = try!(self.segment_easy(try!(file.try_clone()),
+                                              progress.clone()));
+            let mut handle = try!(multi.add(easy));
+            try!(handle.set_token(i));
+            handles.push((i, progress, handle));
+        }
+
+        let mut results = state.segments.iter().map(|_| None)
+                                               .collect::<Vec<_>>();
+        let mut idle = 0;
+        loop {
+            let running = try!(multi.perform());
+            multi.messages(|msg| {
+                if let (Ok(token), Some(result)) = (msg.token(), msg.result()) {
+                    results[token] = Some(result);
+                }
+            });
+            if running == 0 {
+                break
+            }
+            // `wait` returns right away while there's no descriptor to wait
+            // on yet, so back off instead of spinning if that keeps up.
+            if try!(multi.wait(Duration::new(1, 0))) == 0 {
+                idle += 1;
+                if idle > 1 {
+                    thread::sleep(Duration::from_millis(100));
+                }
+            } else {
+                idle = 0;
+            }
+        }
+
+        let mut first_error = None;
+        for (i, progress, handle) in handles {
+            let mut easy = try!(multi.remove(handle));
+            state.segments[i] = progress.lock().unwrap().clone();
+            let result = match results[i].take() {
+                Some(result) => result,
+                None => Err(Error::new(curl_sys::CURLE_FAILED_INIT)),
+            };
+            let result = result.and_then(|()| easy.response_code())
+                               .and_then(|code| {
+                if code != 206 {
+                    Err(error_with_url(error_with_status(
+                        Error::new(curl_sys::CURLE_HTTP_RETURNED_ERROR),
+                        code), &self.url))
+                } else if !state.segments[i].is_complete() {
+                    let msg = "segment ended early".to_string();
+                    Err(error_with_url(error_with_extra(
+                        curl_sys::CURLE_PARTIAL_FILE, msg.into_boxed_str()),
+                        &self.url))
+                } else {
+                    Ok(())
+                }
+            });
+            if let Err(e) = result {
+                first_error = first_error.or(Some(e));
+            }
+        }
+        match first_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn segment_easy(&self, mut file: File, progress: Arc<Mutex<Segment>>)
+                    -> Result<Easy, Error> {
+        let mut easy = try!(self.easy());
+        {
+            let segment = progress.lock().unwrap();
+            let range = format!("{}-{}", segment.start + segment.done,
+                                segment.end);
+            try!(easy.range(&range));
+        }
+        // Only write data which is part of a `206 Partial Content` response,
+        // a server ignoring the range would otherwise overwrite other
+        // segments.
+        let code = Arc::new(Mutex::new(0));
+        let status = code.clone();
+        try!(easy.header_function(move |line| {
+            if let Ok(line) = str::from_utf8(line) {
+                if line.starts_with("HTTP/") {
+                    let code = line.split_whitespace().nth(1)
+                                   .and_then(|c| c.parse().ok());
+                    *status.lock().unwrap() = code.unwrap_or(0);
+                }
+            }
+            true
+        }));
+        try!(easy.write_function(move |data| {
+            if *code.lock().unwrap() != 206 {
+                return Ok(0)
+            }
+            let mut segment = progress.lock().unwrap();
+            if data.len() as u64 > segment.remaining() {
+                return Ok(0)
+            }
+            let offset = segment.start + segment.done;
+            let written = file.seek(SeekFrom::Start(offset)).and_then(|_| {
+                file.write_all(data)
+            });
+            match written {
+                Ok(()) => {
+                    segment.done += data.len() as u64;
+                    Ok(data.len())
+                }
+                Err(_) => Ok(0),
+            }
+        }));
+        Ok(easy)
+    }
+
+    fn easy(&self) -> Result<Easy, Error> {
+        let mut easy = Easy::new();
+        try!(easy.url(&self.url));
+        try!(easy.follow_location(true));
+        try!(easy.connect_timeout(self.connect_timeout));
+        Ok(easy)
+    }
+
+    fn http_error(&self, code: u32) -> io::Error {
+        let err = Error::new(curl_sys::CURLE_HTTP_RETURNED_ERROR);
+        error_with_url(error_with_status(err, code), &self.url).into()
+    }
+}
+
+impl Summary {
+    /// Returns the size of the downloaded file.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns how many segments the file was downloaded in.
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    /// Returns how many bytes had already been downloaded by a previous
+    /// attempt and were kept.
+    pub fn resumed(&self) -> u64 {
+        self.resumed
+    }
+}
+
+impl State {
+    fn new(len: u64, validator: String, segments: usize) -> State {
+        let segments = segments as u64;
+        let size = (len + segments - 1) / segments;
+        let segments = (0..segments).map(|i| i * size)
+                                    .take_while(|&start| start < len)
+                                    .map(|start| {
+            Segment {
+                start: start,
+                end: cmp::min(start + size, len) - 1,
+                done: 0,
+            }
+        }).collect();
+        State { len: len, validator: validator, segments: segments }
+    }
+
+    fn load(path: &Path) -> io::Result<State> {
+        let mut lines = BufReader::new(try!(File::open(path))).lines();
+        let header = match lines.next() {
+            Some(line) => try!(line),
+            None => return Err(invalid_data()),
+        };
+        let mut parts = header.splitn(2, ' ');
+        let len = try!(parse(parts.next()));
+        let validator = parts.next().unwrap_or("").to_string();
+        let mut segments = Vec::new();
+        for line in lines {
+            let line = try!(line);
+            let mut parts = line.split(' ');
+            let segment = Segment {
+                start: try!(parse(parts.next())),
+                end: try!(parse(parts.next())),
+                done: try!(parse(parts.next())),
+            };
+            if segment.end < segment.start || segment.end >= len ||
+               segment.done > segment.end - segment.start + 1 {
+                return Err(invalid_data())
+            }
+            segments.push(segment);
+        }
+        Ok(State { len: len, validator: validator, segments: segments })
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = try!(File::create(path));
+        try!(writeln!(file, "{} {}", self.len, self.validator));
+        for s in self.segments.iter() {
+            try!(writeln!(file, "{} {} {}", s.start, s.end, s.done));
+        }
+        Ok(())
+    }
+}
+
+impl Segment {
+    fn remaining(&self) -> u64 {
+        self.end - self.start + 1 - self.done
+    }
+
+    fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+impl Probe {
+    fn parse_header(&mut self, line: &[u8]) {
+        let line = match str::from_utf8(line) {
+            Ok(s) => s.trim(),
+            Err(_) => return,
+        };
+        // Only the headers of the last response in a chain of redirects
+        // describe the file.
+        if line.starts_with("HTTP/") {
+            *self = Probe::default();
+            return
+        }
+        let mut parts = line.splitn(2, ':');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name.trim(), value.trim()),
+            _ => return,
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            self.len = value.parse().ok();
+        } else if name.eq_ignore_ascii_case("accept-ranges") {
+            self.ranges = value.eq_ignore_ascii_case("bytes");
+        } else if name.eq_ignore_ascii_case("etag") {
+            self.validator = value.replace(|c: char| c.is_whitespace(), "");
+        } else if name.eq_ignore_ascii_case("last-modified") &&
+                  self.validator.is_empty() {
+            self.validator = value.replace(|c: char| c.is_whitespace(), "");
+        }
+    }
+}
+
+/// Sets the modification time of `file`, leaving its access time alone.
+#[cfg(unix)]
+fn set_modified(file: &File, time: SystemTime) -> io::Result<()> {
+    use std::mem;
+    use std::os::unix::prelude::*;
+    use std::time::UNIX_EPOCH;
+
+    use libc;
+
+    let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                0 => (-(d.as_secs() as i64), 0),
+                n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        }
+    };
+    unsafe {
+        let mut times: [libc::timespec; 2] = mem::zeroed();
+        times[0].tv_nsec = libc::UTIME_OMIT;
+        times[1].tv_sec = secs as libc::time_t;
+        times[1].tv_nsec = nanos as _;
+        if libc::futimens(file.as_raw_fd(), times.as_ptr()) == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn set_modified(_file: &File, _time: SystemTime) -> io::Result<()> {
+    Ok(())
+}
+
+fn state_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string())
+                       .unwrap_or_default();
+    name.push(".segments");
+    path.with_file_name(name)
+}
+
+fn parse(field: Option<&str>) -> io::Result<u64> {
+    field.and_then(|f| f.parse().ok()).ok_or_else(invalid_data)
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid download state")
+}
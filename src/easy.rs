@@ -9,17 +9,24 @@
 
 use std::cell::{RefCell, Cell};
 use std::ffi::{CString, CStr};
-use std::io::SeekFrom;
+use std::io::{self, SeekFrom};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4};
+use std::net::SocketAddrV6;
 use std::path::Path;
 use std::slice;
 use std::str;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use curl_sys;
 use libc::{self, c_long, c_int, c_char, c_void, size_t, c_double, c_ulong};
 
-use {Error, FormError};
+use {Error, FormError, Version};
+use http::Metrics;
+use multi::Socket;
 use panic;
+use profile::Profile;
 
 // TODO: checked casts everywhere
 
@@ -92,6 +99,9 @@ use panic;
 pub struct Easy {
     handle: *mut curl_sys::CURL,
     data: Box<EasyData>,
+    // Every connection keeps the close callback it was created with, cached
+    // ones even after `reset`, so each callback lives as long as the handle.
+    closesocket: Vec<Box<Box<FnMut(Socket) + Send>>>,
 }
 
 /// A scoped transfer of information which borrows an `Easy` and allows
@@ -121,7 +131,18 @@ struct EasyData {
     header: Option<Box<FnMut(&[u8]) -> bool + Send>>,
     progress: Option<Box<FnMut(f64, f64, f64, f64) -> bool + Send>>,
     ssl_ctx: Option<Box<FnMut(*mut c_void) -> Result<(), Error> + Send>>,
+    trailer: Option<Box<FnMut(&mut List) -> bool + Send>>,
+    sockopt: Option<Box<FnMut(Socket) -> SockoptResult + Send>>,
+    opensocket: Option<Box<FnMut(&OpenSocket) -> Option<Socket> + Send>>,
+    prereq: Option<Box<FnMut(&PreRequest) -> bool + Send>>,
+    hsts_read: Option<Box<FnMut() -> Option<HstsEntry> + Send>>,
+    hsts_write: Option<Box<FnMut(&HstsEntry) + Send>>,
+    log: RefCell<Option<Box<FnMut(&TransferLog) + Send>>>,
     header_list: Option<List>,
+    quote_list: Option<List>,
+    post_quote_list: Option<List>,
+    default_headers: Vec<(String, String)>,
+    metrics: Option<Arc<Metrics>>,
     form: Option<Form>,
     error_buf: RefCell<Vec<u8>>,
 }
@@ -135,6 +156,10 @@ struct TransferData<'a> {
     header: Option<Box<FnMut(&[u8]) -> bool + 'a>>,
     progress: Option<Box<FnMut(f64, f64, f64, f64) -> bool + 'a>>,
     ssl_ctx: Option<Box<FnMut(*mut c_void) -> Result<(), Error> + 'a>>,
+    trailer: Option<Box<FnMut(&mut List) -> bool + 'a>>,
+    sockopt: Option<Box<FnMut(Socket) -> SockoptResult + 'a>>,
+    opensocket: Option<Box<FnMut(&OpenSocket) -> Option<Socket> + 'a>>,
+    prereq: Option<Box<FnMut(&PreRequest) -> bool + 'a>>,
 }
 
 // libcurl guarantees that a CURL handle is fine to be transferred so long as
@@ -145,6 +170,10 @@ unsafe impl Send for Easy {}
 ///
 /// This structure is built up and then passed to the `Easy::httppost` method to
 /// be sent off with a request.
+///
+/// Forms are built with `curl_formadd` and sent through `CURLOPT_HTTPPOST`,
+/// which every version of libcurl supports, so they work the same regardless
+/// of whether the newer mime API is available.
 pub struct Form {
     head: *mut curl_sys::curl_httppost,
     tail: *mut curl_sys::curl_httppost,
@@ -251,6 +280,25 @@ pub enum SslVersion {
     __Nonexhaustive = 500,
 }
 
+/// Possible values to pass to the `ftp_create_missing_dirs` method.
+pub enum FtpCreateDirs {
+    /// Fail the transfer if a directory in its path doesn't exist.
+    None = curl_sys::CURLFTP_CREATE_DIR_NONE as isize,
+
+    /// Create each directory in the path which doesn't exist yet.
+    Create = curl_sys::CURLFTP_CREATE_DIR as isize,
+
+    /// Like `Create`, but if creating a directory fails, try to change into
+    /// it once more before failing, in case another client created it
+    /// concurrently.
+    CreateRetry = curl_sys::CURLFTP_CREATE_DIR_RETRY as isize,
+
+    /// Hidden variant to indicate that this enum should not be matched on, it
+    /// may grow over time.
+    #[doc(hidden)]
+    __Nonexhaustive = 500,
+}
+
 /// Possible return values from the `seek_function` callback.
 pub enum SeekResult {
     /// Indicates that the seek operation was a success
@@ -270,8 +318,29 @@ pub enum SeekResult {
     __Nonexhaustive = 500,
 }
 
+/// Possible return values from the `sockopt_function` callback.
+pub enum SockoptResult {
+    /// Indicates that the socket was configured successfully and libcurl
+    /// should go on to connect it.
+    Ok = curl_sys::CURL_SOCKOPT_OK as isize,
+
+    /// Indicates that configuring the socket failed, and the transfer should
+    /// fail as a result.
+    Error = curl_sys::CURL_SOCKOPT_ERROR as isize,
+
+    /// Indicates that the socket is already connected, so libcurl should skip
+    /// connecting it.
+    AlreadyConnected = curl_sys::CURL_SOCKOPT_ALREADY_CONNECTED as isize,
+
+    /// Hidden variant to indicate that this enum should not be matched on, it
+    /// may grow over time.
+    #[doc(hidden)]
+    __Nonexhaustive = 500,
+}
+
 /// Possible data chunks that can be witnessed as part of the `debug_function`
 /// callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InfoType {
     /// The data is informational text.
     Text,
@@ -356,6 +425,35 @@ pub enum NetRc {
     Required = curl_sys::CURL_NETRC_REQUIRED as isize,
 }
 
+/// A description of a socket libcurl wants to open, as passed to the callback
+/// configured with `Easy::opensocket_function`.
+pub struct OpenSocket<'a> {
+    inner: &'a curl_sys::curl_sockaddr,
+}
+
+/// The connection a request is about to be sent over, as passed to the
+/// callback configured with `Easy::prereq_function`.
+#[derive(Debug)]
+pub struct PreRequest<'a> {
+    primary_ip: &'a str,
+    primary_port: u16,
+    local_ip: &'a str,
+    local_port: u16,
+}
+
+/// A summary of a completed transfer, as passed to the callback configured
+/// with `Easy::log_function`.
+#[derive(Debug)]
+pub struct TransferLog<'a> {
+    method: Option<String>,
+    url: Option<String>,
+    response_code: u32,
+    downloaded: u64,
+    uploaded: u64,
+    total_time: Duration,
+    error: Option<&'a Error>,
+}
+
 /// Structure which stores possible authentication methods to get passed to
 /// `http_auth` and `proxy_auth`.
 #[derive(Clone, Debug)]
@@ -363,6 +461,44 @@ pub struct Auth {
     bits: c_long,
 }
 
+/// Structure which stores how the Alt-Svc cache of a handle behaves, passed
+/// to `alt_svc_ctrl`.
+#[derive(Clone, Debug)]
+pub struct AltSvcCtrl {
+    bits: c_long,
+}
+
+/// Structure which stores how the HSTS cache of a handle behaves, passed to
+/// `hsts_ctrl`.
+#[derive(Clone, Debug)]
+pub struct HstsCtrl {
+    bits: c_long,
+}
+
+/// An entry of the HSTS cache, exchanged with the callbacks configured with
+/// `hsts_read_function` and `hsts_write_function`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HstsEntry {
+    host: String,
+    include_subdomains: bool,
+    expire: Option<String>,
+}
+
+/// The size and modification time of a remote file, as returned by
+/// `Easy::stat`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemoteMeta {
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+/// A set of protocols, passed to `allowed_protocols` and
+/// `redirect_protocols` to restrict which protocols may be used.
+#[derive(Clone, Debug)]
+pub struct ProtocolSet {
+    bits: c_long,
+}
+
 impl Easy {
     /// Creates a new "easy" handle which is the core of almost all operations
     /// in libcurl.
@@ -379,12 +515,26 @@ impl Easy {
             let mut ret = Easy {
                 handle: handle,
                 data: Default::default(),
+                closesocket: Vec::new(),
             };
             default_configure(&mut ret);
             return ret
         }
     }
 
+    /// Creates a new handle configured from environment variables.
+    ///
+    /// This is the same as `Easy::new` followed by applying
+    /// `Profile::from_env`, see its documentation for the list of variables
+    /// which are consulted. An error is returned if any of them holds an
+    /// invalid value.
+    pub fn from_env() -> io::Result<Easy> {
+        let profile = try!(Profile::from_env());
+        let mut ret = Easy::new();
+        try!(profile.apply(&mut ret));
+        Ok(ret)
+    }
+
     // =========================================================================
     // Behavior options
 
@@ -437,8 +587,10 @@ impl Easy {
     ///
     /// By default this option is `false` and corresponds to `CURLOPT_NOSIGNAL`.
     /// Note that this default is **different than libcurl** as it is intended
-    /// that this library is threadsafe by default. See the [libcurl docs] for
-    /// some more information.
+    /// that this library is threadsafe by default: with signals enabled,
+    /// libcurl uses `SIGALRM` to time out name resolution, which can crash
+    /// multi-threaded programs. See the [libcurl docs] for some more
+    /// information.
     ///
     /// [libcurl docs]: https://curl.haxx.se/libcurl/c/threadsafe.html
     pub fn signal(&mut self, signal: bool) -> Result<(), Error> {
@@ -768,11 +920,242 @@ impl Easy {
         Ok(())
     }
 
+    /// Callback to provide trailing headers for a chunked upload.
+    ///
+    /// This function gets called by libcurl once the body of a request sent
+    /// with `Transfer-Encoding: chunked` has been read, and can append
+    /// headers of the form `Name: value` to the list it's passed. Those are
+    /// then sent as trailers after the last chunk, which is useful for
+    /// checksums computed while the body is streamed. Servers expect the
+    /// request to announce them with a `Trailer` header.
+    ///
+    /// The callback should return `true` to continue or `false` to abort the
+    /// transfer.
+    ///
+    /// This requires libcurl 7.64.0 or later, and is ignored for uploads which
+    /// aren't chunked. By default this option is not set and corresponds to
+    /// `CURLOPT_TRAILERFUNCTION` and `CURLOPT_TRAILERDATA`.
+    ///
+    /// Note that the lifetime bound on this function is `'static`, but that
+    /// is often too restrictive. To use stack data consider calling the
+    /// `transfer` method and then using `trailer_function` to configure a
+    /// callback that can reference stack-local data.
+    pub fn trailer_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&mut List) -> bool + Send + 'static
+    {
+        self.data.trailer = Some(Box::new(f));
+        unsafe {
+            self.set_trailer_function(easy_trailer_cb,
+                                      &*self.data as *const _ as *mut _)
+        }
+    }
+
+    unsafe fn set_trailer_function(&self,
+                                   cb: curl_sys::curl_trailer_callback,
+                                   ptr: *mut c_void) -> Result<(), Error> {
+        try!(self.setopt_ptr(curl_sys::CURLOPT_TRAILERFUNCTION,
+                             cb as *const _));
+        try!(self.setopt_ptr(curl_sys::CURLOPT_TRAILERDATA, ptr as *const _));
+        Ok(())
+    }
+
+    /// Callback to configure a socket before it's connected.
+    ///
+    /// This function gets called by libcurl right after it has created a
+    /// socket for a new connection, and before that socket is connected. It
+    /// can be used to set socket options libcurl doesn't support itself,
+    /// such as `SO_MARK`, `SO_BINDTODEVICE` or the type of service. For FTP
+    /// transfers in active mode it's also called for the accepted data
+    /// connection.
+    ///
+    /// The callback function must return `SockoptResult::Ok` to go on with
+    /// the connection, `SockoptResult::Error` to make the transfer fail with
+    /// an error for which `is_aborted_by_callback` returns `true`, or
+    /// `SockoptResult::AlreadyConnected` if it connected the socket itself.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_SOCKOPTFUNCTION` and `CURLOPT_SOCKOPTDATA`.
+    ///
+    /// Note that the lifetime bound on this function is `'static`, but that
+    /// is often too restrictive. To use stack data consider calling the
+    /// `transfer` method and then using `sockopt_function` to configure a
+    /// callback that can reference stack-local data.
+    pub fn sockopt_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(Socket) -> SockoptResult + Send + 'static
+    {
+        self.data.sockopt = Some(Box::new(f));
+        unsafe {
+            self.set_sockopt_function(easy_sockopt_cb,
+                                      &*self.data as *const _ as *mut _)
+        }
+    }
+
+    unsafe fn set_sockopt_function(&self,
+                                   cb: curl_sys::curl_sockopt_callback,
+                                   ptr: *mut c_void) -> Result<(), Error> {
+        try!(self.setopt_ptr(curl_sys::CURLOPT_SOCKOPTFUNCTION,
+                             cb as *const _));
+        try!(self.setopt_ptr(curl_sys::CURLOPT_SOCKOPTDATA, ptr as *const _));
+        Ok(())
+    }
+
+    /// Callback to create the sockets libcurl connects with.
+    ///
+    /// This function gets called by libcurl instead of `socket` whenever it
+    /// needs a socket for a new connection, and is passed the family, type,
+    /// protocol and address of the connection. It can, for example, create
+    /// and configure a socket through a broker, bind it to a particular
+    /// network, or hand out a socket which was connected beforehand. In the
+    /// latter case `sockopt_function` should return
+    /// `SockoptResult::AlreadyConnected` so libcurl doesn't connect it again.
+    ///
+    /// The callback function must return the socket to use, or `None` to
+    /// make the connection attempt fail with an error for which
+    /// `is_couldnt_connect` returns `true`.
+    ///
+    /// Sockets are closed by libcurl unless `closesocket_function` is
+    /// configured as well. By default this option is not set and corresponds
+    /// to `CURLOPT_OPENSOCKETFUNCTION` and `CURLOPT_OPENSOCKETDATA`.
+    ///
+    /// Note that the lifetime bound on this function is `'static`, but that
+    /// is often too restrictive. To use stack data consider calling the
+    /// `transfer` method and then using `opensocket_function` to configure a
+    /// callback that can reference stack-local data.
+    pub fn opensocket_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&OpenSocket) -> Option<Socket> + Send + 'static
+    {
+        self.data.opensocket = Some(Box::new(f));
+        unsafe {
+            let ptr = &*self.data as *const _ as *mut _;
+            self.set_opensocket_function(easy_opensocket_cb, ptr)
+        }
+    }
+
+    unsafe fn set_opensocket_function(&self,
+                                      cb: curl_sys::curl_opensocket_callback,
+                                      ptr: *mut c_void) -> Result<(), Error> {
+        // libcurl's own implementation can't be called from a callback, so
+        // restore it by clearing the function when there's no data.
+        let cb = if ptr.is_null() {0 as *const _} else {cb as *const _};
+        try!(self.setopt_ptr(curl_sys::CURLOPT_OPENSOCKETFUNCTION, cb));
+        try!(self.setopt_ptr(curl_sys::CURLOPT_OPENSOCKETDATA,
+                             ptr as *const _));
+        Ok(())
+    }
+
+    /// Callback to close the sockets libcurl is done with.
+    ///
+    /// This function gets called by libcurl instead of `close` for every
+    /// socket it closes, which is mostly useful in combination with
+    /// `opensocket_function` for sockets which must be released in a
+    /// particular way. The callback is responsible for closing the socket.
+    ///
+    /// Each connection sticks with the callback which was configured when it
+    /// was made, even once it's replaced or the handle is `reset`, so a
+    /// socket is always closed by the callback matching the one it was
+    /// opened with. For the same reason the callbacks are only dropped along
+    /// with the handle, and there's no variant of this function on
+    /// `Transfer`: cached connections outlive a transfer.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_CLOSESOCKETFUNCTION` and `CURLOPT_CLOSESOCKETDATA`.
+    pub fn closesocket_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(Socket) + Send + 'static
+    {
+        let mut f: Box<Box<FnMut(Socket) + Send>> = Box::new(Box::new(f));
+        let ptr = &mut *f as *mut _ as *mut c_void;
+        self.closesocket.push(f);
+        let cb = easy_closesocket_cb as curl_sys::curl_closesocket_callback;
+        try!(self.setopt_ptr(curl_sys::CURLOPT_CLOSESOCKETFUNCTION,
+                             cb as *const _));
+        self.setopt_ptr(curl_sys::CURLOPT_CLOSESOCKETDATA, ptr as *const _)
+    }
+
+    /// Callback to inspect the connection a request is sent over before
+    /// sending it.
+    ///
+    /// This function gets called by libcurl once a connection has been
+    /// established, or picked from the connection cache, and right before
+    /// each request is sent over it, including the requests made when
+    /// following redirects. It's passed the IP addresses and ports of both
+    /// ends of the connection, so it can, for example, refuse to talk to
+    /// private addresses whatever a host name resolved to. When a proxy is
+    /// used, the remote end is the proxy.
+    ///
+    /// The callback should return `true` to send the request or `false` to
+    /// abort the transfer, which then fails with an error for which
+    /// `is_aborted_by_callback` returns `true`.
+    ///
+    /// This requires libcurl 7.80.0 or later. By default this option is not
+    /// set and corresponds to `CURLOPT_PREREQFUNCTION` and
+    /// `CURLOPT_PREREQDATA`.
+    ///
+    /// Note that the lifetime bound on this function is `'static`, but that
+    /// is often too restrictive. To use stack data consider calling the
+    /// `transfer` method and then using `prereq_function` to configure a
+    /// callback that can reference stack-local data.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use curl::easy::Easy;
+    ///
+    /// let mut handle = Easy::new();
+    /// handle.url("https://example.com/").unwrap();
+    /// handle.prereq_function(|conn| {
+    ///     match conn.primary_addr() {
+    ///         Some(addr) => !addr.ip().is_loopback(),
+    ///         None => false,
+    ///     }
+    /// }).unwrap();
+    /// handle.perform().unwrap();
+    /// ```
+    pub fn prereq_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&PreRequest) -> bool + Send + 'static
+    {
+        self.data.prereq = Some(Box::new(f));
+        unsafe {
+            let ptr = &*self.data as *const _ as *mut _;
+            self.set_prereq_function(easy_prereq_cb, ptr)
+        }
+    }
+
+    unsafe fn set_prereq_function(&self,
+                                  cb: curl_sys::curl_prereq_callback,
+                                  ptr: *mut c_void) -> Result<(), Error> {
+        let cb = if ptr.is_null() {0 as *const _} else {cb as *const _};
+        try!(self.setopt_ptr(curl_sys::CURLOPT_PREREQFUNCTION, cb));
+        try!(self.setopt_ptr(curl_sys::CURLOPT_PREREQDATA, ptr as *const _));
+        Ok(())
+    }
+
+    /// Set a callback which is invoked with a summary of each transfer once
+    /// it's completed.
+    ///
+    /// Unlike the output of `verbose` and `debug_function`, which describe a
+    /// transfer while it's happening, this is called exactly once per call to
+    /// `perform`, whether the transfer succeeded or not, making it a
+    /// convenient place to log all requests made through a handle
+    /// consistently. The summary includes the transfer's method, URL, response
+    /// code, byte counts, duration and error.
+    ///
+    /// The callback is kept across transfers and `reset`, but it's not called
+    /// for transfers driven by a `Multi` handle. By default no callback is
+    /// set.
+    pub fn log_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&TransferLog) + Send + 'static
+    {
+        *self.data.log.borrow_mut() = Some(Box::new(f));
+        Ok(())
+    }
+
     /// Specify a debug callback
     ///
     /// `debug_function` replaces the standard debug function used when
     /// `verbose` is in effect. This callback receives debug information,
-    /// as specified in the type argument.
+    /// as specified in the type argument. Note that libcurl only invokes it
+    /// while `verbose` is enabled, in which case nothing is written to stderr
+    /// anymore, so it can be used to route the verbose output to a logger.
     ///
     /// By default this option is not set and corresponds to the
     /// `CURLOPT_DEBUGFUNCTION` and `CURLOPT_DEBUGDATA` options.
@@ -914,6 +1297,23 @@ impl Easy {
         self.setopt_long(curl_sys::CURLOPT_FAILONERROR, fail as c_long)
     }
 
+    /// Indicates whether the request body keeps being sent after the server
+    /// responds with an HTTP code >= 300 before the upload is complete.
+    ///
+    /// By default libcurl stops sending as soon as such a response arrives,
+    /// so a server which rejects the request early and then waits for the
+    /// rest of the body before replying, or which closes the connection if
+    /// the body isn't complete, leaves the detailed response unreadable.
+    ///
+    /// By default this option is `false` and corresponds to
+    /// `CURLOPT_KEEP_SENDING_ON_ERROR`, which requires libcurl 7.51.0 or
+    /// later.
+    pub fn keep_sending_on_error(&mut self, keep_sending: bool)
+                                 -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_KEEP_SENDING_ON_ERROR,
+                         keep_sending as c_long)
+    }
+
     // =========================================================================
     // Network options
 
@@ -941,12 +1341,44 @@ impl Easy {
         self.setopt_long(curl_sys::CURLOPT_PORT, port as c_long)
     }
 
-    // /// Indicates whether sequences of `/../` and `/./` will be squashed or not.
-    // ///
-    // /// By default this option is `false` and corresponds to
-    // /// `CURLOPT_PATH_AS_IS`.
-    // pub fn path_as_is(&mut self, as_is: bool) -> Result<(), Error> {
-    // }
+    /// Restricts the protocols this handle may use to those in `protocols`.
+    ///
+    /// Transfers of URLs using any other protocol fail with an error for
+    /// which `is_unsupported_protocol` returns `true`. This is useful if URLs
+    /// come from untrusted input, and also applies to redirects unless
+    /// `redirect_protocols` is configured as well.
+    ///
+    /// By default all protocols supported by libcurl are allowed and this
+    /// corresponds to `CURLOPT_PROTOCOLS`.
+    pub fn allowed_protocols(&mut self, protocols: &ProtocolSet)
+                             -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_PROTOCOLS, protocols.bits)
+    }
+
+    /// Restricts the protocols `follow_location` may follow redirects to.
+    ///
+    /// Redirects to URLs using any other protocol fail with an error for
+    /// which `is_unsupported_protocol` returns `true`. Protocols not allowed
+    /// by `allowed_protocols` are never followed either way.
+    ///
+    /// By default libcurl allows HTTP, HTTPS, FTP and FTPS since 7.65.2, and
+    /// all protocols except for FILE, SCP, SMB and SMBS before. This
+    /// corresponds to `CURLOPT_REDIR_PROTOCOLS`.
+    pub fn redirect_protocols(&mut self, protocols: &ProtocolSet)
+                              -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_REDIR_PROTOCOLS, protocols.bits)
+    }
+
+    /// Indicates whether sequences of `/../` and `/./` will be squashed or not.
+    ///
+    /// If enabled, the path of the URL is sent to the server exactly as it
+    /// was given instead of being normalized first.
+    ///
+    /// By default this option is `false` and corresponds to
+    /// `CURLOPT_PATH_AS_IS`.
+    pub fn path_as_is(&mut self, as_is: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_PATH_AS_IS, as_is as c_long)
+    }
 
     /// Provide the URL of a proxy to use.
     ///
@@ -1029,6 +1461,9 @@ impl Easy {
     /// Sets the timeout of how long name resolves will be kept in memory.
     ///
     /// This is distinct from DNS TTL options and is entirely speculative.
+    /// The timeout has a granularity of whole seconds, and a timeout of zero
+    /// disables the cache completely so every transfer resolves names anew.
+    /// To keep entries cached forever use `dns_cache_forever` instead.
     ///
     /// By default this option is 60s and corresponds to
     /// `CURLOPT_DNS_CACHE_TIMEOUT`.
@@ -1037,67 +1472,111 @@ impl Easy {
                          dur.as_secs() as c_long)
     }
 
+    /// Keeps name resolves cached in memory forever.
+    ///
+    /// Entries never expire from the DNS cache after this is called, which
+    /// means that transfers keep using the old addresses even if the records
+    /// of a host change. Use `dns_cache_timeout` to switch back to a limited
+    /// lifetime.
+    ///
+    /// This corresponds to setting `CURLOPT_DNS_CACHE_TIMEOUT` to -1.
+    pub fn dns_cache_forever(&mut self) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_DNS_CACHE_TIMEOUT, -1)
+    }
+
     /// Specify the preferred receive buffer size, in bytes.
     ///
     /// This is treated as a request, not an order, and the main point of this
     /// is that the write callback may get called more often with smaller
     /// chunks.
     ///
+    /// Larger buffers can improve throughput on fast links. Since libcurl
+    /// 7.53.0 sizes of up to 512KB are accepted.
+    ///
     /// By default this option is the maximum write size and corresopnds to
     /// `CURLOPT_BUFFERSIZE`.
     pub fn buffer_size(&mut self, size: usize) -> Result<(), Error> {
         self.setopt_long(curl_sys::CURLOPT_BUFFERSIZE, size as c_long)
     }
 
-    // /// Enable or disable TCP Fast Open
-    // ///
-    // /// By default this options defaults to `false` and corresponds to
-    // /// `CURLOPT_TCP_FASTOPEN`
-    // pub fn fast_open(&mut self, enable: bool) -> Result<(), Error> {
-    // }
+    /// Specify the preferred upload buffer size, in bytes.
+    ///
+    /// This is the size of the buffer filled by the read callback, so larger
+    /// sizes mean fewer and larger reads. Sizes between 16KB and 2MB are
+    /// accepted.
+    ///
+    /// By default this option is 64KB and corresponds to
+    /// `CURLOPT_UPLOAD_BUFFERSIZE`, which requires libcurl 7.62.0 or later.
+    pub fn upload_buffer_size(&mut self, size: usize) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_UPLOAD_BUFFERSIZE, size as c_long)
+    }
+
+    /// Enable or disable TCP Fast Open
+    ///
+    /// With Fast Open the first data of a request is sent along with the SYN
+    /// packet, saving a round trip when connecting to a server seen before.
+    ///
+    /// Support depends on both the operating system and the libcurl build.
+    /// Where it's missing this returns an error for which
+    /// `Error::is_not_built_in` (or `Error::is_unknown_option` for libcurl
+    /// older than 7.49.0) returns `true`, leaving the handle unchanged.
+    ///
+    /// By default this option is `false` and corresponds to
+    /// `CURLOPT_TCP_FASTOPEN`.
+    pub fn tcp_fastopen(&mut self, enable: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_TCP_FASTOPEN, enable as c_long)
+    }
 
     /// Configures whether the TCP_NODELAY option is set, or Nagle's algorithm
     /// is disabled.
     ///
     /// The purpose of Nagle's algorithm is to minimize the number of small
     /// packet's on the network, and disabling this may be less efficient in
-    /// some situations.
+    /// some situations. Latency-sensitive workloads issuing many small
+    /// requests usually want this enabled, while bulk transfers are
+    /// unaffected either way.
     ///
-    /// By default this option is `false` and corresponds to
-    /// `CURLOPT_TCP_NODELAY`.
+    /// By default this option is `true` for libcurl 7.50.2 and later, `false`
+    /// for older versions, and corresponds to `CURLOPT_TCP_NODELAY`.
     pub fn tcp_nodelay(&mut self, enable: bool) -> Result<(), Error> {
         self.setopt_long(curl_sys::CURLOPT_TCP_NODELAY, enable as c_long)
     }
 
-    // /// Configures whether TCP keepalive probes will be sent.
-    // ///
-    // /// The delay and frequency of these probes is controlled by `tcp_keepidle`
-    // /// and `tcp_keepintvl`.
-    // ///
-    // /// By default this option is `false` and corresponds to
-    // /// `CURLOPT_TCP_KEEPALIVE`.
-    // pub fn tcp_keepalive(&mut self, enable: bool) -> Result<(), Error> {
-    //     self.setopt_long(curl_sys::CURLOPT_TCP_KEEPALIVE, enable as c_long)
-    // }
+    /// Configures whether TCP keepalive probes will be sent.
+    ///
+    /// The delay and frequency of these probes is controlled by `tcp_keepidle`
+    /// and `tcp_keepintvl`.
+    ///
+    /// By default this option is `false` and corresponds to
+    /// `CURLOPT_TCP_KEEPALIVE`.
+    pub fn tcp_keepalive(&mut self, enable: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_TCP_KEEPALIVE, enable as c_long)
+    }
 
-    // /// Configures the TCP keepalive idle time wait.
-    // ///
-    // /// This is the delay, after which the connection is idle, keepalive probes
-    // /// will be sent. Not all operating systems support this.
-    // ///
-    // /// By default this corresponds to `CURLOPT_TCP_KEEPIDLE`.
-    // pub fn tcp_keepidle(&mut self, amt: Duration) -> Result<(), Error> {
-    //     self.setopt_long(curl_sys::CURLOPT_TCP_KEEPIDLE,
-    //                      amt.as_secs() as c_long)
-    // }
-    //
-    // /// Configures the delay between keepalive probes.
-    // ///
-    // /// By default this corresponds to `CURLOPT_TCP_KEEPINTVL`.
-    // pub fn tcp_keepintvl(&mut self, amt: Duration) -> Result<(), Error> {
-    //     self.setopt_long(curl_sys::CURLOPT_TCP_KEEPINTVL,
-    //                      amt.as_secs() as c_long)
-    // }
+    /// Configures the TCP keepalive idle time wait.
+    ///
+    /// This is the delay, after which the connection is idle, keepalive probes
+    /// will be sent. Not all operating systems support this. Only has an
+    /// effect if `tcp_keepalive` is enabled.
+    ///
+    /// By default this value is 60 seconds and corresponds to
+    /// `CURLOPT_TCP_KEEPIDLE`.
+    pub fn tcp_keepidle(&mut self, amt: Duration) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_TCP_KEEPIDLE,
+                         amt.as_secs() as c_long)
+    }
+
+    /// Configures the delay between keepalive probes.
+    ///
+    /// Not all operating systems support this. Only has an effect if
+    /// `tcp_keepalive` is enabled.
+    ///
+    /// By default this value is 60 seconds and corresponds to
+    /// `CURLOPT_TCP_KEEPINTVL`.
+    pub fn tcp_keepintvl(&mut self, amt: Duration) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_TCP_KEEPINTVL,
+                         amt.as_secs() as c_long)
+    }
 
     /// Configures the scope for local IPv6 addresses.
     ///
@@ -1129,6 +1608,65 @@ impl Easy {
         self.setopt_str(curl_sys::CURLOPT_PASSWORD, &pass)
     }
 
+    /// Configures protocol specific options for logging in.
+    ///
+    /// For IMAP, POP3 and SMTP this selects the SASL mechanism to
+    /// authenticate with, such as `AUTH=NTLM` for an Exchange server which
+    /// only allows that or `AUTH=XOAUTH2` to only log in with an OAuth token.
+    /// `AUTH=*` lets libcurl choose any mechanism the server offers, and for
+    /// IMAP and POP3 `AUTH=+LOGIN` and `AUTH=+APOP` select a login without
+    /// SASL. Other protocols ignore this option.
+    ///
+    /// By default this value is not set and corresponds to
+    /// `CURLOPT_LOGIN_OPTIONS`.
+    pub fn login_options(&mut self, options: &str) -> Result<(), Error> {
+        let options = try!(CString::new(options));
+        self.setopt_str(curl_sys::CURLOPT_LOGIN_OPTIONS, &options)
+    }
+
+    /// Configures the OAuth 2.0 access token to authenticate with.
+    ///
+    /// For IMAP, POP3 and SMTP this authenticates with the `XOAUTH2` or
+    /// `OAUTHBEARER` SASL mechanism, together with the username set with
+    /// `username`, if the server offers it. This is how providers such as
+    /// Gmail and Office 365 expect clients to log in instead of with a
+    /// password. Use `login_options` with `AUTH=XOAUTH2` to rule out any
+    /// other mechanism. For HTTP the token is sent in an `Authorization:
+    /// Bearer` header if `http_auth` allows `Auth::bearer`.
+    ///
+    /// By default this value is not set and corresponds to
+    /// `CURLOPT_XOAUTH2_BEARER`.
+    pub fn xoauth2_bearer(&mut self, token: &str) -> Result<(), Error> {
+        let token = try!(CString::new(token));
+        self.setopt_str(curl_sys::CURLOPT_XOAUTH2_BEARER, &token)
+    }
+
+    /// Configures whether to send the initial response of SASL authentication
+    /// along with the authentication command.
+    ///
+    /// This saves a round-trip for IMAP, POP3 and SMTP servers which support
+    /// it, as described in RFC 4954.
+    ///
+    /// By default this option is `false` and corresponds to
+    /// `CURLOPT_SASL_IR`.
+    pub fn sasl_ir(&mut self, enable: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_SASL_IR, enable as c_long)
+    }
+
+    /// Configures the authorization identity to act as with SASL `PLAIN`
+    /// authentication.
+    ///
+    /// This lets an administrator authenticated with `username` and
+    /// `password` act on behalf of another user, such as to access their
+    /// mailbox.
+    ///
+    /// By default this value is not set and corresponds to
+    /// `CURLOPT_SASL_AUTHZID`, which requires libcurl 7.66.0 or later.
+    pub fn sasl_authzid(&mut self, authzid: &str) -> Result<(), Error> {
+        let authzid = try!(CString::new(authzid));
+        self.setopt_str(curl_sys::CURLOPT_SASL_AUTHZID, &authzid)
+    }
+
     /// Set HTTP server authentication methods to try
     ///
     /// If more than one method is set, libcurl will first query the site to see
@@ -1364,6 +1902,63 @@ impl Easy {
         self.setopt_ptr(curl_sys::CURLOPT_HTTPHEADER, ptr as *const _)
     }
 
+    /// Adds a header which every `http::Request` performed on this handle
+    /// sends.
+    ///
+    /// This is meant for headers such as `Authorization` or `X-Api-Key`
+    /// which every request to an API needs. A request which adds a header of
+    /// the same name itself sends its own value instead, and one which calls
+    /// `unset_header` for the name doesn't send it at all. Names are compared
+    /// case-insensitively. Adding the same name again here sends the header
+    /// multiple times.
+    ///
+    /// Only `http::Request` looks at these headers, so they aren't sent by a
+    /// plain `perform`, which uses the list configured with `http_headers`.
+    ///
+    /// By default there are no default headers.
+    pub fn default_header(&mut self, name: &str, value: &str) {
+        self.data.default_headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Removes all headers added with `default_header`.
+    pub fn clear_default_headers(&mut self) {
+        self.data.default_headers.clear();
+    }
+
+    /// Returns the names and values of the headers added with
+    /// `default_header`, in the order they were added.
+    pub fn default_headers(&self) -> &[(String, String)] {
+        &self.data.default_headers
+    }
+
+    /// Installs hooks which are told about every `http::Request` performed
+    /// on this handle.
+    ///
+    /// The hooks see each request start, the bytes it sends and receives as
+    /// the transfer goes on, and its outcome once it's done. As they're
+    /// invoked by the handle itself, they also observe requests made through
+    /// any `http::Transport` which ends up performing them on this handle.
+    /// Reporting progress requires `CURLOPT_NOPROGRESS` to be off, so each
+    /// request performed while hooks are installed turns progress reporting
+    /// on.
+    ///
+    /// Only `http::Request` invokes these hooks, a plain `perform` doesn't.
+    ///
+    /// By default no hooks are installed.
+    pub fn metrics(&mut self, metrics: Arc<Metrics>) {
+        self.data.metrics = Some(metrics);
+    }
+
+    /// Removes the hooks installed with `metrics`.
+    pub fn clear_metrics(&mut self) {
+        self.data.metrics = None;
+    }
+
+    /// Returns the hooks installed with `metrics`, if any.
+    pub fn installed_metrics(&self) -> Option<&Arc<Metrics>> {
+        self.data.metrics.as_ref()
+    }
+
     // /// Add some headers to send to the HTTP proxy.
     // ///
     // /// This function is essentially the same as `http_headers`.
@@ -1473,6 +2068,96 @@ impl Easy {
         self.setopt_str(curl_sys::CURLOPT_COOKIELIST, &cookie)
     }
 
+    /// Configures the Alt-Svc cache.
+    ///
+    /// Once enabled, libcurl remembers the alternative services which HTTPS
+    /// servers advertise with the `Alt-Svc` header, and connects to them for
+    /// subsequent requests to the same origin if they use one of the allowed
+    /// HTTP versions. This is how servers usually announce HTTP/3 support.
+    ///
+    /// By default Alt-Svc is disabled and this option corresponds to
+    /// `CURLOPT_ALTSVC_CTRL`, which requires libcurl 7.64.1 or later built
+    /// with Alt-Svc support, see `Version::feature_altsvc`.
+    pub fn alt_svc_ctrl(&mut self, ctrl: &AltSvcCtrl) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_ALTSVC_CTRL, ctrl.bits)
+    }
+
+    /// Set the file the Alt-Svc cache is read from and saved to.
+    ///
+    /// The file is read right away and written back when this handle is
+    /// dropped, unless `AltSvcCtrl::read_only_file` is set, so that the
+    /// alternative services learned are remembered across runs. This doesn't
+    /// enable Alt-Svc by itself, which is done with `alt_svc_ctrl`.
+    ///
+    /// By default this option is not set and corresponds to `CURLOPT_ALTSVC`.
+    pub fn alt_svc_file<P: AsRef<Path>>(&mut self, file: P)
+                                        -> Result<(), Error> {
+        self.setopt_path(curl_sys::CURLOPT_ALTSVC, file.as_ref())
+    }
+
+    /// Configures the HSTS (HTTP Strict Transport Security) cache.
+    ///
+    /// Once enabled, libcurl remembers the hosts which sent a
+    /// `Strict-Transport-Security` header over HTTPS and transparently
+    /// upgrades `http://` URLs for them to `https://` until the entry
+    /// expires.
+    ///
+    /// By default HSTS is disabled and this option corresponds to
+    /// `CURLOPT_HSTS_CTRL`, which requires libcurl 7.74.0 or later.
+    pub fn hsts_ctrl(&mut self, ctrl: &HstsCtrl) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_HSTS_CTRL, ctrl.bits)
+    }
+
+    /// Set the file the HSTS cache is read from and saved to.
+    ///
+    /// The file is read right away and written back when this handle is
+    /// dropped, unless `HstsCtrl::read_only_file` is set. This doesn't enable
+    /// HSTS by itself, which is done with `hsts_ctrl`.
+    ///
+    /// By default this option is not set and corresponds to `CURLOPT_HSTS`.
+    pub fn hsts_file<P: AsRef<Path>>(&mut self, file: P) -> Result<(), Error> {
+        self.setopt_path(curl_sys::CURLOPT_HSTS, file.as_ref())
+    }
+
+    /// Set a callback which supplies entries for the HSTS cache.
+    ///
+    /// The callback is invoked repeatedly as each transfer starts, returning
+    /// one entry at a time to add to the cache, and `None` once there are no
+    /// more. This allows keeping the cache somewhere other than in a file.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_HSTSREADFUNCTION` and `CURLOPT_HSTSREADDATA`.
+    pub fn hsts_read_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut() -> Option<HstsEntry> + Send + 'static
+    {
+        self.data.hsts_read = Some(Box::new(f));
+        let ptr = &*self.data as *const _ as *const c_void;
+        let cb: curl_sys::curl_hstsread_callback = hsts_read_cb;
+        try!(self.setopt_ptr(curl_sys::CURLOPT_HSTSREADFUNCTION,
+                             cb as *const _));
+        self.setopt_ptr(curl_sys::CURLOPT_HSTSREADDATA, ptr as *const _)
+    }
+
+    /// Set a callback which receives the entries of the HSTS cache when it's
+    /// saved.
+    ///
+    /// The callback is invoked once for each entry when this handle is
+    /// dropped, which allows persisting the cache somewhere other than in a
+    /// file.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_HSTSWRITEFUNCTION` and `CURLOPT_HSTSWRITEDATA`.
+    pub fn hsts_write_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&HstsEntry) + Send + 'static
+    {
+        self.data.hsts_write = Some(Box::new(f));
+        let ptr = &*self.data as *const _ as *const c_void;
+        let cb: curl_sys::curl_hstswrite_callback = hsts_write_cb;
+        try!(self.setopt_ptr(curl_sys::CURLOPT_HSTSWRITEFUNCTION,
+                             cb as *const _));
+        self.setopt_ptr(curl_sys::CURLOPT_HSTSWRITEDATA, ptr as *const _)
+    }
+
     /// Ask for a HTTP GET request.
     ///
     /// By default this option is `false` and corresponds to `CURLOPT_HTTPGET`.
@@ -1498,6 +2183,10 @@ impl Easy {
 
     /// Enable or disable HTTP content decoding.
     ///
+    /// Content is only decoded if an encoding was requested through
+    /// `accept_encoding`. If this is disabled the body is passed to the write
+    /// callback still compressed, exactly as the server sent it.
+    ///
     /// By default this option is `true` and corresponds to
     /// `CURLOPT_HTTP_CONTENT_DECODING`.
     pub fn http_content_decoding(&mut self, enable: bool) -> Result<(), Error> {
@@ -1507,6 +2196,9 @@ impl Easy {
 
     /// Enable or disable HTTP transfer decoding.
     ///
+    /// If this is disabled the body of a chunked response is passed to the
+    /// write callback including the chunk framing.
+    ///
     /// By default this option is `true` and corresponds to
     /// `CURLOPT_HTTP_TRANSFER_DECODING`.
     pub fn http_transfer_decoding(&mut self, enable: bool) -> Result<(), Error> {
@@ -1514,6 +2206,19 @@ impl Easy {
                          enable as c_long)
     }
 
+    /// Configures whether HTTP/0.9 responses are accepted.
+    ///
+    /// An HTTP/0.9 response has no status line or headers, the body simply
+    /// starts right away and lasts until the connection is closed. If such
+    /// responses aren't allowed the transfer fails instead.
+    ///
+    /// By default this option is `false` since libcurl 7.66.0 (and `true`
+    /// before) and corresponds to `CURLOPT_HTTP09_ALLOWED`, which requires
+    /// libcurl 7.64.0 or later.
+    pub fn http_09_allowed(&mut self, allow: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_HTTP09_ALLOWED, allow as c_long)
+    }
+
     // /// Timeout for the Expect: 100-continue response
     // ///
     // /// By default this option is 1s and corresponds to
@@ -1523,33 +2228,155 @@ impl Easy {
     //                      enable as c_long)
     // }
 
-    // /// Wait for pipelining/multiplexing.
-    // ///
-    // /// Tells libcurl to prefer to wait for a connection to confirm or deny that
-    // /// it can do pipelining or multiplexing before continuing.
-    // ///
-    // /// When about to perform a new transfer that allows pipelining or
-    // /// multiplexing, libcurl will check for existing connections to re-use and
-    // /// pipeline on. If no such connection exists it will immediately continue
-    // /// and create a fresh new connection to use.
-    // ///
-    // /// By setting this option to `true` - having `pipeline` enabled for the
-    // /// multi handle this transfer is associated with - libcurl will instead
-    // /// wait for the connection to reveal if it is possible to
-    // /// pipeline/multiplex on before it continues. This enables libcurl to much
-    // /// better keep the number of connections to a minimum when using pipelining
-    // /// or multiplexing protocols.
-    // ///
-    // /// The effect thus becomes that with this option set, libcurl prefers to
-    // /// wait and re-use an existing connection for pipelining rather than the
-    // /// opposite: prefer to open a new connection rather than waiting.
-    // ///
-    // /// The waiting time is as long as it takes for the connection to get up and
-    // /// for libcurl to get the necessary response back that informs it about its
-    // /// protocol and support level.
-    // pub fn http_pipewait(&mut self, enable: bool) -> Result<(), Error> {
-    // }
+    /// Wait for multiplexing.
+    ///
+    /// Tells libcurl to prefer to wait for a connection to confirm or deny that
+    /// it can do multiplexing before continuing.
+    ///
+    /// When about to perform a new transfer that allows multiplexing, libcurl
+    /// will check for existing connections to re-use and multiplex on. If no
+    /// such connection exists it will immediately continue and create a fresh
+    /// new connection to use.
+    ///
+    /// By setting this option to `true` - having multiplexing enabled with
+    /// `Multi::pipelining` for the multi handle this transfer is associated
+    /// with - libcurl will instead wait for the connection to reveal if it is
+    /// possible to multiplex on before it continues. Many transfers to the
+    /// same host started at once then share a single HTTP/2 connection rather
+    /// than each opening its own.
+    ///
+    /// The waiting time is as long as it takes for the connection to get up and
+    /// for libcurl to get the necessary response back that informs it about its
+    /// protocol and support level.
+    ///
+    /// By default this option is `false` and corresponds to
+    /// `CURLOPT_PIPEWAIT`.
+    pub fn http_pipewait(&mut self, wait: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_PIPEWAIT, wait as c_long)
+    }
+
+
+    // =========================================================================
+    // FTP Options
+
+    /// Makes FTP transfers use active mode, in which the server connects back
+    /// to the client for each transfer.
+    ///
+    /// `spec` names the address the server should connect to. It can be an
+    /// IP address, a host name, a network interface name (under Unix) or `-`
+    /// for the address of the control connection, optionally followed by
+    /// `:` and a port or range of ports to listen on, such as `-:32000-32100`.
+    /// Active mode is sometimes the only one which works with a server whose
+    /// passive mode is broken by a NAT in front of it.
+    ///
+    /// By default transfers use passive mode and this corresponds to
+    /// `CURLOPT_FTPPORT`.
+    pub fn ftp_port(&mut self, spec: &str) -> Result<(), Error> {
+        let spec = try!(CString::new(spec));
+        self.setopt_str(curl_sys::CURLOPT_FTPPORT, &spec)
+    }
+
+    /// Configures whether to try the `EPSV` command for passive FTP
+    /// transfers before falling back to `PASV`.
+    ///
+    /// Some servers or firewalls in front of them mishandle `EPSV`, in which
+    /// case disabling it makes libcurl use `PASV` right away. This has no
+    /// effect in active mode.
+    ///
+    /// By default this option is `true` and corresponds to
+    /// `CURLOPT_FTP_USE_EPSV`.
+    pub fn ftp_use_epsv(&mut self, enable: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_FTP_USE_EPSV, enable as c_long)
+    }
+
+    /// Configures whether to try the `EPRT` command for active FTP transfers
+    /// before falling back to `PORT`.
+    ///
+    /// This has no effect in passive mode, see `ftp_port` for enabling
+    /// active mode.
+    ///
+    /// By default this option is `true` and corresponds to
+    /// `CURLOPT_FTP_USE_EPRT`.
+    pub fn ftp_use_eprt(&mut self, enable: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_FTP_USE_EPRT, enable as c_long)
+    }
+
+    /// Configures whether to create the directories in the path of an upload
+    /// which don't exist on the server yet.
+    ///
+    /// With this, uploading to `ftp://host/a/b/file` creates `a` and `a/b`
+    /// as needed rather than failing the transfer. This applies to FTP as
+    /// well as SFTP, for which only `None` and `Create` make a difference.
+    ///
+    /// By default this option is `FtpCreateDirs::None` and corresponds to
+    /// `CURLOPT_FTP_CREATE_MISSING_DIRS`.
+    pub fn ftp_create_missing_dirs(&mut self, create: FtpCreateDirs)
+                                   -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_FTP_CREATE_MISSING_DIRS,
+                         create as c_long)
+    }
+
+    /// Configures whether an FTP upload appends to the remote file.
+    ///
+    /// When enabled the upload is sent with `APPE` rather than `STOR`, so an
+    /// existing file is extended instead of overwritten, and created if it
+    /// doesn't exist yet.
+    ///
+    /// By default this option is `false` and corresponds to `CURLOPT_APPEND`.
+    pub fn append(&mut self, append: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_APPEND, append as c_long)
+    }
+
+    /// Specifies commands to send to the server before the transfer.
+    ///
+    /// Each entry of the list is sent as is over the control connection of
+    /// an FTP transfer, once logged in and before the transfer starts. With
+    /// SFTP, the entries are the commands understood by the sftp client
+    /// instead, such as `rename source target`, `rm file` and `mkdir dir`.
+    /// Any command failing aborts the transfer with a `QuoteError`, unless
+    /// its entry is prefixed with an asterisk (`*`).
+    ///
+    /// By default this option is not set and corresponds to `CURLOPT_QUOTE`.
+    pub fn quote(&mut self, list: List) -> Result<(), Error> {
+        let ptr = list.raw;
+        self.data.quote_list = Some(list);
+        self.setopt_ptr(curl_sys::CURLOPT_QUOTE, ptr as *const _)
+    }
 
+    /// Specifies commands to send to the server after a successful transfer.
+    ///
+    /// The list is handled like the one passed to `quote`, but its commands
+    /// only run once the transfer completed. Renaming a file which was just
+    /// uploaded under a temporary name here means it only ever shows up on
+    /// the server complete.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_POSTQUOTE`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    /// use curl::easy::{Easy, List};
+    ///
+    /// let mut data = "report contents".as_bytes();
+    /// let mut rename = List::new();
+    /// rename.append("RNFR report.csv.part").unwrap();
+    /// rename.append("RNTO report.csv").unwrap();
+    ///
+    /// let mut handle = Easy::new();
+    /// handle.url("ftp://example.com/upload/report.csv.part").unwrap();
+    /// handle.upload(true).unwrap();
+    /// handle.post_quote(rename).unwrap();
+    /// let mut transfer = handle.transfer();
+    /// transfer.read_function(|buf| Ok(data.read(buf).unwrap_or(0))).unwrap();
+    /// transfer.perform().unwrap();
+    /// ```
+    pub fn post_quote(&mut self, list: List) -> Result<(), Error> {
+        let ptr = list.raw;
+        self.data.post_quote_list = Some(list);
+        self.setopt_ptr(curl_sys::CURLOPT_POSTQUOTE, ptr as *const _)
+    }
 
     // =========================================================================
     // Protocol Options
@@ -1570,6 +2397,11 @@ impl Easy {
     ///
     /// Specify the offset in bytes you want the transfer to start from.
     ///
+    /// For uploads libcurl skips that many bytes of the data to send, through
+    /// the seek callback if one is set, and has the server continue at the
+    /// offset: with `APPE` for FTP and a `Content-Range` header for HTTP. The
+    /// `upload::ResumableUpload` helper finds the offset to use.
+    ///
     /// By default this option is 0 and corresponds to
     /// `CURLOPT_RESUME_FROM_LARGE`.
     pub fn resume_from(&mut self, from: u64) -> Result<(), Error> {
@@ -1684,8 +2516,12 @@ impl Easy {
     /// `low_speed_time` or using `progress_function` to implement your own
     /// timeout logic.
     ///
-    /// By default this option is not set and corresponds to
-    /// `CURLOPT_TIMEOUT_MS`.
+    /// A timeout of zero means there is no limit, which lifts a timeout set
+    /// before, for example by a `Profile`, for a single long transfer such as
+    /// a large upload or a long-polling request.
+    ///
+    /// By default this option is not set, so a transfer may take as long as
+    /// it needs, and corresponds to `CURLOPT_TIMEOUT_MS`.
     pub fn timeout(&mut self, timeout: Duration) -> Result<(), Error> {
         // TODO: checked arithmetic and casts
         // TODO: use CURLOPT_TIMEOUT if the timeout is too great
@@ -1790,6 +2626,49 @@ impl Easy {
         self.setopt_long(curl_sys::CURLOPT_FORBID_REUSE, enable as c_long)
     }
 
+    /// Limits how long a cached connection may sit idle and still be reused.
+    ///
+    /// A connection in the cache which hasn't been used for longer than this
+    /// is closed instead of picked for the next transfer. The limit has a
+    /// resolution of seconds.
+    ///
+    /// By default this value is 118 seconds and corresponds to
+    /// `CURLOPT_MAXAGE_CONN`, which requires libcurl 7.65.0 or later.
+    pub fn maxage_conn(&mut self, max_age: Duration) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_MAXAGE_CONN,
+                         max_age.as_secs() as c_long)
+    }
+
+    /// Limits how long after it was created a connection may be reused.
+    ///
+    /// Unlike `maxage_conn` this applies however recently the connection was
+    /// used, which keeps a busy connection from staying pinned to a server
+    /// that is being drained, such as behind a load balancer during a deploy.
+    /// A transfer already using the connection is allowed to complete. The
+    /// limit has a resolution of seconds, and zero disables it.
+    ///
+    /// By default this value is zero and corresponds to
+    /// `CURLOPT_MAXLIFETIME_CONN`, which requires libcurl 7.80.0 or later.
+    pub fn maxlifetime_conn(&mut self, max_life: Duration)
+                            -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_MAXLIFETIME_CONN,
+                         max_life.as_secs() as c_long)
+    }
+
+    /// Sets how often `upkeep` does work to keep connections alive.
+    ///
+    /// Calls to `upkeep` in between do nothing, so it can be invoked as often
+    /// as is convenient.
+    ///
+    /// By default this value is 60 seconds and corresponds to
+    /// `CURLOPT_UPKEEP_INTERVAL_MS`, which requires libcurl 7.62.0 or later.
+    pub fn upkeep_interval(&mut self, interval: Duration)
+                           -> Result<(), Error> {
+        let ms = interval.as_secs() * 1000 +
+                 (interval.subsec_nanos() / 1_000_000) as u64;
+        self.setopt_long(curl_sys::CURLOPT_UPKEEP_INTERVAL_MS, ms as c_long)
+    }
+
     /// Timeout for the connect phase
     ///
     /// This is the maximum time that you allow the connection phase to the
@@ -1804,6 +2683,24 @@ impl Easy {
         self.setopt_long(curl_sys::CURLOPT_CONNECTTIMEOUT_MS, ms as c_long)
     }
 
+    /// Head start for IPv6 in the Happy Eyeballs algorithm.
+    ///
+    /// When connecting to a host that resolves to both IPv6 and IPv4
+    /// addresses, libcurl first tries IPv6 and only starts racing IPv4
+    /// connection attempts after this much time has passed. Lowering this
+    /// helps on networks where IPv6 is advertised but broken, while raising it
+    /// makes IPv6 more likely to win.
+    ///
+    /// By default this value is 200 milliseconds and corresponds to
+    /// `CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS`.
+    pub fn happy_eyeballs_timeout(&mut self, timeout: Duration)
+                                  -> Result<(), Error> {
+        let ms = timeout.as_secs() * 1000 +
+                 (timeout.subsec_nanos() / 1_000_000) as u64;
+        self.setopt_long(curl_sys::CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS,
+                         ms as c_long)
+    }
+
     /// Specify which IP protocol version to use
     ///
     /// Allows an application to select what kind of IP addresses to use when
@@ -1829,59 +2726,122 @@ impl Easy {
         self.setopt_long(curl_sys::CURLOPT_CONNECT_ONLY, enable as c_long)
     }
 
-    // /// Set interface to speak DNS over.
-    // ///
-    // /// Set the name of the network interface that the DNS resolver should bind
-    // /// to. This must be an interface name (not an address).
-    // ///
-    // /// By default this option is not set and corresponds to
-    // /// `CURLOPT_DNS_INTERFACE`.
-    // pub fn dns_interface(&mut self, interface: &str) -> Result<(), Error> {
-    //     let interface = try!(CString::new(interface));
-    //     self.setopt_str(curl_sys::CURLOPT_DNS_INTERFACE, &interface)
-    // }
-    //
-    // /// IPv4 address to bind DNS resolves to
-    // ///
-    // /// Set the local IPv4 address that the resolver should bind to. The
-    // /// argument should be of type char * and contain a single numerical IPv4
-    // /// address as a string.
-    // ///
-    // /// By default this option is not set and corresponds to
-    // /// `CURLOPT_DNS_LOCAL_IP4`.
-    // pub fn dns_local_ip4(&mut self, ip: &str) -> Result<(), Error> {
-    //     let ip = try!(CString::new(ip));
-    //     self.setopt_str(curl_sys::CURLOPT_DNS_LOCAL_IP4, &ip)
-    // }
-    //
-    // /// IPv6 address to bind DNS resolves to
-    // ///
-    // /// Set the local IPv6 address that the resolver should bind to. The
-    // /// argument should be of type char * and contain a single numerical IPv6
-    // /// address as a string.
-    // ///
-    // /// By default this option is not set and corresponds to
-    // /// `CURLOPT_DNS_LOCAL_IP6`.
-    // pub fn dns_local_ip6(&mut self, ip: &str) -> Result<(), Error> {
-    //     let ip = try!(CString::new(ip));
-    //     self.setopt_str(curl_sys::CURLOPT_DNS_LOCAL_IP6, &ip)
-    // }
-    //
-    // /// Set preferred DNS servers.
-    // ///
-    // /// Provides a list of DNS servers to be used instead of the system default.
-    // /// The format of the dns servers option is:
-    // ///
-    // /// ```text
-    // /// host[:port],[host[:port]]...
-    // /// ```
-    // ///
-    // /// By default this option is not set and corresponds to
-    // /// `CURLOPT_DNS_SERVERS`.
-    // pub fn dns_servers(&mut self, servers: &str) -> Result<(), Error> {
-    //     let servers = try!(CString::new(servers));
-    //     self.setopt_str(curl_sys::CURLOPT_DNS_SERVERS, &servers)
-    // }
+    /// Set interface to speak DNS over.
+    ///
+    /// Set the name of the network interface that the DNS resolver should bind
+    /// to. This must be an interface name (not an address).
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_DNS_INTERFACE`.
+    pub fn dns_interface(&mut self, interface: &str) -> Result<(), Error> {
+        let interface = try!(CString::new(interface));
+        self.setopt_str(curl_sys::CURLOPT_DNS_INTERFACE, &interface)
+    }
+
+    /// IPv4 address to bind DNS resolves to
+    ///
+    /// Set the local IPv4 address that the resolver should bind to. The
+    /// argument should be of type char * and contain a single numerical IPv4
+    /// address as a string.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_DNS_LOCAL_IP4`.
+    pub fn dns_local_ip4(&mut self, ip: &str) -> Result<(), Error> {
+        let ip = try!(CString::new(ip));
+        self.setopt_str(curl_sys::CURLOPT_DNS_LOCAL_IP4, &ip)
+    }
+
+    /// IPv6 address to bind DNS resolves to
+    ///
+    /// Set the local IPv6 address that the resolver should bind to. The
+    /// argument should be of type char * and contain a single numerical IPv6
+    /// address as a string.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_DNS_LOCAL_IP6`.
+    pub fn dns_local_ip6(&mut self, ip: &str) -> Result<(), Error> {
+        let ip = try!(CString::new(ip));
+        self.setopt_str(curl_sys::CURLOPT_DNS_LOCAL_IP6, &ip)
+    }
+
+    /// Set preferred DNS servers.
+    ///
+    /// Provides a list of DNS servers to be used instead of the system default.
+    /// The format of the dns servers option is:
+    ///
+    /// ```text
+    /// host[:port],[host[:port]]...
+    /// ```
+    ///
+    /// This option, like the other `dns_*` options, requires that libcurl was
+    /// built with the c-ares resolver. If it wasn't then an error is returned
+    /// for which `is_not_built_in` (or `is_unknown_option` on newer versions
+    /// of libcurl) returns `true`.
+    ///
+    /// By default this option is not set and corresponds to
+    /// `CURLOPT_DNS_SERVERS`.
+    pub fn dns_servers(&mut self, servers: &str) -> Result<(), Error> {
+        let servers = try!(CString::new(servers));
+        self.setopt_str(curl_sys::CURLOPT_DNS_SERVERS, &servers)
+    }
+
+    /// Provide the DNS-over-HTTPS URL.
+    ///
+    /// The parameter must be a URL-encoded string pointing to a DoH server
+    /// using the https scheme, for example `https://dns.example.com/dns-query`.
+    /// When set, host names are resolved with DoH requests sent to this URL
+    /// instead of with the native resolver. Passing `None` disables DoH again.
+    ///
+    /// The DoH requests themselves are made with the TLS settings configured
+    /// with `doh_ssl_verify_peer` and friends, not the ones used for the main
+    /// transfer.
+    ///
+    /// By default this option is not set and corresponds to `CURLOPT_DOH_URL`.
+    pub fn doh_url(&mut self, url: Option<&str>) -> Result<(), Error> {
+        match url {
+            Some(url) => {
+                let url = try!(CString::new(url));
+                self.setopt_str(curl_sys::CURLOPT_DOH_URL, &url)
+            }
+            None => self.setopt_ptr(curl_sys::CURLOPT_DOH_URL, 0 as *const _),
+        }
+    }
+
+    /// Verify the DoH server's SSL certificate.
+    ///
+    /// This is the same as `ssl_verify_peer` but applies only to the
+    /// DNS-over-HTTPS requests made to the server configured with `doh_url`.
+    ///
+    /// By default this option is set to `true` and corresponds to
+    /// `CURLOPT_DOH_SSL_VERIFYPEER`.
+    pub fn doh_ssl_verify_peer(&mut self, verify: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_DOH_SSL_VERIFYPEER, verify as c_long)
+    }
+
+    /// Verify the DoH server's certificate name against its host name.
+    ///
+    /// This is the same as `ssl_verify_host` but applies only to the
+    /// DNS-over-HTTPS requests made to the server configured with `doh_url`.
+    ///
+    /// By default this option is set to `true` and corresponds to
+    /// `CURLOPT_DOH_SSL_VERIFYHOST`.
+    pub fn doh_ssl_verify_host(&mut self, verify: bool) -> Result<(), Error> {
+        let val = if verify {2} else {0};
+        self.setopt_long(curl_sys::CURLOPT_DOH_SSL_VERIFYHOST, val)
+    }
+
+    /// Verify the status of the DoH server's certificate.
+    ///
+    /// This option determines whether libcurl verifies the status of the DoH
+    /// server's certificate using the "Certificate Status Request" TLS
+    /// extension (aka. OCSP stapling).
+    ///
+    /// By default this option is set to `false` and corresponds to
+    /// `CURLOPT_DOH_SSL_VERIFYSTATUS`.
+    pub fn doh_ssl_verify_status(&mut self, verify: bool) -> Result<(), Error> {
+        self.setopt_long(curl_sys::CURLOPT_DOH_SSL_VERIFYSTATUS,
+                         verify as c_long)
+    }
 
     // =========================================================================
     // SSL/Security Options
@@ -2000,9 +2960,24 @@ impl Easy {
 
     /// Set preferred HTTP version.
     ///
+    /// An error for which `is_not_built_in` returns `true` is returned if one
+    /// of the HTTP/2 versions is requested but the linked libcurl doesn't
+    /// support HTTP/2, see `Version::feature_http2`.
+    ///
     /// By default this option is not set and corresponds to
     /// `CURLOPT_HTTP_VERSION`.
     pub fn http_version(&mut self, version: HttpVersion) -> Result<(), Error> {
+        let http2 = match version {
+            HttpVersion::V2 |
+            HttpVersion::V2TLS |
+            HttpVersion::V2PriorKnowledge => true,
+            _ => false,
+        };
+        if http2 && !Version::get().feature_http2() {
+            let msg = "HTTP/2 isn't supported by the linked libcurl";
+            return Err(::error::error_with_extra(curl_sys::CURLE_NOT_BUILT_IN,
+                                                 msg.into()))
+        }
         self.setopt_long(curl_sys::CURLOPT_HTTP_VERSION, version as c_long)
     }
 
@@ -2295,8 +3270,8 @@ impl Easy {
     /// and the time of the document is unknown.
     ///
     /// Note that you must tell the server to collect this information before
-    /// the transfer is made, by using the `filetime` method to
-    /// or you will unconditionally get a `None` back.
+    /// the transfer is made, by using the `fetch_filetime` method, or you will
+    /// unconditionally get a `None` back. `stat` does both in one go.
     ///
     /// This corresponds to `CURLINFO_FILETIME` and may return an error if the
     /// option is not supported
@@ -2310,6 +3285,19 @@ impl Easy {
         })
     }
 
+    /// Get whether the time condition of the previous transfer was unmet
+    ///
+    /// Returns `true` if the condition configured with `time_condition` and
+    /// `time_value` prevented the transfer, for example because a document
+    /// wasn't modified since the given time. For HTTP this is also the case
+    /// when the server answered with `304 Not Modified`.
+    ///
+    /// Corresponds to `CURLINFO_CONDITION_UNMET` and may return an error if
+    /// the option isn't supported.
+    pub fn condition_unmet(&mut self) -> Result<bool, Error> {
+        self.getopt_long(curl_sys::CURLINFO_CONDITION_UNMET).map(|r| r != 0)
+    }
+
     /// Get total time of previous transfer
     ///
     /// Returns the total time for the previous transfer, 
@@ -2454,6 +3442,85 @@ impl Easy {
         self.getopt_long(curl_sys::CURLINFO_REQUEST_SIZE).map(|c| c as u64)
     }
 
+    /// Get the number of bytes downloaded.
+    ///
+    /// This counts the body data of the last transfer, not including headers.
+    ///
+    /// Corresponds to `CURLINFO_SIZE_DOWNLOAD` and may return an error if the
+    /// option isn't supported.
+    pub fn download_size(&mut self) -> Result<u64, Error> {
+        self.getopt_double(curl_sys::CURLINFO_SIZE_DOWNLOAD).map(|c| c as u64)
+    }
+
+    /// Get the number of bytes uploaded.
+    ///
+    /// Corresponds to `CURLINFO_SIZE_UPLOAD` and may return an error if the
+    /// option isn't supported.
+    pub fn upload_size(&mut self) -> Result<u64, Error> {
+        self.getopt_double(curl_sys::CURLINFO_SIZE_UPLOAD).map(|c| c as u64)
+    }
+
+    /// Get the content length of the download, as announced by the server.
+    ///
+    /// This is the value of the `Content-Length` header of the last
+    /// response, or `None` if the length isn't known.
+    ///
+    /// Corresponds to `CURLINFO_CONTENT_LENGTH_DOWNLOAD` and may return an
+    /// error if the option isn't supported.
+    pub fn content_length_download(&mut self) -> Result<Option<u64>, Error> {
+        self.getopt_double(curl_sys::CURLINFO_CONTENT_LENGTH_DOWNLOAD)
+            .map(|c| if c < 0.0 { None } else { Some(c as u64) })
+    }
+
+    /// Get the specified size of the upload, or `None` if it isn't known.
+    ///
+    /// Corresponds to `CURLINFO_CONTENT_LENGTH_UPLOAD` and may return an
+    /// error if the option isn't supported.
+    pub fn content_length_upload(&mut self) -> Result<Option<u64>, Error> {
+        self.getopt_double(curl_sys::CURLINFO_CONTENT_LENGTH_UPLOAD)
+            .map(|c| if c < 0.0 { None } else { Some(c as u64) })
+    }
+
+    /// Get the average download speed, in bytes per second.
+    ///
+    /// This is measured over the complete download of the last transfer.
+    ///
+    /// Corresponds to `CURLINFO_SPEED_DOWNLOAD` and may return an error if the
+    /// option isn't supported.
+    pub fn download_speed(&mut self) -> Result<f64, Error> {
+        self.getopt_double(curl_sys::CURLINFO_SPEED_DOWNLOAD)
+    }
+
+    /// Get the average upload speed, in bytes per second.
+    ///
+    /// This is measured over the complete upload of the last transfer.
+    ///
+    /// Corresponds to `CURLINFO_SPEED_UPLOAD` and may return an error if the
+    /// option isn't supported.
+    pub fn upload_speed(&mut self) -> Result<f64, Error> {
+        self.getopt_double(curl_sys::CURLINFO_SPEED_UPLOAD)
+    }
+
+    /// Get the number of new connections made by the last transfer.
+    ///
+    /// Connections reused from the cache of the handle aren't counted, so
+    /// this is 0 for a transfer which only reused connections.
+    ///
+    /// Corresponds to `CURLINFO_NUM_CONNECTS` and may return an error if the
+    /// option isn't supported.
+    pub fn num_connects(&mut self) -> Result<u32, Error> {
+        self.getopt_long(curl_sys::CURLINFO_NUM_CONNECTS).map(|c| c as u32)
+    }
+
+    /// Get the URL scheme used for the most recent connection, such as
+    /// `"HTTP"` or `"HTTPS"`.
+    ///
+    /// Corresponds to `CURLINFO_SCHEME` and may return an error if the option
+    /// isn't supported, which requires libcurl 7.52.0 or later.
+    pub fn scheme(&mut self) -> Result<Option<&str>, Error> {
+        self.getopt_str(curl_sys::CURLINFO_SCHEME)
+    }
+
     /// Get Content-Type
     ///
     /// Returns the content-type of the downloaded object. This is the value
@@ -2548,6 +3615,63 @@ impl Easy {
         }
     }
 
+    /// Get a `long` piece of information about the last transfer.
+    ///
+    /// This is an escape hatch for querying `CURLINFO_*` values which don't
+    /// have a dedicated method yet. If `info` isn't of type `CURLINFO_LONG`
+    /// an error is returned without calling into libcurl.
+    ///
+    /// Corresponds to `curl_easy_getinfo` and may return an error if the
+    /// information isn't supported.
+    pub fn getinfo_long(&mut self, info: curl_sys::CURLINFO)
+                        -> Result<i64, Error> {
+        try!(check_info_type(info, curl_sys::CURLINFO_LONG));
+        self.getopt_long(info).map(|v| v as i64)
+    }
+
+    /// Get a `double` piece of information about the last transfer.
+    ///
+    /// Like `getinfo_long`, except that `info` must be of type
+    /// `CURLINFO_DOUBLE`.
+    pub fn getinfo_double(&mut self, info: curl_sys::CURLINFO)
+                          -> Result<f64, Error> {
+        try!(check_info_type(info, curl_sys::CURLINFO_DOUBLE));
+        self.getopt_double(info).map(|v| v as f64)
+    }
+
+    /// Get a string piece of information about the last transfer.
+    ///
+    /// Like `getinfo_long`, except that `info` must be of type
+    /// `CURLINFO_STRING`. `CURLINFO_PRIVATE` is rejected as it isn't
+    /// actually a string.
+    pub fn getinfo_str(&mut self, info: curl_sys::CURLINFO)
+                       -> Result<Option<&str>, Error> {
+        try!(check_info_type(info, curl_sys::CURLINFO_STRING));
+        if info == curl_sys::CURLINFO_PRIVATE {
+            return Err(Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))
+        }
+        self.getopt_str(info)
+    }
+
+    /// Get a list of strings about the last transfer.
+    ///
+    /// Several `CURLINFO_SLIST` values, such as `CURLINFO_CERTINFO`, actually
+    /// return other kinds of pointers, so only `CURLINFO_SSL_ENGINES` and
+    /// `CURLINFO_COOKIELIST` are accepted here.
+    pub fn getinfo_list(&mut self, info: curl_sys::CURLINFO)
+                        -> Result<List, Error> {
+        if info != curl_sys::CURLINFO_SSL_ENGINES &&
+           info != curl_sys::CURLINFO_COOKIELIST {
+            return Err(Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))
+        }
+        unsafe {
+            let mut list = 0 as *mut _;
+            let rc = curl_sys::curl_easy_getinfo(self.handle, info, &mut list);
+            try!(self.cvt(rc));
+            Ok(List { raw: list })
+        }
+    }
+
     // =========================================================================
     // Other methods
 
@@ -2584,7 +3708,69 @@ impl Easy {
         };
         self.data.running.set(false);
         panic::propagate();
-        return ret
+        let ret = ret.map_err(|mut e| {
+            if e.is_http_returned_error() {
+                let mut code = 0 as c_long;
+                let info = curl_sys::CURLINFO_RESPONSE_CODE;
+                let rc = unsafe {
+                    curl_sys::curl_easy_getinfo(self.handle, info, &mut code)
+                };
+                if rc == curl_sys::CURLE_OK && code != 0 {
+                    e = ::error::error_with_status(e, code as u32);
+                }
+            }
+            let mut url = 0 as *const c_char;
+            let rc = unsafe {
+                curl_sys::curl_easy_getinfo(self.handle,
+                                            curl_sys::CURLINFO_EFFECTIVE_URL,
+                                            &mut url)
+            };
+            if rc != curl_sys::CURLE_OK || url.is_null() {
+                return e
+            }
+            let url = unsafe { CStr::from_ptr(url).to_string_lossy() };
+            ::error::error_with_url(e, &url)
+        });
+        if let Some(ref mut f) = *self.data.log.borrow_mut() {
+            f(&self.transfer_log(&ret));
+        }
+        ret
+    }
+
+    fn transfer_log<'a>(&self, result: &'a Result<(), Error>)
+                        -> TransferLog<'a> {
+        unsafe {
+            let string = |info| {
+                let mut p = 0 as *const c_char;
+                let rc = curl_sys::curl_easy_getinfo(self.handle, info, &mut p);
+                if rc != curl_sys::CURLE_OK || p.is_null() {
+                    None
+                } else {
+                    Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+                }
+            };
+            let long = |info| {
+                let mut n = 0 as c_long;
+                curl_sys::curl_easy_getinfo(self.handle, info, &mut n);
+                n
+            };
+            let double = |info| {
+                let mut n = 0 as c_double;
+                curl_sys::curl_easy_getinfo(self.handle, info, &mut n);
+                n
+            };
+            TransferLog {
+                method: string(curl_sys::CURLINFO_EFFECTIVE_METHOD),
+                url: string(curl_sys::CURLINFO_EFFECTIVE_URL)
+                         .map(|url| ::error::redact_url(&url)),
+                response_code: long(curl_sys::CURLINFO_RESPONSE_CODE) as u32,
+                downloaded: double(curl_sys::CURLINFO_SIZE_DOWNLOAD) as u64,
+                uploaded: double(curl_sys::CURLINFO_SIZE_UPLOAD) as u64,
+                total_time: double_seconds_to_duration(
+                    double(curl_sys::CURLINFO_TOTAL_TIME)),
+                error: result.as_ref().err(),
+            }
+        }
     }
 
     /// Creates a new scoped transfer which can be used to set callbacks and
@@ -2640,11 +3826,22 @@ impl Easy {
             ref header,
             ref progress,
             ref ssl_ctx,
+            ref trailer,
+            ref sockopt,
+            ref opensocket,
+            ref prereq,
             ref running,
             debug_set,
             header_list: _,
+            quote_list: _,
+            post_quote_list: _,
+            default_headers: _,
+            metrics: _,
             form: _,
             error_buf: _,
+            log: _,
+            hsts_read: _,
+            hsts_write: _,
         } = *self.data;
 
         // Can't reset while running, we'll detect this elsewhere
@@ -2667,6 +3864,10 @@ impl Easy {
         let header = ptr(header.is_some());
         let progress = ptr(progress.is_some());
         let ssl_ctx = ptr(ssl_ctx.is_some());
+        let trailer = ptr(trailer.is_some());
+        let sockopt = ptr(sockopt.is_some());
+        let opensocket = ptr(opensocket.is_some());
+        let prereq = ptr(prereq.is_some());
 
         let _ = self.set_write_function(easy_write_cb, write);
         let _ = self.set_read_function(easy_read_cb, read);
@@ -2674,6 +3875,12 @@ impl Easy {
         let _ = self.set_header_function(easy_header_cb, header);
         let _ = self.set_progress_function(easy_progress_cb, progress);
         let _ = self.set_ssl_ctx_function(easy_ssl_ctx_cb, ssl_ctx);
+        let _ = self.set_trailer_function(easy_trailer_cb, trailer);
+        let _ = self.set_sockopt_function(easy_sockopt_cb, sockopt);
+        let _ = self.set_opensocket_function(easy_opensocket_cb, opensocket);
+        // libcurl only accepts the option from 7.80.0 on, which is fine to
+        // ignore when it was never set.
+        let _ = self.set_prereq_function(easy_prereq_cb, prereq);
 
         // Don't reset the debug callback if we haven't set it yet to preserve
         // the default behavior.
@@ -2687,6 +3894,56 @@ impl Easy {
         //                            0 as *const i32);
     }
 
+    /// Retrieves the size and modification time of the file at `url`
+    /// without downloading it.
+    ///
+    /// This performs a transfer with the `nobody` and `fetch_filetime`
+    /// options enabled, which is a `HEAD` request for HTTP, `SIZE` and `MDTM`
+    /// commands for FTP and a stat of the file for SFTP. Both options are
+    /// disabled again afterwards, while the URL stays set to `url`.
+    ///
+    /// An HTTP response with a status of 400 or above is returned as a
+    /// `HttpReturnedError`, as it doesn't describe the file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use curl::easy::Easy;
+    ///
+    /// let mut handle = Easy::new();
+    /// let meta = handle.stat("ftp://example.com/pub/file.tar.gz").unwrap();
+    /// println!("{:?} bytes, modified {:?}", meta.size(), meta.modified());
+    /// ```
+    pub fn stat(&mut self, url: &str) -> Result<RemoteMeta, Error> {
+        try!(self.url(url));
+        try!(self.upload(false));
+        try!(self.nobody(true));
+        try!(self.fetch_filetime(true));
+        let result = {
+            let mut transfer = self.transfer();
+            try!(transfer.write_function(|data| Ok(data.len())));
+            transfer.perform()
+        };
+        try!(self.nobody(false));
+        try!(self.fetch_filetime(false));
+        try!(result);
+
+        let scheme = url.split(':').next().unwrap_or("");
+        if scheme.eq_ignore_ascii_case("http") ||
+           scheme.eq_ignore_ascii_case("https") {
+            let code = try!(self.response_code());
+            if code >= 400 {
+                let err = Error::new(curl_sys::CURLE_HTTP_RETURNED_ERROR);
+                let err = ::error::error_with_status(err, code);
+                return Err(::error::error_with_url(err, url))
+            }
+        }
+        Ok(RemoteMeta {
+            size: try!(self.content_length_download()),
+            modified: try!(self.filetime()).map(filetime_to_system_time),
+        })
+    }
+
     /// Unpause reading on a connection.
     ///
     /// Using this function, you can explicitly unpause a connection that was
@@ -2813,12 +4070,26 @@ impl Easy {
     /// Re-initializes this handle to the default values.
     ///
     /// This puts the handle to the same state as it was in when it was just
-    /// created. This does, however, keep live connections, the session id
-    /// cache, the dns cache, and cookies.
+    /// created, so for example signals are disabled again as described in
+    /// `signal`. All callbacks, header lists and forms set on the handle are
+    /// dropped, as are the headers added with `default_header`, apart from
+    /// the callback set with `log_function`. This does, however, keep live
+    /// connections, the session id cache, the dns cache, and cookies, so it's
+    /// a cheap way to get a long-lived handle back into a known state. The
+    /// connections kept are still closed through the `closesocket_function`
+    /// they were made with.
+    ///
+    /// This corresponds to `curl_easy_reset`.
     pub fn reset(&mut self) {
         unsafe {
             curl_sys::curl_easy_reset(self.handle);
         }
+        // The options referring to `data` are all cleared now. The cached
+        // connections keep pointers to their close callbacks, but those are
+        // owned by the handle itself rather than `data`.
+        let log = self.data.log.borrow_mut().take();
+        self.data = Default::default();
+        *self.data.log.borrow_mut() = log;
         default_configure(self);
     }
 
@@ -2857,18 +4128,80 @@ impl Easy {
         }
     }
 
+    /// Performs maintenance on the connections kept open by this handle.
+    ///
+    /// Idle connections may be closed by servers or middleboxes which time
+    /// them out. Calling this regularly while no transfer is running sends
+    /// whatever the protocol uses to keep a connection alive, which is a
+    /// `PING` frame for HTTP/2, once per `upkeep_interval`. Nothing is done
+    /// for protocols without such a mechanism.
+    ///
+    /// This corresponds to `curl_easy_upkeep`, which requires libcurl 7.62.0
+    /// or later.
+    pub fn upkeep(&mut self) -> Result<(), Error> {
+        unsafe {
+            self.cvt(curl_sys::curl_easy_upkeep(self.handle))
+        }
+    }
+
     /// Get a pointer to the raw underlying CURL handle.
     pub fn raw(&self) -> *mut curl_sys::CURL {
         self.handle
     }
 
-    #[cfg(unix)]
-    fn setopt_path(&mut self,
-                   opt: curl_sys::CURLoption,
-                   val: &Path) -> Result<(), Error> {
-        use std::os::unix::prelude::*;
-        let s = try!(CString::new(val.as_os_str().as_bytes()));
-        self.setopt_str(opt, &s)
+    /// Sets a numeric libcurl option which doesn't have a dedicated method.
+    ///
+    /// This is an escape hatch for options this crate doesn't wrap yet.
+    /// `opt` must be either a `CURLOPTTYPE_LONG` or a `CURLOPTTYPE_OFF_T`
+    /// option, and `value` is passed along as the matching C type. Any other
+    /// kind of option, including the `CURLOPTTYPE_BLOB` ones numbered after
+    /// the `CURLOPTTYPE_OFF_T` options, is rejected with an error without
+    /// calling into libcurl.
+    ///
+    /// Corresponds to `curl_easy_setopt`, and an error is returned if libcurl
+    /// doesn't know about the option.
+    pub fn raw_option(&mut self,
+                      opt: curl_sys::CURLoption,
+                      value: i64) -> Result<(), Error> {
+        if opt < curl_sys::CURLOPTTYPE_OBJECTPOINT {
+            self.setopt_long(opt, value as c_long)
+        } else if opt >= curl_sys::CURLOPTTYPE_OFF_T &&
+                  opt < curl_sys::CURLOPTTYPE_BLOB {
+            self.setopt_off_t(opt, value as curl_sys::curl_off_t)
+        } else {
+            Err(Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))
+        }
+    }
+
+    /// Sets a string libcurl option which doesn't have a dedicated method.
+    ///
+    /// Like `raw_option`, except that `opt` must be a `CURLOPTTYPE_OBJECTPOINT`
+    /// option and `value` is passed along as a C string.
+    ///
+    /// # Safety
+    ///
+    /// libcurl doesn't distinguish string options from other pointer options
+    /// such as `CURLOPT_HTTPHEADER`, and a few string options such as
+    /// `CURLOPT_POSTFIELDS` aren't copied by libcurl. The caller must ensure
+    /// `opt` takes a `char *` which libcurl copies.
+    pub unsafe fn raw_option_str(&mut self,
+                                 opt: curl_sys::CURLoption,
+                                 value: &str) -> Result<(), Error> {
+        if opt < curl_sys::CURLOPTTYPE_OBJECTPOINT ||
+           opt >= curl_sys::CURLOPTTYPE_FUNCTIONPOINT {
+            return Err(Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))
+        }
+        let value = try!(CString::new(value));
+        self.setopt_str(opt, &value)
+    }
+
+    #[cfg(unix)]
+    fn setopt_path(&mut self,
+                   opt: curl_sys::CURLoption,
+                   val: &Path) -> Result<(), Error> {
+        use std::os::unix::prelude::*;
+        let s = try!(CString::new(val.as_os_str().as_bytes()));
+        self.setopt_str(opt, &s)
     }
 
     #[cfg(windows)]
@@ -2981,6 +4314,17 @@ impl Easy {
     }
 }
 
+/// Converts a time as returned by `Easy::filetime`, in seconds relative to
+/// the Unix epoch, to a `SystemTime`.
+#[doc(hidden)]
+pub fn filetime_to_system_time(secs: i64) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.wrapping_neg() as u64)
+    }
+}
+
 extern fn easy_write_cb(ptr: *mut c_char,
                         size: size_t,
                         nmemb: size_t,
@@ -3178,6 +4522,256 @@ extern fn transfer_ssl_ctx_cb(handle: *mut curl_sys::CURL,
     })
 }
 
+extern fn easy_trailer_cb(list: *mut *mut curl_sys::curl_slist,
+                          data: *mut c_void) -> c_int {
+    trailer_cb(list, data, |trailers| unsafe {
+        (*(data as *mut EasyData)).trailer.as_mut().map(|f| f(trailers))
+    })
+}
+
+extern fn transfer_trailer_cb(list: *mut *mut curl_sys::curl_slist,
+                              data: *mut c_void) -> c_int {
+    trailer_cb(list, data, |trailers| unsafe {
+        (*(data as *mut TransferData)).trailer.as_mut().map(|f| f(trailers))
+    })
+}
+
+fn trailer_cb<F>(list: *mut *mut curl_sys::curl_slist,
+                 data: *mut c_void,
+                 f: F) -> c_int
+    where F: FnOnce(&mut List) -> Option<bool>
+{
+    if data.is_null() {
+        return curl_sys::CURL_TRAILERFUNC_OK
+    }
+    panic::catch(|| {
+        let mut trailers = List::new();
+        match f(&mut trailers) {
+            Some(true) => {}
+            Some(false) => return curl_sys::CURL_TRAILERFUNC_ABORT,
+            None => return curl_sys::CURL_TRAILERFUNC_OK,
+        }
+        // libcurl takes ownership of the list and frees it once the trailers
+        // have been sent.
+        unsafe {
+            *list = trailers.raw;
+        }
+        mem::forget(trailers);
+        curl_sys::CURL_TRAILERFUNC_OK
+    }).unwrap_or(curl_sys::CURL_TRAILERFUNC_ABORT)
+}
+
+extern fn easy_sockopt_cb(data: *mut c_void,
+                          socket: curl_sys::curl_socket_t,
+                          _purpose: curl_sys::curlsocktype) -> c_int {
+    sockopt_cb(data, || unsafe {
+        (*(data as *mut EasyData)).sockopt.as_mut().map(|f| f(socket))
+    })
+}
+
+extern fn transfer_sockopt_cb(data: *mut c_void,
+                              socket: curl_sys::curl_socket_t,
+                              _purpose: curl_sys::curlsocktype) -> c_int {
+    sockopt_cb(data, || unsafe {
+        (*(data as *mut TransferData)).sockopt.as_mut().map(|f| f(socket))
+    })
+}
+
+fn sockopt_cb<F>(data: *mut c_void, f: F) -> c_int
+    where F: FnOnce() -> Option<SockoptResult>
+{
+    if data.is_null() {
+        return curl_sys::CURL_SOCKOPT_OK
+    }
+    panic::catch(|| {
+        match f() {
+            Some(result) => result as c_int,
+            None => curl_sys::CURL_SOCKOPT_OK,
+        }
+    }).unwrap_or(curl_sys::CURL_SOCKOPT_ERROR)
+}
+
+extern fn easy_opensocket_cb(data: *mut c_void,
+                             _purpose: curl_sys::curlsocktype,
+                             addr: *mut curl_sys::curl_sockaddr)
+                             -> curl_sys::curl_socket_t {
+    opensocket_cb(data, addr, |socket| unsafe {
+        (*(data as *mut EasyData)).opensocket.as_mut().map(|f| f(socket))
+    })
+}
+
+extern fn transfer_opensocket_cb(data: *mut c_void,
+                                 _purpose: curl_sys::curlsocktype,
+                                 addr: *mut curl_sys::curl_sockaddr)
+                                 -> curl_sys::curl_socket_t {
+    opensocket_cb(data, addr, |socket| unsafe {
+        (*(data as *mut TransferData)).opensocket.as_mut().map(|f| f(socket))
+    })
+}
+
+fn opensocket_cb<F>(data: *mut c_void,
+                    addr: *mut curl_sys::curl_sockaddr,
+                    f: F) -> curl_sys::curl_socket_t
+    where F: FnOnce(&OpenSocket) -> Option<Option<Socket>>
+{
+    if data.is_null() || addr.is_null() {
+        return curl_sys::CURL_SOCKET_BAD
+    }
+    panic::catch(|| {
+        let socket = OpenSocket { inner: unsafe { &*addr } };
+        match f(&socket) {
+            Some(Some(socket)) => socket,
+            Some(None) => curl_sys::CURL_SOCKET_BAD,
+            // Without a callback configured the data pointer is null, so
+            // this can't actually happen.
+            None => curl_sys::CURL_SOCKET_BAD,
+        }
+    }).unwrap_or(curl_sys::CURL_SOCKET_BAD)
+}
+
+extern fn easy_closesocket_cb(data: *mut c_void,
+                              socket: curl_sys::curl_socket_t) -> c_int {
+    closesocket_cb(data, || unsafe {
+        Some((*(data as *mut Box<FnMut(Socket) + Send>))(socket))
+    })
+}
+
+fn closesocket_cb<F>(data: *mut c_void, f: F) -> c_int
+    where F: FnOnce() -> Option<()>
+{
+    if data.is_null() {
+        return 1
+    }
+    panic::catch(f);
+    0
+}
+
+extern fn easy_prereq_cb(data: *mut c_void,
+                         primary_ip: *mut c_char,
+                         local_ip: *mut c_char,
+                         primary_port: c_int,
+                         local_port: c_int) -> c_int {
+    let conn = prereq_info(primary_ip, local_ip, primary_port, local_port);
+    prereq_cb(data, || unsafe {
+        (*(data as *mut EasyData)).prereq.as_mut().map(|f| f(&conn))
+    })
+}
+
+extern fn transfer_prereq_cb(data: *mut c_void,
+                             primary_ip: *mut c_char,
+                             local_ip: *mut c_char,
+                             primary_port: c_int,
+                             local_port: c_int) -> c_int {
+    let conn = prereq_info(primary_ip, local_ip, primary_port, local_port);
+    prereq_cb(data, || unsafe {
+        (*(data as *mut TransferData)).prereq.as_mut().map(|f| f(&conn))
+    })
+}
+
+fn prereq_info<'a>(primary_ip: *mut c_char,
+                   local_ip: *mut c_char,
+                   primary_port: c_int,
+                   local_port: c_int) -> PreRequest<'a> {
+    let ip = |ip: *mut c_char| {
+        if ip.is_null() {
+            return ""
+        }
+        unsafe { CStr::from_ptr(ip).to_str().unwrap_or("") }
+    };
+    PreRequest {
+        primary_ip: ip(primary_ip),
+        primary_port: primary_port as u16,
+        local_ip: ip(local_ip),
+        local_port: local_port as u16,
+    }
+}
+
+fn prereq_cb<F>(data: *mut c_void, f: F) -> c_int
+    where F: FnOnce() -> Option<bool>
+{
+    if data.is_null() {
+        return curl_sys::CURL_PREREQFUNC_OK
+    }
+    match panic::catch(f) {
+        Some(Some(true)) | Some(None) => curl_sys::CURL_PREREQFUNC_OK,
+        Some(Some(false)) | None => curl_sys::CURL_PREREQFUNC_ABORT,
+    }
+}
+
+// `includeSubDomains` is a one bit wide bitfield, which C compilers place at
+// the low end of its storage on little endian targets and at the high end on
+// big endian ones.
+#[cfg(target_env = "msvc")]
+const HSTS_INCLUDE_SUBDOMAINS: libc::c_uint = 1;
+#[cfg(all(not(target_env = "msvc"), target_endian = "little"))]
+const HSTS_INCLUDE_SUBDOMAINS: u8 = 1;
+#[cfg(all(not(target_env = "msvc"), target_endian = "big"))]
+const HSTS_INCLUDE_SUBDOMAINS: u8 = 1 << 7;
+
+// How libcurl marks entries which never expire.
+const HSTS_UNLIMITED: &'static str = "unlimited";
+
+extern fn hsts_read_cb(_handle: *mut curl_sys::CURL,
+                       entry: *mut curl_sys::curl_hstsentry,
+                       data: *mut c_void) -> curl_sys::CURLSTScode {
+    panic::catch(|| unsafe {
+        let entry = &mut *entry;
+        let f = match (*(data as *mut EasyData)).hsts_read {
+            Some(ref mut f) => f,
+            None => return curl_sys::CURLSTS_DONE,
+        };
+        let next = match f() {
+            Some(next) => next,
+            None => return curl_sys::CURLSTS_DONE,
+        };
+        let expire = next.expire.as_ref().map(|s| &s[..]).unwrap_or("");
+        if next.host.len() >= entry.namelen ||
+           expire.len() >= entry.expire.len() ||
+           next.host.contains('\0') || expire.contains('\0') {
+            return curl_sys::CURLSTS_FAIL
+        }
+        let name = slice::from_raw_parts_mut(entry.name as *mut u8,
+                                             next.host.len() + 1);
+        name[..next.host.len()].copy_from_slice(next.host.as_bytes());
+        name[next.host.len()] = 0;
+        for (dst, src) in entry.expire.iter_mut()
+                               .zip(expire.bytes().chain(Some(0))) {
+            *dst = src as c_char;
+        }
+        if next.include_subdomains {
+            entry.includeSubDomains |= HSTS_INCLUDE_SUBDOMAINS;
+        } else {
+            entry.includeSubDomains &= !HSTS_INCLUDE_SUBDOMAINS;
+        }
+        curl_sys::CURLSTS_OK
+    }).unwrap_or(curl_sys::CURLSTS_FAIL)
+}
+
+extern fn hsts_write_cb(_handle: *mut curl_sys::CURL,
+                        entry: *mut curl_sys::curl_hstsentry,
+                        _index: *mut curl_sys::curl_index,
+                        data: *mut c_void) -> curl_sys::CURLSTScode {
+    panic::catch(|| unsafe {
+        let entry = &*entry;
+        let host = CStr::from_ptr(entry.name).to_string_lossy().into_owned();
+        let expire = CStr::from_ptr(entry.expire.as_ptr()).to_string_lossy();
+        let entry = HstsEntry {
+            host: host,
+            include_subdomains:
+                entry.includeSubDomains & HSTS_INCLUDE_SUBDOMAINS != 0,
+            expire: if expire.is_empty() || expire == HSTS_UNLIMITED {
+                None
+            } else {
+                Some(expire.into_owned())
+            },
+        };
+        if let Some(ref mut f) = (*(data as *mut EasyData)).hsts_write {
+            f(&entry);
+        }
+        curl_sys::CURLSTS_OK
+    }).unwrap_or(curl_sys::CURLSTS_FAIL)
+}
+
 // TODO: same thing as `debug_cb`: can we expose `handle`?
 fn ssl_ctx_cb<F>(_handle: *mut curl_sys::CURL,
                  ssl_ctx: *mut c_void,
@@ -3353,6 +4947,54 @@ impl<'easy, 'data> Transfer<'easy, 'data> {
         }
     }
 
+    /// Same as `Easy::trailer_function`, just takes a non `'static` lifetime
+    /// corresponding to the lifetime of this transfer.
+    pub fn trailer_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&mut List) -> bool + 'data
+    {
+        self.data.trailer = Some(Box::new(f));
+        unsafe {
+            self.easy.set_trailer_function(transfer_trailer_cb,
+                                           &*self.data as *const _ as *mut _)
+        }
+    }
+
+    /// Same as `Easy::opensocket_function`, just takes a non `'static`
+    /// lifetime corresponding to the lifetime of this transfer.
+    pub fn opensocket_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&OpenSocket) -> Option<Socket> + 'data
+    {
+        self.data.opensocket = Some(Box::new(f));
+        unsafe {
+            let ptr = &*self.data as *const _ as *mut _;
+            self.easy.set_opensocket_function(transfer_opensocket_cb, ptr)
+        }
+    }
+
+    /// Same as `Easy::prereq_function`, just takes a non `'static` lifetime
+    /// corresponding to the lifetime of this transfer.
+    pub fn prereq_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(&PreRequest) -> bool + 'data
+    {
+        self.data.prereq = Some(Box::new(f));
+        unsafe {
+            let ptr = &*self.data as *const _ as *mut _;
+            self.easy.set_prereq_function(transfer_prereq_cb, ptr)
+        }
+    }
+
+    /// Same as `Easy::sockopt_function`, just takes a non `'static` lifetime
+    /// corresponding to the lifetime of this transfer.
+    pub fn sockopt_function<F>(&mut self, f: F) -> Result<(), Error>
+        where F: FnMut(Socket) -> SockoptResult + 'data
+    {
+        self.data.sockopt = Some(Box::new(f));
+        unsafe {
+            self.easy.set_sockopt_function(transfer_sockopt_cb,
+                                           &*self.data as *const _ as *mut _)
+        }
+    }
+
     /// Same as `Easy::debug_function`, just takes a non `'static` lifetime
     /// corresponding to the lifetime of this transfer.
     pub fn debug_function<F>(&mut self, f: F) -> Result<(), Error>
@@ -3410,6 +5052,15 @@ impl<'easy, 'data> Transfer<'easy, 'data> {
     }
 }
 
+fn check_info_type(info: curl_sys::CURLINFO,
+                   ty: curl_sys::CURLINFO) -> Result<(), Error> {
+    if info & curl_sys::CURLINFO_TYPEMASK == ty {
+        Ok(())
+    } else {
+        Err(Error::new(curl_sys::CURLE_BAD_FUNCTION_ARGUMENT))
+    }
+}
+
 fn double_seconds_to_duration(seconds: f64) -> Duration {
     let whole_seconds = seconds.trunc() as u64;
     let nanos = seconds.fract() * 1_000_000_000f64;
@@ -3788,6 +5439,268 @@ impl<'form, 'data> Part<'form, 'data> {
     }
 }
 
+impl<'a> OpenSocket<'a> {
+    /// Returns the address family of the socket, such as `AF_INET`.
+    pub fn family(&self) -> c_int {
+        self.inner.family
+    }
+
+    /// Returns the type of the socket, such as `SOCK_STREAM`.
+    pub fn socktype(&self) -> c_int {
+        self.inner.socktype
+    }
+
+    /// Returns the protocol of the socket, such as `IPPROTO_TCP`.
+    pub fn protocol(&self) -> c_int {
+        self.inner.protocol
+    }
+
+    /// Returns the address the socket will be connected to, if it's an IPv4
+    /// or IPv6 address.
+    pub fn address(&self) -> Option<SocketAddr> {
+        // `sockaddr_in` and `sockaddr_in6` have the same layout on all
+        // supported platforms after the leading family field, apart from the
+        // byte order of the port and address.
+        let len = self.inner.addrlen as usize;
+        let raw = unsafe {
+            slice::from_raw_parts(&self.inner.addr as *const _ as *const u8,
+                                  len)
+        };
+        let be16 = |i: usize| (raw[i] as u16) << 8 | raw[i + 1] as u16;
+        let ne32 = |i: usize| {
+            let mut b = [0; 4];
+            b.copy_from_slice(&raw[i..i + 4]);
+            unsafe { mem::transmute::<[u8; 4], u32>(b) }
+        };
+        if self.inner.family == AF_INET && len >= 8 {
+            let ip = Ipv4Addr::new(raw[4], raw[5], raw[6], raw[7]);
+            Some(SocketAddr::V4(SocketAddrV4::new(ip, be16(2))))
+        } else if self.inner.family == AF_INET6 && len >= 28 {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&raw[8..24]);
+            let ip = Ipv6Addr::from(octets);
+            Some(SocketAddr::V6(SocketAddrV6::new(ip, be16(2), ne32(4),
+                                                  ne32(24))))
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> PreRequest<'a> {
+    /// Returns the IP address of the remote end of the connection, such as
+    /// `"93.184.216.34"` or `"::1"`.
+    pub fn primary_ip(&self) -> &str {
+        self.primary_ip
+    }
+
+    /// Returns the port of the remote end of the connection.
+    pub fn primary_port(&self) -> u16 {
+        self.primary_port
+    }
+
+    /// Returns the address of the remote end of the connection, or `None` if
+    /// libcurl reported one which isn't an IP address.
+    pub fn primary_addr(&self) -> Option<SocketAddr> {
+        self.primary_ip.parse::<IpAddr>().ok().map(|ip| {
+            SocketAddr::new(ip, self.primary_port)
+        })
+    }
+
+    /// Returns the IP address of the local end of the connection.
+    pub fn local_ip(&self) -> &str {
+        self.local_ip
+    }
+
+    /// Returns the port of the local end of the connection.
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+}
+
+#[cfg(unix)]
+const AF_INET: c_int = libc::AF_INET;
+#[cfg(unix)]
+const AF_INET6: c_int = libc::AF_INET6;
+#[cfg(windows)]
+const AF_INET: c_int = 2;
+#[cfg(windows)]
+const AF_INET6: c_int = 23;
+
+impl<'a> TransferLog<'a> {
+    /// Returns the method of the final request, such as `"GET"`.
+    ///
+    /// This requires libcurl 7.72.0 or later, on older versions `None` is
+    /// always returned.
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the last URL used, which differs from the configured one if
+    /// redirects were followed.
+    ///
+    /// Any user name and password in the URL are removed.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the last response code received, or 0 if there was none.
+    pub fn response_code(&self) -> u32 {
+        self.response_code
+    }
+
+    /// Returns the number of bytes downloaded, not including headers.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    /// Returns the number of bytes uploaded, not including headers.
+    pub fn uploaded(&self) -> u64 {
+        self.uploaded
+    }
+
+    /// Returns how long the transfer took in total.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Returns the error the transfer failed with, if it did.
+    pub fn error(&self) -> Option<&Error> {
+        self.error
+    }
+}
+
+impl ProtocolSet {
+    /// Creates a new set of protocols with no members.
+    pub fn new() -> ProtocolSet {
+        ProtocolSet { bits: 0 }
+    }
+
+    /// Creates a new set containing all protocols.
+    pub fn all() -> ProtocolSet {
+        ProtocolSet { bits: curl_sys::CURLPROTO_ALL as c_long }
+    }
+
+    /// The HTTP protocol.
+    pub fn http(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_HTTP, on)
+    }
+
+    /// The HTTPS protocol.
+    pub fn https(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_HTTPS, on)
+    }
+
+    /// The FTP protocol.
+    pub fn ftp(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_FTP, on)
+    }
+
+    /// The FTP protocol over SSL/TLS.
+    pub fn ftps(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_FTPS, on)
+    }
+
+    /// Access to local files through `file://` URLs.
+    pub fn file(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_FILE, on)
+    }
+
+    /// The SCP protocol.
+    pub fn scp(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_SCP, on)
+    }
+
+    /// The SFTP protocol.
+    pub fn sftp(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_SFTP, on)
+    }
+
+    /// The TELNET protocol.
+    pub fn telnet(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_TELNET, on)
+    }
+
+    /// The LDAP protocol.
+    pub fn ldap(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_LDAP, on)
+    }
+
+    /// The LDAP protocol over SSL/TLS.
+    pub fn ldaps(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_LDAPS, on)
+    }
+
+    /// The DICT protocol.
+    pub fn dict(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_DICT, on)
+    }
+
+    /// The TFTP protocol.
+    pub fn tftp(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_TFTP, on)
+    }
+
+    /// The IMAP protocol.
+    pub fn imap(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_IMAP, on)
+    }
+
+    /// The IMAP protocol over SSL/TLS.
+    pub fn imaps(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_IMAPS, on)
+    }
+
+    /// The POP3 protocol.
+    pub fn pop3(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_POP3, on)
+    }
+
+    /// The POP3 protocol over SSL/TLS.
+    pub fn pop3s(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_POP3S, on)
+    }
+
+    /// The SMTP protocol.
+    pub fn smtp(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_SMTP, on)
+    }
+
+    /// The SMTP protocol over SSL/TLS.
+    pub fn smtps(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_SMTPS, on)
+    }
+
+    /// The RTSP protocol.
+    pub fn rtsp(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_RTSP, on)
+    }
+
+    /// The Gopher protocol.
+    pub fn gopher(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_GOPHER, on)
+    }
+
+    /// The SMB protocol.
+    pub fn smb(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_SMB, on)
+    }
+
+    /// The SMB protocol over SSL/TLS.
+    pub fn smbs(&mut self, on: bool) -> &mut ProtocolSet {
+        self.flag(curl_sys::CURLPROTO_SMBS, on)
+    }
+
+    fn flag(&mut self, bit: c_int, on: bool) -> &mut ProtocolSet {
+        if on {
+            self.bits |= bit as c_long;
+        } else {
+            self.bits &= !bit as c_long;
+        }
+        self
+    }
+}
+
 impl Auth {
     /// Creates a new set of authentications with no members.
     ///
@@ -3872,6 +5785,15 @@ impl Auth {
         self.flag(curl_sys::CURLAUTH_NTLM_WB, on)
     }
 
+    /// HTTP Bearer token authentication.
+    ///
+    /// This sends the OAuth 2.0 access token set with
+    /// `Easy::xoauth2_bearer` in an `Authorization` header, as defined in
+    /// RFC 6750. It requires libcurl 7.61.0 or later.
+    pub fn bearer(&mut self, on: bool) -> &mut Auth {
+        self.flag(curl_sys::CURLAUTH_BEARER, on)
+    }
+
     fn flag(&mut self, bit: c_ulong, on: bool) -> &mut Auth {
         if on {
             self.bits |= bit as c_long;
@@ -3881,3 +5803,111 @@ impl Auth {
         self
     }
 }
+
+impl AltSvcCtrl {
+    /// Creates a new configuration which doesn't allow any alternative
+    /// services, leaving Alt-Svc disabled.
+    pub fn new() -> AltSvcCtrl {
+        AltSvcCtrl { bits: 0 }
+    }
+
+    /// Allows alternative services speaking HTTP/1.1.
+    pub fn h1(&mut self, on: bool) -> &mut AltSvcCtrl {
+        self.flag(curl_sys::CURLALTSVC_H1, on)
+    }
+
+    /// Allows alternative services speaking HTTP/2.
+    pub fn h2(&mut self, on: bool) -> &mut AltSvcCtrl {
+        self.flag(curl_sys::CURLALTSVC_H2, on)
+    }
+
+    /// Allows alternative services speaking HTTP/3.
+    pub fn h3(&mut self, on: bool) -> &mut AltSvcCtrl {
+        self.flag(curl_sys::CURLALTSVC_H3, on)
+    }
+
+    /// Only reads the file configured with `Easy::alt_svc_file`, without
+    /// writing the cache back to it.
+    pub fn read_only_file(&mut self, on: bool) -> &mut AltSvcCtrl {
+        self.flag(curl_sys::CURLALTSVC_READONLYFILE, on)
+    }
+
+    fn flag(&mut self, bit: c_long, on: bool) -> &mut AltSvcCtrl {
+        if on {
+            self.bits |= bit;
+        } else {
+            self.bits &= !bit;
+        }
+        self
+    }
+}
+
+impl HstsCtrl {
+    /// Creates a new configuration with HSTS disabled.
+    pub fn new() -> HstsCtrl {
+        HstsCtrl { bits: 0 }
+    }
+
+    /// Enables the HSTS cache.
+    pub fn enable(&mut self, on: bool) -> &mut HstsCtrl {
+        self.flag(curl_sys::CURLHSTS_ENABLE, on)
+    }
+
+    /// Only reads the file configured with `Easy::hsts_file`, without writing
+    /// the cache back to it.
+    pub fn read_only_file(&mut self, on: bool) -> &mut HstsCtrl {
+        self.flag(curl_sys::CURLHSTS_READONLYFILE, on)
+    }
+
+    fn flag(&mut self, bit: c_long, on: bool) -> &mut HstsCtrl {
+        if on {
+            self.bits |= bit;
+        } else {
+            self.bits &= !bit;
+        }
+        self
+    }
+}
+
+impl RemoteMeta {
+    /// Returns the size of the file in bytes, if the server reported it.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Returns when the file was last modified, if the server reported it.
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+}
+
+impl HstsEntry {
+    /// Creates a new entry for `host`.
+    ///
+    /// The expiry time is given in GMT in the format `YYYYMMDD HH:MM:SS`, and
+    /// an entry without one never expires.
+    pub fn new(host: &str, include_subdomains: bool, expire: Option<&str>)
+               -> HstsEntry {
+        HstsEntry {
+            host: host.to_string(),
+            include_subdomains: include_subdomains,
+            expire: expire.map(|s| s.to_string()),
+        }
+    }
+
+    /// Returns the host name this entry applies to.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Returns whether this entry also applies to the subdomains of `host`.
+    pub fn include_subdomains(&self) -> bool {
+        self.include_subdomains
+    }
+
+    /// Returns when this entry expires, in GMT in the format
+    /// `YYYYMMDD HH:MM:SS`, or `None` if it never does.
+    pub fn expire(&self) -> Option<&str> {
+        self.expire.as_ref().map(|s| &s[..])
+    }
+}
@@ -8,26 +8,95 @@ use curl_sys;
 
 /// An error returned from various "easy" operations.
 ///
-/// This structure wraps a `CURLcode`.
+/// This structure wraps a `CURLcode`, along with libcurl's detailed message
+/// and the URL of the failed transfer when those are known.
 #[derive(Clone, PartialEq)]
 pub struct Error {
     code: curl_sys::CURLcode,
     extra: Option<Box<str>>,
+    url: Option<Box<str>>,
+    status: Option<u32>,
+    body: Option<Box<[u8]>>,
+}
+
+/// A broad classification of an `Error`, as returned by `Error::kind`.
+///
+/// This is meant for decisions such as whether to retry a request, where
+/// many different libcurl codes call for the same reaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The host or proxy name couldn't be resolved.
+    Dns,
+    /// The connection to the host or proxy couldn't be established.
+    ConnectionRefused,
+    /// The transfer took longer than the configured timeouts allowed.
+    Timeout,
+    /// The certificate of the peer couldn't be verified.
+    TlsVerification,
+    /// The maximum number of redirects was exceeded.
+    TooManyRedirects,
+    /// The transfer was aborted by one of the callbacks.
+    Aborted,
+    /// The server responded with the given HTTP error status, see
+    /// `Easy::fail_on_error`.
+    Http(u32),
+    /// Any other error.
+    Other,
 }
 
 pub fn error_with_extra(code: curl_sys::CURLcode, extra: Box<str>) -> Error {
     Error {
         code: code,
         extra: Some(extra),
+        url: None,
+        status: None,
+        body: None,
     }
 }
 
+/// Attaches the URL of a failed transfer to `err`, with any credentials in
+/// it removed.
+pub fn error_with_url(mut err: Error, url: &str) -> Error {
+    err.url = Some(redact_url(url).into_boxed_str());
+    err
+}
+
+/// Removes the user name and password, if any, from `url`.
+pub fn redact_url(url: &str) -> String {
+    match url.find("://") {
+        Some(i) => {
+            let rest = &url[i + 3..];
+            let host_end = rest.find('/').unwrap_or(rest.len());
+            match rest[..host_end].rfind('@') {
+                Some(at) => format!("{}{}", &url[..i + 3], &rest[at + 1..]),
+                None => url.to_string(),
+            }
+        }
+        None => url.to_string(),
+    }
+}
+
+/// Attaches the HTTP response code of a failed transfer to `err`.
+pub fn error_with_status(mut err: Error, status: u32) -> Error {
+    err.status = Some(status);
+    err
+}
+
+/// Attaches the body of an HTTP error response to `err`.
+pub fn error_with_body(mut err: Error, body: Vec<u8>) -> Error {
+    err.body = Some(body.into_boxed_slice());
+    err
+}
+
 impl Error {
     /// Creates a new error from the underlying code returned by libcurl.
     pub fn new(code: curl_sys::CURLcode) -> Error {
         Error {
             code: code,
             extra: None,
+            url: None,
+            status: None,
+            body: None,
         }
     }
 
@@ -46,10 +115,10 @@ impl Error {
         self.code == curl_sys::CURLE_URL_MALFORMAT
     }
 
-    // /// Returns whether this error corresponds to CURLE_NOT_BUILT_IN.
-    // pub fn is_not_built_in(&self) -> bool {
-    //     self.code == curl_sys::CURLE_NOT_BUILT_IN
-    // }
+    /// Returns whether this error corresponds to CURLE_NOT_BUILT_IN.
+    pub fn is_not_built_in(&self) -> bool {
+        self.code == curl_sys::CURLE_NOT_BUILT_IN
+    }
 
     /// Returns whether this error corresponds to CURLE_COULDNT_RESOLVE_PROXY.
     pub fn is_couldnt_resolve_proxy(&self) -> bool {
@@ -256,6 +325,11 @@ impl Error {
         self.code == curl_sys::CURLE_SSL_CACERT_BADFILE
     }
 
+    /// Returns whether this error corresponds to CURLE_REMOTE_FILE_NOT_FOUND.
+    pub fn is_remote_file_not_found(&self) -> bool {
+        self.code == curl_sys::CURLE_REMOTE_FILE_NOT_FOUND
+    }
+
     /// Returns whether this error corresponds to CURLE_SSL_CRL_BADFILE.
     pub fn is_ssl_crl_badfile(&self) -> bool {
         self.code == curl_sys::CURLE_SSL_CRL_BADFILE
@@ -295,14 +369,67 @@ impl Error {
     pub fn extra_description(&self) -> Option<&str> {
         self.extra.as_ref().map(|s| &**s)
     }
+
+    /// Classifies this error for callers which don't care about the exact
+    /// libcurl code.
+    ///
+    /// `ErrorKind::Http` is only returned for errors caused by
+    /// `Easy::fail_on_error` which know the status of the response.
+    pub fn kind(&self) -> ErrorKind {
+        match self.code {
+            curl_sys::CURLE_COULDNT_RESOLVE_HOST |
+            curl_sys::CURLE_COULDNT_RESOLVE_PROXY => ErrorKind::Dns,
+            curl_sys::CURLE_COULDNT_CONNECT => ErrorKind::ConnectionRefused,
+            curl_sys::CURLE_OPERATION_TIMEDOUT => ErrorKind::Timeout,
+            curl_sys::CURLE_PEER_FAILED_VERIFICATION |
+            curl_sys::CURLE_SSL_CACERT |
+            curl_sys::CURLE_SSL_ISSUER_ERROR => ErrorKind::TlsVerification,
+            curl_sys::CURLE_TOO_MANY_REDIRECTS => ErrorKind::TooManyRedirects,
+            curl_sys::CURLE_ABORTED_BY_CALLBACK => ErrorKind::Aborted,
+            curl_sys::CURLE_HTTP_RETURNED_ERROR => {
+                match self.status {
+                    Some(status) => ErrorKind::Http(status),
+                    None => ErrorKind::Other,
+                }
+            }
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Returns the HTTP response code which caused this error, if known.
+    pub fn status(&self) -> Option<u32> {
+        self.status
+    }
+
+    /// Returns the body of the HTTP error response which caused this error.
+    ///
+    /// This is only available for errors returned by `http::Request`
+    /// configured with `fail_on_error`, as libcurl itself discards the body
+    /// of such responses.
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_ref().map(|b| &**b)
+    }
+
+    /// Returns the URL of the transfer which failed, if known.
+    ///
+    /// This is filled in for errors returned by `Easy::perform` and
+    /// `Transfer::perform`. Any user name and password in the URL are
+    /// removed.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_ref().map(|s| &**s)
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let desc = error::Error::description(self);
-        match self.extra {
+        try!(match self.extra {
             Some(ref s) => write!(f, "[{}] {} ({})", self.code(), desc, s),
             None => write!(f, "[{}] {}", self.code(), desc),
+        });
+        match self.url {
+            Some(ref url) => write!(f, " for {}", url),
+            None => Ok(()),
         }
     }
 }
@@ -313,6 +440,9 @@ impl fmt::Debug for Error {
          .field("description", &error::Error::description(self))
          .field("code", &self.code)
          .field("extra", &self.extra)
+         .field("url", &self.url)
+         .field("status", &self.status)
+         .field("body", &self.body.as_ref().map(|b| b.len()))
          .finish()
     }
 }
@@ -569,7 +699,8 @@ impl error::Error for FormError {
 
 impl From<ffi::NulError> for Error {
     fn from(_: ffi::NulError) -> Error {
-        Error { code: curl_sys::CURLE_CONV_FAILED, extra: None }
+        Error { code: curl_sys::CURLE_CONV_FAILED, extra: None, url: None,
+                status: None, body: None }
     }
 }
 
@@ -0,0 +1,410 @@
+//! Recording HTTP exchanges and replaying them later
+//!
+//! Tests which talk to a real server are slow, flaky and need the network.
+//! The types in this module make it possible to run such tests against a
+//! real server once, record each request together with the response it got
+//! into a `Cassette`, and from then on serve the same responses from the
+//! cassette without touching the network.
+//!
+//! A `Recorder` wraps any other `Transport`, such as an `Easy` handle, and
+//! records everything performed through it. A `Replayer` is a `Transport`
+//! which answers requests from a cassette instead, picking the recorded
+//! exchange whose request matches. Cassettes are saved in a plain text format
+//! which is easy to review in version control.
+//!
+//! ```no_run
+//! use std::env;
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! use curl::easy::Easy;
+//! use curl::fixture::{Cassette, Recorder, Replayer};
+//! use curl::http::{Request, Transport};
+//!
+//! fn check_status<T: Transport>(transport: &mut T) {
+//!     let request = Request::get("https://example.com/status");
+//!     assert_eq!(transport.execute(&request).unwrap().code(), 200);
+//! }
+//!
+//! if env::var_os("RECORD").is_some() {
+//!     let mut recorder = Recorder::new(Easy::new());
+//!     check_status(&mut recorder);
+//!     let file = File::create("tests/fixtures/status.txt").unwrap();
+//!     recorder.cassette().save(file).unwrap();
+//! } else {
+//!     let file = File::open("tests/fixtures/status.txt").unwrap();
+//!     let cassette = Cassette::load(BufReader::new(file)).unwrap();
+//!     check_status(&mut Replayer::new(cassette));
+//! }
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use curl_sys;
+
+use Error;
+use error::{error_with_extra, error_with_url};
+use http::{Request, Response, Transport};
+
+/// A list of recorded exchanges of a request and its response.
+///
+/// See the [module documentation](index.html) for more information.
+#[derive(Clone, Debug, Default)]
+pub struct Cassette {
+    exchanges: Vec<Exchange>,
+}
+
+/// A request together with the response it got, as recorded in a
+/// `Cassette`.
+#[derive(Clone, Debug)]
+pub struct Exchange {
+    method: String,
+    url: String,
+    headers: Headers,
+    body: Option<Vec<u8>>,
+    response: Response,
+}
+
+/// A `Transport` which performs requests through another one, recording the
+/// exchanges into a `Cassette`.
+///
+/// Requests which fail with an error aren't recorded.
+pub struct Recorder<T> {
+    inner: T,
+    cassette: Cassette,
+}
+
+/// A `Transport` which answers requests with the responses recorded in a
+/// `Cassette`, without making any transfers.
+///
+/// By default a request matches a recorded exchange if it has the same
+/// method and URL. Each exchange is only played back once, in the order they
+/// were recorded, so a sequence of identical requests gets the sequence of
+/// responses recorded for it. A request which doesn't match any exchange left
+/// fails with an error.
+pub struct Replayer {
+    exchanges: Vec<(Exchange, bool)>,
+    match_body: bool,
+    match_headers: Vec<String>,
+}
+
+impl Cassette {
+    /// Creates a new, empty cassette.
+    pub fn new() -> Cassette {
+        Cassette::default()
+    }
+
+    /// Returns the exchanges recorded so far, in the order they happened.
+    pub fn exchanges(&self) -> &[Exchange] {
+        &self.exchanges
+    }
+
+    /// Records `request` as having received `response`.
+    pub fn push(&mut self, request: &Request, response: &Response) {
+        let headers = request.headers().map(|(name, value)| {
+            (name.to_string(), value.map(|v| v.to_string()))
+        });
+        // Only keep what `save` writes out, so a replayed response looks the
+        // same whether or not the cassette went through a file.
+        let response_headers = response.headers().collect::<Vec<_>>();
+        self.exchanges.push(Exchange {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: headers.collect(),
+            body: request.body_bytes().map(|b| b.to_vec()),
+            response: Response::from_parts(response.code(), &response_headers,
+                                           response.body().to_vec()),
+        });
+    }
+
+    /// Reads a cassette previously written with `save`.
+    pub fn load<R: BufRead>(mut reader: R) -> io::Result<Cassette> {
+        let mut cassette = Cassette::new();
+        while let Some(line) = try!(read_line(&mut reader)) {
+            if line.is_empty() {
+                continue
+            }
+            let mut parts = line.splitn(3, ' ');
+            let (method, url) = match (parts.next(), parts.next(),
+                                       parts.next()) {
+                (Some("request"), Some(method), Some(url)) => (method, url),
+                _ => return Err(invalid_data("expected a request")),
+            };
+            let (headers, body, next) = try!(read_message(&mut reader));
+            let line = match next {
+                Some(line) => Some(line),
+                None => try!(read_line(&mut reader)),
+            };
+            let code = match line.as_ref().map(|l| l.splitn(2, ' ')) {
+                Some(mut parts) => {
+                    match (parts.next(), parts.next().map(|c| c.parse())) {
+                        (Some("response"), Some(Ok(code))) => code,
+                        _ => return Err(invalid_data("expected a response")),
+                    }
+                }
+                None => return Err(invalid_data("expected a response")),
+            };
+            let (response_headers, response_body, next) =
+                try!(read_message(&mut reader));
+            if next.is_some() {
+                return Err(invalid_data("expected a request"))
+            }
+            let response_headers = response_headers.iter().map(|h| {
+                (&h.0[..], h.1.as_ref().map(|v| &v[..]).unwrap_or(""))
+            }).collect::<Vec<_>>();
+            let response_body = response_body.unwrap_or_else(Vec::new);
+            cassette.exchanges.push(Exchange {
+                method: method.to_string(),
+                url: url.to_string(),
+                headers: headers,
+                body: body,
+                response: Response::from_parts(code, &response_headers,
+                                               response_body),
+            });
+        }
+        Ok(cassette)
+    }
+
+    /// Writes this cassette in a line-based text format which can later be
+    /// read back with `load`.
+    ///
+    /// Each exchange starts with a `request <method> <url>` line followed by
+    /// a `header <name>: <value>` line for each header of the request, or an
+    /// `unset <name>` line for headers it unset. A `response <code>` line and
+    /// the headers of the response follow. Bodies are written as a `body
+    /// <length>` line followed by the raw bytes and a line break, so they
+    /// needn't be text.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for exchange in self.exchanges.iter() {
+            try!(writeln!(writer, "request {} {}", exchange.method,
+                          exchange.url));
+            for &(ref name, ref value) in exchange.headers.iter() {
+                match *value {
+                    Some(ref value) => {
+                        try!(writeln!(writer, "header {}: {}", name, value))
+                    }
+                    None => try!(writeln!(writer, "unset {}", name)),
+                }
+            }
+            if let Some(ref body) = exchange.body {
+                try!(write_body(&mut writer, body));
+            }
+            let response = &exchange.response;
+            try!(writeln!(writer, "response {}", response.code()));
+            for (name, value) in response.headers() {
+                try!(writeln!(writer, "header {}: {}", name, value));
+            }
+            try!(write_body(&mut writer, response.body()));
+            try!(writeln!(writer));
+        }
+        Ok(())
+    }
+}
+
+type Headers = Vec<(String, Option<String>)>;
+
+fn write_body<W: Write>(writer: &mut W, body: &[u8]) -> io::Result<()> {
+    try!(writeln!(writer, "body {}", body.len()));
+    try!(writer.write_all(body));
+    writeln!(writer)
+}
+
+type Message = (Headers, Option<Vec<u8>>, Option<String>);
+
+/// Reads the headers and body following a request or response line, up to
+/// the end of the body, the first blank line or a `response` line.
+///
+/// A `response` line ends a request without a body, and is handed back as
+/// the last element since it has been read already.
+fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Message> {
+    let mut headers = Vec::new();
+    loop {
+        let line = match try!(read_line(reader)) {
+            Some(ref line) if line.is_empty() => {
+                return Ok((headers, None, None))
+            }
+            Some(line) => line,
+            None => return Ok((headers, None, None)),
+        };
+        if line.starts_with("response ") {
+            return Ok((headers, None, Some(line)))
+        } else if line.starts_with("header ") {
+            let mut parts = line[7..].splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) => {
+                    let value = if value.starts_with(' ') {
+                        &value[1..]
+                    } else {
+                        value
+                    };
+                    headers.push((name.to_string(), Some(value.to_string())));
+                }
+                _ => return Err(invalid_data("malformed header")),
+            }
+        } else if line.starts_with("unset ") {
+            headers.push((line[6..].to_string(), None));
+        } else if line.starts_with("body ") {
+            let len = try!(line[5..].parse::<usize>().map_err(|_| {
+                invalid_data("malformed body length")
+            }));
+            let mut body = vec![0; len];
+            try!(reader.read_exact(&mut body));
+            let mut newline = [0; 1];
+            try!(reader.read_exact(&mut newline));
+            if newline != *b"\n" {
+                return Err(invalid_data("body is longer than its length"))
+            }
+            return Ok((headers, Some(body), None))
+        } else {
+            return Err(invalid_data("expected a header or body"))
+        }
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if try!(reader.read_until(b'\n', &mut line)) == 0 {
+        return Ok(None)
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    String::from_utf8(line).map(Some).map_err(|_| {
+        invalid_data("cassette isn't valid UTF-8 outside of bodies")
+    })
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Exchange {
+    /// Returns the method of the recorded request.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the URL of the recorded request.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the value of the first header named `name` of the recorded
+    /// request, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .filter(|h| h.0.eq_ignore_ascii_case(name))
+            .filter_map(|h| h.1.as_ref())
+            .map(|v| &v[..])
+            .next()
+    }
+
+    /// Returns the body of the recorded request, if it had one.
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_ref().map(|b| &b[..])
+    }
+
+    /// Returns the recorded response.
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+}
+
+impl<T: Transport> Recorder<T> {
+    /// Creates a recorder which performs requests through `inner`.
+    pub fn new(inner: T) -> Recorder<T> {
+        Recorder { inner: inner, cassette: Cassette::new() }
+    }
+
+    /// Returns the exchanges recorded so far.
+    pub fn cassette(&self) -> &Cassette {
+        &self.cassette
+    }
+
+    /// Returns the wrapped transport.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes the recorder, returning the exchanges it recorded.
+    pub fn into_cassette(self) -> Cassette {
+        self.cassette
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn execute(&mut self, request: &Request) -> Result<Response, Error> {
+        let response = try!(self.inner.execute(request));
+        self.cassette.push(request, &response);
+        Ok(response)
+    }
+}
+
+impl Replayer {
+    /// Creates a replayer which answers requests from `cassette`.
+    pub fn new(cassette: Cassette) -> Replayer {
+        Replayer {
+            exchanges: cassette.exchanges.into_iter()
+                                         .map(|e| (e, false))
+                                         .collect(),
+            match_body: false,
+            match_headers: Vec::new(),
+        }
+    }
+
+    /// Configures whether a request only matches an exchange if their bodies
+    /// are equal as well.
+    ///
+    /// By default bodies aren't compared.
+    pub fn match_body(&mut self, enable: bool) -> &mut Replayer {
+        self.match_body = enable;
+        self
+    }
+
+    /// Adds a header whose value must be the same for a request to match an
+    /// exchange.
+    ///
+    /// Names are compared case-insensitively, and a request which lacks the
+    /// header only matches exchanges which lack it as well. By default no
+    /// headers are compared.
+    pub fn match_header(&mut self, name: &str) -> &mut Replayer {
+        self.match_headers.push(name.to_string());
+        self
+    }
+
+    /// Returns the number of recorded exchanges which haven't been played
+    /// back yet.
+    pub fn remaining(&self) -> usize {
+        self.exchanges.iter().filter(|e| !e.1).count()
+    }
+
+    fn matches(&self, exchange: &Exchange, request: &Request) -> bool {
+        exchange.method == request.method() &&
+            exchange.url == request.url() &&
+            (!self.match_body || exchange.body() == request.body_bytes()) &&
+            self.match_headers.iter().all(|name| {
+                exchange.header(name) == request.header_value(name)
+            })
+    }
+}
+
+impl Transport for Replayer {
+    fn execute(&mut self, request: &Request) -> Result<Response, Error> {
+        let found = self.exchanges.iter().position(|&(ref e, used)| {
+            !used && self.matches(e, request)
+        });
+        match found {
+            Some(i) => {
+                self.exchanges[i].1 = true;
+                Ok(self.exchanges[i].0.response.clone())
+            }
+            None => {
+                let msg = format!("no recorded exchange matches {} {}",
+                                  request.method(), request.url());
+                let err = error_with_extra(curl_sys::CURLE_COULDNT_CONNECT,
+                                           msg.into());
+                Err(error_with_url(err, request.url()))
+            }
+        }
+    }
+}
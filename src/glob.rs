@@ -0,0 +1,415 @@
+//! Expansion of URL patterns in the style of the `curl` command line tool
+//!
+//! A pattern can contain any number of sets like `{one,two,three}` and
+//! ranges like `[1-100]`, `[001-100]` (zero padded), `[a-z]` or `[0-100:10]`
+//! (with a step). Expanding it yields one URL for every combination of their
+//! values, with the last one in the pattern varying fastest, just like
+//! `curl` does. The characters `{`, `}`, `[`, `]` and `,` can be escaped with
+//! a backslash to be used literally.
+//!
+//! The values a URL was made from can be substituted into a template such as
+//! `page_#1.html`, which is how `curl -o` names its output files.
+//!
+//! ```
+//! use curl::glob::UrlGlob;
+//!
+//! let glob = UrlGlob::parse("https://example.com/{a,b}/[1-2].txt").unwrap();
+//! let names = glob.iter()
+//!     .map(|m| m.output_name("#1_#2.txt"))
+//!     .collect::<Vec<_>>();
+//! assert_eq!(names, ["a_1.txt", "a_2.txt", "b_1.txt", "b_2.txt"]);
+//! ```
+
+use std::error;
+use std::fmt;
+
+/// A parsed URL pattern.
+///
+/// See the [module documentation](index.html) for more information.
+#[derive(Clone, Debug)]
+pub struct UrlGlob {
+    literals: Vec<String>,
+    patterns: Vec<Pattern>,
+    len: u64,
+}
+
+#[derive(Clone, Debug)]
+enum Pattern {
+    Set(Vec<String>),
+    Numeric { start: u64, step: u64, count: u64, width: usize },
+    Alpha { start: u8, step: u8, count: u64 },
+}
+
+/// One URL produced by expanding a `UrlGlob`, along with the values it was
+/// made from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobMatch {
+    url: String,
+    values: Vec<String>,
+}
+
+/// An iterator over the URLs a `UrlGlob` expands to, created by
+/// `UrlGlob::iter`.
+pub struct Iter<'a> {
+    glob: &'a UrlGlob,
+    indices: Vec<u64>,
+    done: bool,
+}
+
+/// An error returned from `UrlGlob::parse`.
+///
+/// The positions are byte offsets into the pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GlobError {
+    /// A `{` or `[` isn't closed, or a `}` or `]` wasn't opened.
+    Unbalanced(usize),
+
+    /// A `{` or `[` appears inside a set or range.
+    Nested(usize),
+
+    /// The contents of a `[...]` range are malformed.
+    InvalidRange(usize),
+
+    /// The pattern expands to more URLs than can be counted.
+    TooLarge,
+}
+
+impl UrlGlob {
+    /// Parses `pattern`.
+    pub fn parse(pattern: &str) -> Result<UrlGlob, GlobError> {
+        let mut glob = UrlGlob {
+            literals: vec![String::new()],
+            patterns: Vec::new(),
+            len: 0,
+        };
+        let mut pos = 0;
+        while let Some(c) = pattern[pos..].chars().next() {
+            match c {
+                '\\' => {
+                    match pattern[pos + 1..].chars().next() {
+                        Some(c) if is_special(c) => {
+                            glob.push_literal(c);
+                            pos += 1;
+                        }
+                        _ => glob.push_literal('\\'),
+                    }
+                }
+                '{' => {
+                    let (values, end) = try!(parse_set(pattern, pos));
+                    glob.push_pattern(Pattern::Set(values));
+                    pos = end;
+                }
+                '[' => {
+                    let end = match pattern[pos..].find(']') {
+                        Some(i) => pos + i,
+                        None => return Err(GlobError::Unbalanced(pos)),
+                    };
+                    let inner = &pattern[pos + 1..end];
+                    if is_ipv6(inner) {
+                        glob.push_str(&pattern[pos..end + 1]);
+                    } else {
+                        glob.push_pattern(try!(parse_range(inner, pos)));
+                    }
+                    pos = end;
+                }
+                '}' | ']' => return Err(GlobError::Unbalanced(pos)),
+                c => glob.push_literal(c),
+            }
+            pos += pattern[pos..].chars().next().unwrap().len_utf8();
+        }
+        glob.len = match glob.count() {
+            Some(len) => len,
+            None => return Err(GlobError::TooLarge),
+        };
+        Ok(glob)
+    }
+
+    /// Returns the number of URLs this pattern expands to.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns whether this pattern contains any sets or ranges.
+    pub fn is_glob(&self) -> bool {
+        !self.patterns.is_empty()
+    }
+
+    /// Returns an iterator over the URLs this pattern expands to.
+    pub fn iter(&self) -> Iter {
+        Iter {
+            glob: self,
+            indices: vec![0; self.patterns.len()],
+            done: self.len() == 0,
+        }
+    }
+
+    fn count(&self) -> Option<u64> {
+        self.patterns.iter().fold(Some(1), |count, p| {
+            count.and_then(|c| c.checked_mul(p.len()))
+        })
+    }
+
+    fn push_literal(&mut self, c: char) {
+        self.literals.last_mut().unwrap().push(c);
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.literals.last_mut().unwrap().push_str(s);
+    }
+
+    fn push_pattern(&mut self, pattern: Pattern) {
+        self.patterns.push(pattern);
+        self.literals.push(String::new());
+    }
+}
+
+impl<'a> IntoIterator for &'a UrlGlob {
+    type Item = GlobMatch;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = GlobMatch;
+
+    fn next(&mut self) -> Option<GlobMatch> {
+        if self.done {
+            return None
+        }
+        let mut url = self.glob.literals[0].clone();
+        let mut values = Vec::with_capacity(self.indices.len());
+        for (i, pattern) in self.glob.patterns.iter().enumerate() {
+            let value = pattern.value(self.indices[i]);
+            url.push_str(&value);
+            url.push_str(&self.glob.literals[i + 1]);
+            values.push(value);
+        }
+
+        // Advance like an odometer, the last pattern turning fastest.
+        self.done = true;
+        for (i, pattern) in self.glob.patterns.iter().enumerate().rev() {
+            self.indices[i] += 1;
+            if self.indices[i] < pattern.len() {
+                self.done = false;
+                break
+            }
+            self.indices[i] = 0;
+        }
+        Some(GlobMatch { url: url, values: values })
+    }
+}
+
+impl Pattern {
+    fn len(&self) -> u64 {
+        match *self {
+            Pattern::Set(ref values) => values.len() as u64,
+            Pattern::Numeric { count, .. } => count,
+            Pattern::Alpha { count, .. } => count,
+        }
+    }
+
+    fn value(&self, index: u64) -> String {
+        match *self {
+            Pattern::Set(ref values) => values[index as usize].clone(),
+            Pattern::Numeric { start, step, width, .. } => {
+                format!("{:01$}", start + index * step, width)
+            }
+            Pattern::Alpha { start, step, .. } => {
+                ((start as u64 + index * step as u64) as u8 as char)
+                    .to_string()
+            }
+        }
+    }
+}
+
+impl GlobMatch {
+    /// Returns the expanded URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the value each set or range of the pattern took for this URL,
+    /// in the order they appear in the pattern.
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+
+    /// Fills in `template` with the values of this match.
+    ///
+    /// Each `#N` in the template is replaced with the value of the `N`th set
+    /// or range of the pattern, counting from 1. A `#` which isn't followed
+    /// by the number of one of them is kept as it is.
+    pub fn output_name(&self, template: &str) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(i) = rest.find('#') {
+            out.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+            let digits = rest.bytes()
+                             .take_while(|b| b.is_ascii_digit())
+                             .count();
+            let value = rest[..digits].parse::<usize>().ok().and_then(|n| {
+                n.checked_sub(1).and_then(|n| self.values.get(n))
+            });
+            match value {
+                Some(value) => {
+                    out.push_str(value);
+                    rest = &rest[digits..];
+                }
+                None => out.push('#'),
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+fn is_special(c: char) -> bool {
+    match c {
+        '{' | '}' | '[' | ']' | ',' => true,
+        _ => false,
+    }
+}
+
+/// Parses the set starting with the `{` at `start`, returning its values and
+/// the position of its closing `}`.
+fn parse_set(pattern: &str, start: usize)
+             -> Result<(Vec<String>, usize), GlobError> {
+    let mut values = vec![String::new()];
+    let mut chars = pattern[start + 1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        let pos = start + 1 + i;
+        match c {
+            '\\' => {
+                let next = chars.clone().next();
+                let value = values.last_mut().unwrap();
+                match next {
+                    Some((_, c)) if is_special(c) => {
+                        chars.next();
+                        value.push(c);
+                    }
+                    _ => value.push('\\'),
+                }
+            }
+            ',' => values.push(String::new()),
+            '}' => return Ok((values, pos)),
+            '{' | '[' => return Err(GlobError::Nested(pos)),
+            ']' => return Err(GlobError::Unbalanced(pos)),
+            c => values.last_mut().unwrap().push(c),
+        }
+    }
+    Err(GlobError::Unbalanced(start))
+}
+
+/// Parses the contents of the range starting with the `[` at `start`.
+fn parse_range(inner: &str, start: usize) -> Result<Pattern, GlobError> {
+    let invalid = GlobError::InvalidRange(start);
+    if inner.contains('[') || inner.contains('{') {
+        let i = inner.find(|c| c == '[' || c == '{').unwrap();
+        return Err(GlobError::Nested(start + 1 + i))
+    }
+    let (range, step) = match inner.find(':') {
+        Some(i) => {
+            let step = try!(inner[i + 1..].parse::<u64>().map_err(|_| {
+                invalid.clone()
+            }));
+            (&inner[..i], step)
+        }
+        None => (inner, 1),
+    };
+    let mut parts = range.splitn(2, '-');
+    let (first, last) = match (parts.next(), parts.next()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(invalid),
+    };
+    if step == 0 {
+        return Err(invalid)
+    }
+
+    let alpha = |s: &str| {
+        let b = s.as_bytes();
+        if b.len() == 1 && (b[0] as char).is_ascii_alphabetic() {
+            Some(b[0])
+        } else {
+            None
+        }
+    };
+    if let (Some(first), Some(last)) = (alpha(first), alpha(last)) {
+        let same_case = (first as char).is_ascii_lowercase() ==
+            (last as char).is_ascii_lowercase();
+        if first > last || !same_case || step > 25 {
+            return Err(invalid)
+        }
+        return Ok(Pattern::Alpha {
+            start: first,
+            step: step as u8,
+            count: (last - first) as u64 / step + 1,
+        })
+    }
+
+    let digits = |s: &str| {
+        !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+    };
+    if !digits(first) || !digits(last) {
+        return Err(invalid)
+    }
+    let start_value = try!(first.parse::<u64>().map_err(|_| invalid.clone()));
+    let end_value = try!(last.parse::<u64>().map_err(|_| invalid.clone()));
+    if start_value > end_value {
+        return Err(invalid)
+    }
+    // Like `curl`, pad all values to the width of the first one if it has
+    // leading zeros.
+    let width = if first.len() > 1 && first.starts_with('0') {
+        first.len()
+    } else {
+        0
+    };
+    // `[0-18446744073709551615]` has one value more than fits in a `u64`.
+    let count = match ((end_value - start_value) / step).checked_add(1) {
+        Some(count) => count,
+        None => return Err(GlobError::TooLarge),
+    };
+    Ok(Pattern::Numeric {
+        start: start_value,
+        step: step,
+        count: count,
+        width: width,
+    })
+}
+
+/// Returns whether the contents of a `[...]` are an IPv6 address rather
+/// than a range.
+fn is_ipv6(inner: &str) -> bool {
+    let addr = inner.splitn(2, '%').next().unwrap();
+    addr.contains(':') &&
+        addr.chars().all(|c| c.is_digit(16) || c == ':' || c == '.')
+}
+
+impl fmt::Display for GlobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GlobError::Unbalanced(pos) |
+            GlobError::Nested(pos) |
+            GlobError::InvalidRange(pos) => {
+                write!(f, "{} at position {}",
+                       error::Error::description(self), pos)
+            }
+            GlobError::TooLarge => error::Error::description(self).fmt(f),
+        }
+    }
+}
+
+impl error::Error for GlobError {
+    fn description(&self) -> &str {
+        match *self {
+            GlobError::Unbalanced(..) => "unmatched brace or bracket",
+            GlobError::Nested(..) => "nested brace or bracket",
+            GlobError::InvalidRange(..) => "invalid range",
+            GlobError::TooLarge => "pattern expands to too many URLs",
+        }
+    }
+}
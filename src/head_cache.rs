@@ -0,0 +1,350 @@
+//! Caching of `HEAD` metadata for a set of URLs
+//!
+//! Mirroring tools typically need to answer the question "which of these URLs
+//! changed since I last looked at them?" before deciding what to download.
+//! The `HeadCache` type in this module remembers the `ETag`, `Content-Length`
+//! and `Last-Modified` headers of a set of URLs and refreshes them
+//! concurrently with `HEAD` requests driven by a `Multi` handle, reporting
+//! the differences as a `Diff`.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! use curl::head_cache::HeadCache;
+//!
+//! let mut cache = match File::open("listing.cache") {
+//!     Ok(f) => HeadCache::load(BufReader::new(f)).unwrap(),
+//!     Err(_) => HeadCache::new(),
+//! };
+//! let diff = cache.refresh(&["https://example.com/a.tar.gz",
+//!                            "https://example.com/b.tar.gz"]).unwrap();
+//! for url in diff.changed() {
+//!     println!("needs downloading: {}", url);
+//! }
+//! cache.save(File::create("listing.cache").unwrap()).unwrap();
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use curl_sys;
+
+use {Error, MultiError};
+use easy::Easy;
+use multi::Multi;
+
+/// A cache of `HEAD` metadata keyed by URL.
+///
+/// See the [module documentation](index.html) for more information.
+pub struct HeadCache {
+    entries: HashMap<String, Metadata>,
+    timeout: Duration,
+}
+
+/// Metadata learned about a URL from the headers of a `HEAD` request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    response_code: u32,
+    etag: Option<String>,
+    content_length: Option<u64>,
+    last_modified: Option<String>,
+}
+
+/// Result of refreshing a `HeadCache`, classifying each probed URL.
+#[derive(Debug, Default)]
+pub struct Diff {
+    added: Vec<String>,
+    modified: Vec<String>,
+    unchanged: Vec<String>,
+    failed: Vec<(String, Error)>,
+}
+
+impl HeadCache {
+    /// Creates a new, empty cache.
+    pub fn new() -> HeadCache {
+        HeadCache {
+            entries: HashMap::new(),
+            timeout: Duration::new(30, 0),
+        }
+    }
+
+    /// Configures the timeout applied to each individual `HEAD` request.
+    ///
+    /// By default this is 30 seconds.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut HeadCache {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the metadata last recorded for `url`, if any.
+    pub fn get(&self, url: &str) -> Option<&Metadata> {
+        self.entries.get(url)
+    }
+
+    /// Forgets everything known about `url`, returning the old metadata.
+    pub fn remove(&mut self, url: &str) -> Option<Metadata> {
+        self.entries.remove(url)
+    }
+
+    /// Issues a `HEAD` request for each of `urls` concurrently and compares
+    /// the results with what's currently in the cache.
+    ///
+    /// The cache is updated with the fresh metadata of every URL which could
+    /// be probed successfully. Entries for URLs which failed are left
+    /// untouched, and the failures are reported through `Diff::failed`.
+    ///
+    /// An error is only returned if the multi handle itself fails.
+    pub fn refresh<I, S>(&mut self, urls: I) -> Result<Diff, MultiError>
+        where I: IntoIterator<Item = S>,
+              S: AsRef<str>,
+    {
+        let multi = Multi::new();
+        let mut diff = Diff::default();
+        let mut pending = Vec::new();
+        for url in urls {
+            let url = url.as_ref().to_string();
+            let meta = Arc::new(Mutex::new(Metadata::default()));
+            match self.probe(&url, meta.clone()) {
+                Ok(easy) => {
+                    let mut handle = try!(multi.add(easy));
+                    if let Err(e) = handle.set_token(pending.len()) {
+                        diff.failed.push((url, e));
+                        try!(multi.remove(handle));
+                        continue
+                    }
+                    pending.push((url, meta, handle));
+                }
+                Err(e) => diff.failed.push((url, e)),
+            }
+        }
+
+        let mut results = pending.iter().map(|_| None).collect::<Vec<_>>();
+        let mut idle = 0;
+        loop {
+            let running = try!(multi.perform());
+            multi.messages(|msg| {
+                if let (Ok(token), Some(result)) = (msg.token(), msg.result()) {
+                    results[token] = Some(result);
+                }
+            });
+            if running == 0 {
+                break
+            }
+            // `wait` returns right away while there's no descriptor to wait
+            // on yet, so back off instead of spinning if that keeps up.
+            if try!(multi.wait(Duration::new(1, 0))) == 0 {
+                idle += 1;
+                if idle > 1 {
+                    thread::sleep(Duration::from_millis(100));
+                }
+            } else {
+                idle = 0;
+            }
+        }
+
+        for ((url, meta, handle), result) in pending.into_iter().zip(results) {
+            let mut easy = try!(multi.remove(handle));
+            // Every transfer should have reported completion by now, but be
+            // defensive in case libcurl never told us about one.
+            let result = match result {
+                Some(result) => result,
+                None => Err(Error::new(curl_sys::CURLE_FAILED_INIT)),
+            };
+            let code = match result.and_then(|()| easy.response_code()) {
+                Ok(code) => code,
+                Err(e) => {
+                    diff.failed.push((url, e));
+                    continue
+                }
+            };
+            let mut meta = meta.lock().unwrap().clone();
+            meta.response_code = code;
+            match self.entries.insert(url.clone(), meta.clone()) {
+                None => diff.added.push(url),
+                Some(ref old) if meta.differs_from(old) => {
+                    diff.modified.push(url)
+                }
+                Some(_) => diff.unchanged.push(url),
+            }
+        }
+        Ok(diff)
+    }
+
+    fn probe(&self, url: &str, meta: Arc<Mutex<Metadata>>)
+             -> Result<Easy, Error> {
+        let mut easy = Easy::new();
+        try!(easy.url(url));
+        try!(easy.nobody(true));
+        try!(easy.follow_location(true));
+        try!(easy.timeout(self.timeout));
+        try!(easy.header_function(move |line| {
+            meta.lock().unwrap().parse_header(line);
+            true
+        }));
+        Ok(easy)
+    }
+
+    /// Reads a cache previously written with `save`.
+    pub fn load<R: BufRead>(reader: R) -> io::Result<HeadCache> {
+        let mut cache = HeadCache::new();
+        for line in reader.lines() {
+            let line = try!(line);
+            if line.is_empty() {
+                continue
+            }
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != 5 {
+                return Err(invalid_data("wrong number of fields in cache"))
+            }
+            let code = try!(fields[1].parse().map_err(|_| {
+                invalid_data("invalid response code in cache")
+            }));
+            let content_length = match fields[2] {
+                "-" => None,
+                s => Some(try!(s.parse().map_err(|_| {
+                    invalid_data("invalid content length in cache")
+                }))),
+            };
+            let meta = Metadata {
+                response_code: code,
+                etag: optional_field(fields[3]),
+                content_length: content_length,
+                last_modified: optional_field(fields[4]),
+            };
+            cache.entries.insert(fields[0].to_string(), meta);
+        }
+        Ok(cache)
+    }
+
+    /// Writes the contents of this cache in a line-based text format which can
+    /// later be read back with `load`.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut urls = self.entries.keys().collect::<Vec<_>>();
+        urls.sort();
+        for url in urls {
+            let meta = &self.entries[url];
+            let length = meta.content_length.map(|l| l.to_string());
+            try!(writeln!(writer, "{}\t{}\t{}\t{}\t{}",
+                          url,
+                          meta.response_code,
+                          length.as_ref().map(|s| &s[..]).unwrap_or("-"),
+                          meta.etag.as_ref().map(|s| &s[..]).unwrap_or("-"),
+                          meta.last_modified.as_ref().map(|s| &s[..])
+                              .unwrap_or("-")));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn optional_field(s: &str) -> Option<String> {
+    if s == "-" {
+        None
+    } else {
+        Some(s.to_string())
+    }
+}
+
+impl Metadata {
+    /// Returns the response code of the final response to the `HEAD` request.
+    pub fn response_code(&self) -> u32 {
+        self.response_code
+    }
+
+    /// Returns the value of the `ETag` header, if one was sent.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the value of the `Content-Length` header, if one was sent.
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    /// Returns the value of the `Last-Modified` header, if one was sent.
+    pub fn last_modified(&self) -> Option<&str> {
+        self.last_modified.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns whether this metadata describes a different resource than
+    /// `other`.
+    ///
+    /// If both sides carry an `ETag` then only the tags are compared,
+    /// otherwise the content length and modification time are.
+    pub fn differs_from(&self, other: &Metadata) -> bool {
+        if self.response_code != other.response_code {
+            return true
+        }
+        match (&self.etag, &other.etag) {
+            (&Some(ref a), &Some(ref b)) => a != b,
+            _ => {
+                self.content_length != other.content_length ||
+                    self.last_modified != other.last_modified
+            }
+        }
+    }
+
+    fn parse_header(&mut self, line: &[u8]) {
+        let line = match str::from_utf8(line) {
+            Ok(s) => s.trim(),
+            Err(_) => return,
+        };
+        // Each response in a chain of redirects starts with a status line, and
+        // only the headers of the last one are interesting.
+        if line.starts_with("HTTP/") {
+            *self = Metadata::default();
+            return
+        }
+        let mut parts = line.splitn(2, ':');
+        let (name, value) = match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) => (name.trim(), value.trim()),
+            _ => return,
+        };
+        let value = value.replace('\t', " ");
+        if name.eq_ignore_ascii_case("etag") {
+            self.etag = Some(value);
+        } else if name.eq_ignore_ascii_case("content-length") {
+            self.content_length = value.parse().ok();
+        } else if name.eq_ignore_ascii_case("last-modified") {
+            self.last_modified = Some(value);
+        }
+    }
+}
+
+impl Diff {
+    /// URLs which weren't previously in the cache.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// URLs whose metadata changed since they were last probed.
+    pub fn modified(&self) -> &[String] {
+        &self.modified
+    }
+
+    /// URLs whose metadata is the same as when they were last probed.
+    pub fn unchanged(&self) -> &[String] {
+        &self.unchanged
+    }
+
+    /// URLs which couldn't be probed, along with the reason why.
+    pub fn failed(&self) -> &[(String, Error)] {
+        &self.failed
+    }
+
+    /// Returns all URLs which are either new or modified.
+    pub fn changed(&self) -> Vec<&str> {
+        self.added.iter()
+            .chain(self.modified.iter())
+            .map(|s| &s[..])
+            .collect()
+    }
+}
@@ -0,0 +1,4296 @@
+//! HTTP-specific helpers layered on top of the `Easy` handle
+//!
+//! The `Easy` type exposes libcurl more or less as-is. The utilities in this
+//! module build on top of it for common HTTP tasks that libcurl doesn't handle
+//! itself.
+//!
+//! The `Request` and `Response` types offer a higher level way of making a
+//! request on an `Easy` handle: a `Request` describes what to send, and
+//! performing it collects the headers and body of the reply into a
+//! `Response`.
+//!
+//! ```no_run
+//! use curl::easy::Easy;
+//! use curl::http::Request;
+//!
+//! let mut handle = Easy::new();
+//! let response = Request::get("https://www.rust-lang.org/")
+//!     .perform(&mut handle)
+//!     .unwrap();
+//! println!("{} bytes", response.body().len());
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::iter;
+use std::slice;
+use std::str;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use curl_sys;
+#[cfg(feature = "json")]
+use serde::Serialize;
+#[cfg(feature = "json")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "json")]
+use serde_json;
+
+use Error;
+use easy::{Easy, List, ReadError, SeekResult, TimeCondition, WriteError};
+use error::{error_with_extra, error_with_url, error_with_status,
+            error_with_body};
+use glob::{GlobError, GlobMatch, UrlGlob};
+use sink::{self, Algorithm, Hasher, Sha256};
+use zlib::{self, Compressor};
+
+/// The largest body `Request::perform` allocates up front based on the
+/// `Content-Length` of a response, so a bogus length can't exhaust memory
+/// before any data has arrived.
+const MAX_PRESIZE: u64 = 64 * 1024 * 1024;
+
+/// The `User-Agent` sent by a `Request` unless it's configured otherwise.
+pub const DEFAULT_USER_AGENT: &'static str =
+    concat!("curl-rust/", env!("CARGO_PKG_VERSION"));
+
+/// A description of an HTTP request which can be performed on an `Easy`
+/// handle.
+///
+/// Requests don't borrow a handle, so they can be cloned and performed any
+/// number of times, for example to poll a resource or retry a request
+/// which failed. Callbacks such as `on_header` and the hook registered with
+/// `sign` are shared by all clones of a request rather than copied.
+///
+/// ```no_run
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use curl::easy::Easy;
+/// use curl::http::Request;
+///
+/// let mut handle = Easy::new();
+/// let mut request = Request::get("https://example.com/status");
+/// request.header("Accept", "application/json");
+/// loop {
+///     let response = request.perform(&mut handle).unwrap();
+///     if response.code() == 200 {
+///         break
+///     }
+///     thread::sleep(Duration::from_secs(5));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Request {
+    method: String,
+    url: String,
+    mirrors: Vec<String>,
+    headers: Vec<(String, Option<String>)>,
+    body: Option<Vec<u8>>,
+    stream: Option<Stream>,
+    compression: Option<Encoding>,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    auto_referer: bool,
+    if_modified_since: Option<SystemTime>,
+    require_length: bool,
+    ignore_length: bool,
+    transfer_decoding: bool,
+    content_decoding: bool,
+    fail_on_error: bool,
+    error_body_limit: Option<usize>,
+    keep_sending: bool,
+    fresh_connect: bool,
+    forbid_reuse: bool,
+    path_as_is: bool,
+    on_header: Option<Callback<Fn(&str, &str) -> bool + Send + Sync>>,
+    on_informational: Option<Callback<Fn(u32, Headers) + Send + Sync>>,
+    on_length: Option<Callback<Fn(Option<u64>) -> bool + Send + Sync>>,
+    on_body: Option<Callback<Fn(&[u8]) + Send + Sync>>,
+    checksum: Option<(Algorithm, String)>,
+    signer: Option<Callback<Fn(&Signable) -> Vec<(String, String)> +
+                            Send + Sync>>,
+    raw_options: Vec<(curl_sys::CURLoption, i64)>,
+}
+
+/// A callback registered with a `Request`, shared between clones of the
+/// request.
+struct Callback<F: ?Sized>(Arc<F>);
+
+/// A body streamed from a reader, shared between clones of the request.
+#[derive(Clone)]
+struct Stream {
+    source: Arc<Mutex<Source>>,
+    len: Option<u64>,
+}
+
+enum Source {
+    /// A reader which can be rewound to the given position, where the body
+    /// starts.
+    Seekable(Box<ReadSeek + Send>, u64),
+    /// A reader which can't be rewound, along with whether anything has been
+    /// read from it yet.
+    Unseekable(Box<Read + Send>, bool),
+}
+
+trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// A reader over the chunks yielded by an iterator, set with
+/// `Request::body_chunks`.
+struct Chunks<I: Iterator> {
+    iter: I,
+    chunk: Option<I::Item>,
+    pos: usize,
+}
+
+impl<I> Read for Chunks<I>
+    where I: Iterator,
+          I::Item: AsRef<[u8]>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0)
+        }
+        loop {
+            if let Some(ref chunk) = self.chunk {
+                let rest = &chunk.as_ref()[self.pos..];
+                if !rest.is_empty() {
+                    let n = cmp::min(rest.len(), buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    self.pos += n;
+                    return Ok(n)
+                }
+            }
+            // Empty chunks are skipped, as reading nothing ends the body.
+            match self.iter.next() {
+                Some(chunk) => {
+                    self.chunk = Some(chunk);
+                    self.pos = 0;
+                }
+                None => {
+                    self.chunk = None;
+                    return Ok(0)
+                }
+            }
+        }
+    }
+}
+
+/// The response to a `Request`.
+#[derive(Clone, Debug, Default)]
+pub struct Response {
+    code: u32,
+    status_line: String,
+    http1: bool,
+    headers: Vec<(String, String)>,
+    headers_done: bool,
+    trailers: Vec<(String, String)>,
+    body: Vec<u8>,
+    truncated: bool,
+    mirror: Option<usize>,
+    eof_delimited: bool,
+    info: ResponseInfo,
+    effective_url: Option<String>,
+    primary_ip: Option<String>,
+    primary_port: u16,
+    local_ip: Option<String>,
+    local_port: u16,
+    condition_unmet: bool,
+}
+
+/// A parsed `Content-Type` header, as returned by `Response::content_type`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentType {
+    mime: String,
+    charset: Option<String>,
+}
+
+/// A cookie sent by a server in a `Set-Cookie` header, as returned by
+/// `Response::cookies`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    domain: Option<String>,
+    path: Option<String>,
+    expires: Option<SystemTime>,
+    max_age: Option<i64>,
+    secure: bool,
+    http_only: bool,
+}
+
+/// One range of a `206 Partial Content` response, as returned by
+/// `Response::byte_ranges` and `parse_byteranges`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    start: u64,
+    end: u64,
+    total: Option<u64>,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+/// An iterator over the headers of a `Response`, created by
+/// `Response::headers`.
+pub struct Headers<'a> {
+    inner: slice::Iter<'a, (String, String)>,
+}
+
+/// An iterator over the headers added to a `Request`, created by
+/// `Request::headers`.
+///
+/// Headers unset with `Request::unset_header` are yielded with a value of
+/// `None`.
+pub struct RequestHeaders<'a> {
+    inner: slice::Iter<'a, (String, Option<String>)>,
+}
+
+/// The parts of a request a signing hook registered with `Request::sign`
+/// computes its signature over.
+#[derive(Debug)]
+pub struct Signable<'a> {
+    method: &'a str,
+    url: &'a str,
+    headers: Vec<(&'a str, &'a str)>,
+    body: Option<&'a [u8]>,
+    body_digest: Option<[u8; 32]>,
+}
+
+/// Timing information about a completed transfer.
+///
+/// Except for `redirect`, each duration is measured from the start of the
+/// final request in a chain of redirects, so they grow from `namelookup` to
+/// `total`. See `Easy::total_time` and friends for what each phase covers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    namelookup: Duration,
+    connect: Duration,
+    appconnect: Duration,
+    pretransfer: Duration,
+    starttransfer: Duration,
+    total: Duration,
+    redirect: Duration,
+}
+
+/// Everything libcurl reports about a completed transfer, as returned by
+/// `Response::info`.
+///
+/// This gathers the figures which are typically logged for each request in
+/// one place.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResponseInfo {
+    status: u32,
+    effective_url: Option<String>,
+    scheme: Option<String>,
+    timing: Timing,
+    download_size: u64,
+    upload_size: u64,
+    header_size: u64,
+    request_size: u64,
+    download_speed: f64,
+    upload_speed: f64,
+    num_connects: u32,
+    redirect_count: u32,
+}
+
+/// An HTTP status code, such as `200` or `404`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode {
+    code: u32,
+}
+
+/// A content coding to compress the body of a `Request` with, set with
+/// `Request::compress_body`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// The gzip format, sent as `Content-Encoding: gzip`.
+    Gzip,
+    /// The zlib format, sent as `Content-Encoding: deflate`.
+    Deflate,
+}
+
+/// A cache of the `ETag`s last seen for a set of URLs, which makes repeated
+/// requests for them conditional.
+///
+/// Requests performed through `ETagCache::perform` are sent with an
+/// `If-None-Match` header whenever the cache knows an `ETag` for their URL,
+/// so polling an endpoint which hasn't changed only yields a `304 Not
+/// Modified` response without a body.
+///
+/// ```no_run
+/// use curl::easy::Easy;
+/// use curl::http::{ETagCache, Request};
+///
+/// let mut handle = Easy::new();
+/// let mut cache = ETagCache::new();
+/// let request = Request::get("https://example.com/feed.json");
+/// loop {
+///     let response = cache.perform(&request, &mut handle).unwrap();
+///     if !response.not_modified() {
+///         println!("{} new bytes", response.body().len());
+///     }
+///     # break
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ETagCache {
+    etags: HashMap<String, String>,
+}
+
+impl Request {
+    /// Creates a new request with the given method, such as `"GET"` or
+    /// `"DELETE"`, for `url`.
+    pub fn new(method: &str, url: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            url: url.to_string(),
+            mirrors: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+            stream: None,
+            compression: None,
+            user_agent: None,
+            referer: None,
+            auto_referer: false,
+            if_modified_since: None,
+            require_length: false,
+            ignore_length: false,
+            transfer_decoding: true,
+            content_decoding: true,
+            fail_on_error: false,
+            error_body_limit: None,
+            keep_sending: false,
+            fresh_connect: false,
+            forbid_reuse: false,
+            path_as_is: false,
+            on_header: None,
+            on_informational: None,
+            on_length: None,
+            on_body: None,
+            checksum: None,
+            signer: None,
+            raw_options: Vec::new(),
+        }
+    }
+
+    /// Creates a new `GET` request for `url`.
+    pub fn get(url: &str) -> Request {
+        Request::new("GET", url)
+    }
+
+    /// Creates a new `HEAD` request for `url`.
+    pub fn head(url: &str) -> Request {
+        Request::new("HEAD", url)
+    }
+
+    /// Creates a new `POST` request for `url`.
+    pub fn post(url: &str) -> Request {
+        Request::new("POST", url)
+    }
+
+    /// Creates a new `PUT` request for `url`.
+    pub fn put(url: &str) -> Request {
+        Request::new("PUT", url)
+    }
+
+    /// Creates a new `DELETE` request for `url`.
+    pub fn delete(url: &str) -> Request {
+        Request::new("DELETE", url)
+    }
+
+    /// Parses a `curl` command line, such as one copied from the developer
+    /// tools of a browser, into an equivalent request.
+    ///
+    /// The command is split into words like a POSIX shell would, including
+    /// support for quotes, `$'...'` strings and escaped line breaks. The
+    /// `\xNN` escapes of a `$'...'` string stand for bytes, so a string
+    /// which doesn't decode to valid UTF-8 is a syntax error. The options
+    /// corresponding to what a `Request` can describe are understood:
+    ///
+    /// * the URL, either as a plain argument or with `--url`
+    /// * `-X`/`--request`, `-I`/`--head` and `-G`/`--get`
+    /// * `-H`/`--header`, `-A`/`--user-agent`, `-e`/`--referer` and
+    ///   `-b`/`--cookie` with the cookies given inline
+    /// * `-d`/`--data`, `--data-raw`, `--data-binary`, `--data-ascii` and
+    ///   `--data-urlencode` with the data given inline
+    /// * `-z`/`--time-cond` with a date
+    /// * `-f`/`--fail`, `--path-as-is`, `--ignore-content-length` and `--raw`
+    ///
+    /// Options which only affect the output of `curl` or correspond to
+    /// options of the handle, such as `--compressed`, `-L`, `-s` or `-v`, are
+    /// ignored. Any other option is rejected with an error, as is reading
+    /// data or cookies from files.
+    ///
+    /// ```
+    /// use curl::http::Request;
+    ///
+    /// let request = Request::from_curl_command("\
+    ///     curl 'https://example.com/api' \\
+    ///       -H 'Content-Type: application/json' \\
+    ///       --data-raw '{\"id\":1}' --compressed").unwrap();
+    /// assert_eq!(request.method(), "POST");
+    /// assert_eq!(request.url(), "https://example.com/api");
+    /// ```
+    pub fn from_curl_command(command: &str)
+                             -> Result<Request, CurlCommandError> {
+        let words = match shell_words(command) {
+            Some(words) => words,
+            None => return Err(CurlCommandError::Syntax),
+        };
+        let mut words = words.into_iter();
+        match words.next() {
+            Some(ref word) if word == "curl" => {}
+            _ => return Err(CurlCommandError::NotCurl),
+        }
+
+        let mut request = Request::new("GET", "");
+        let mut url = None;
+        let mut method = None;
+        let mut head = false;
+        let mut get = false;
+        let mut data = Vec::<String>::new();
+        let mut cookies = Vec::new();
+        while let Some(word) = words.next() {
+            if !word.starts_with('-') || word == "-" {
+                url = Some(word);
+                continue
+            }
+            let (option, attached) = if word.starts_with("--") {
+                (word.clone(), None)
+            } else if word.len() > 2 &&
+                      b"XHAebdz".contains(&word.as_bytes()[1]) {
+                (word[..2].to_string(), Some(word[2..].to_string()))
+            } else if word.len() > 2 {
+                // A group of short flags such as `-sSL`, all of which are
+                // ignored.
+                for c in word[1..].chars() {
+                    if !"sSLvik#g".contains(c) {
+                        let option = format!("-{}", c);
+                        return Err(CurlCommandError::Unsupported(option))
+                    }
+                }
+                continue
+            } else {
+                (word.clone(), None)
+            };
+            let takes_value = match &option[..] {
+                "-X" | "--request" | "-H" | "--header" | "-A" |
+                "--user-agent" | "-e" | "--referer" | "-b" | "--cookie" |
+                "-d" | "--data" | "--data-ascii" | "--data-binary" |
+                "--data-raw" | "--data-urlencode" | "-z" | "--time-cond" |
+                "--url" => true,
+                _ => false,
+            };
+            let value = if takes_value {
+                match attached.or_else(|| words.next()) {
+                    Some(value) => value,
+                    None => return Err(CurlCommandError::MissingValue(option)),
+                }
+            } else {
+                String::new()
+            };
+            match &option[..] {
+                "--url" => url = Some(value),
+                "-X" | "--request" => method = Some(value),
+                "-I" | "--head" => head = true,
+                "-G" | "--get" => get = true,
+                "-H" | "--header" => {
+                    let trimmed = value.trim();
+                    if !trimmed.contains(':') && trimmed.ends_with(';') {
+                        request.header(&trimmed[..trimmed.len() - 1], "");
+                        continue
+                    }
+                    let mut parts = value.splitn(2, ':');
+                    let name = parts.next().unwrap_or("").trim();
+                    match parts.next().map(|v| v.trim()) {
+                        Some("") => { request.unset_header(name); }
+                        Some(v) => { request.header(name, v); }
+                        None => {
+                            return Err(CurlCommandError::Invalid(option,
+                                                                 value))
+                        }
+                    }
+                }
+                "-A" | "--user-agent" => { request.user_agent(&value); }
+                "-e" | "--referer" => {
+                    let referer = match value.find(";auto") {
+                        Some(i) => {
+                            request.auto_referer(true);
+                            &value[..i]
+                        }
+                        None => &value[..],
+                    };
+                    if !referer.is_empty() {
+                        request.referer(referer);
+                    }
+                }
+                "-b" | "--cookie" => {
+                    if !value.contains('=') {
+                        return Err(CurlCommandError::Invalid(option, value))
+                    }
+                    cookies.push(value);
+                }
+                "-d" | "--data" | "--data-ascii" | "--data-binary" => {
+                    if value.starts_with('@') {
+                        return Err(CurlCommandError::Invalid(option, value))
+                    }
+                    data.push(value);
+                }
+                "--data-raw" => data.push(value),
+                "--data-urlencode" => {
+                    // Like `curl`, what comes before the first `=` is a name
+                    // which isn't encoded, and without any `=` an `@` makes
+                    // the rest the name of a file to read the content from.
+                    let encoded = match value.find('=') {
+                        Some(0) => percent_encode(&value[1..]),
+                        Some(i) => {
+                            format!("{}={}", &value[..i],
+                                    percent_encode(&value[i + 1..]))
+                        }
+                        None if value.contains('@') => {
+                            return Err(CurlCommandError::Invalid(option,
+                                                                 value))
+                        }
+                        None => percent_encode(&value),
+                    };
+                    data.push(encoded);
+                }
+                "-z" | "--time-cond" => {
+                    match parse_http_date(&value) {
+                        Some(time) => { request.if_modified_since(time); }
+                        None => {
+                            return Err(CurlCommandError::Invalid(option,
+                                                                 value))
+                        }
+                    }
+                }
+                "-f" | "--fail" => { request.fail_on_error(true); }
+                "--path-as-is" => { request.path_as_is(true); }
+                "--ignore-content-length" => {
+                    request.ignore_content_length(true);
+                }
+                "--raw" => {
+                    request.transfer_decoding(false).content_decoding(false);
+                }
+                "--compressed" | "-L" | "--location" | "-s" | "--silent" |
+                "-S" | "--show-error" | "-v" | "--verbose" | "-i" |
+                "--include" | "-k" | "--insecure" | "-#" |
+                "--progress-bar" | "-g" | "--globoff" => {}
+                _ => return Err(CurlCommandError::Unsupported(option)),
+            }
+        }
+
+        let mut url = match url {
+            Some(url) => url,
+            None => return Err(CurlCommandError::MissingUrl),
+        };
+        if !cookies.is_empty() {
+            request.header("Cookie", &cookies.join("; "));
+        }
+        let implied = if head {
+            "HEAD"
+        } else if !data.is_empty() && !get {
+            "POST"
+        } else {
+            "GET"
+        };
+        if !data.is_empty() {
+            let data = data.join("&");
+            if get {
+                url.push(if url.contains('?') {'&'} else {'?'});
+                url.push_str(&data);
+            } else {
+                request.body(data);
+            }
+        }
+        request.method = method.unwrap_or_else(|| implied.to_string());
+        request.url = url;
+        Ok(request)
+    }
+
+    /// Returns the method of this request.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the URL this request is sent to.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the value of the first header named `name` added to this
+    /// request.
+    ///
+    /// Names are compared case-insensitively. Headers which were unset with
+    /// `unset_header` aren't returned.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .filter(|h| h.0.eq_ignore_ascii_case(name))
+            .filter_map(|h| h.1.as_ref())
+            .map(|v| &v[..])
+            .next()
+    }
+
+    /// Returns an iterator over the headers added to this request, in the
+    /// order they'll be sent.
+    pub fn headers(&self) -> RequestHeaders {
+        RequestHeaders { inner: self.headers.iter() }
+    }
+
+    /// Returns the body of this request, or `None` if it has no body or its
+    /// body is streamed from a reader.
+    pub fn body_bytes(&self) -> Option<&[u8]> {
+        self.body.as_ref().map(|b| &b[..])
+    }
+
+    /// Configures alternative URLs serving the same resource, which are tried
+    /// in order if the request can't be completed through its own URL.
+    ///
+    /// `perform` moves on to the next URL if the host can't be resolved or
+    /// connected to, if the transfer times out, or if the final response has
+    /// a 5xx status. The response from the last URL is returned as usual, and
+    /// `Response::mirror` tells which URL it came from. Mirrors aren't
+    /// included by `to_curl_command`.
+    ///
+    /// By default a request has no mirrors.
+    pub fn mirrors(&mut self, urls: &[&str]) -> &mut Request {
+        self.mirrors = urls.iter().map(|u| u.to_string()).collect();
+        self
+    }
+
+    /// Expands the URL of this request like the `curl` command line tool
+    /// does, returning a copy of this request for each resulting URL along
+    /// with the values it was made from.
+    ///
+    /// See the `glob` module for the supported syntax. The returned matches
+    /// can name an output file for each request with
+    /// `GlobMatch::output_name`.
+    ///
+    /// ```
+    /// use curl::http::Request;
+    ///
+    /// let requests = Request::get("https://example.com/page[1-3].html")
+    ///     .expand_glob()
+    ///     .unwrap();
+    /// for (m, request) in requests {
+    ///     println!("{} -> {}", request.url(), m.output_name("page_#1.html"));
+    /// }
+    /// ```
+    pub fn expand_glob(&self) -> Result<Vec<(GlobMatch, Request)>, GlobError> {
+        let glob = try!(UrlGlob::parse(&self.url));
+        Ok(glob.iter().map(|m| {
+            let mut request = self.clone();
+            request.url = m.url().to_string();
+            (m, request)
+        }).collect())
+    }
+
+    /// Adds a header to send with this request.
+    ///
+    /// Headers previously added with the same name are kept, so this can be
+    /// used to send a header multiple times. A header added here replaces any
+    /// header of the same name libcurl would otherwise generate itself, such
+    /// as `Accept` or `Content-Type`. An empty value sends the header without
+    /// a value.
+    pub fn header(&mut self, name: &str, value: &str) -> &mut Request {
+        self.headers.retain(|&(ref n, ref v)| {
+            v.is_some() || !n.eq_ignore_ascii_case(name)
+        });
+        self.headers.push((name.to_string(), Some(value.to_string())));
+        self
+    }
+
+    /// Sets a header to send with this request, replacing all headers of the
+    /// same name added previously.
+    ///
+    /// Names are compared case-insensitively. The header takes the place of
+    /// the first one it replaces, so the order headers are sent in stays the
+    /// same, or is added last if there's none. Like with `header`, the value
+    /// also replaces any header of the same name generated by libcurl.
+    pub fn set_header(&mut self, name: &str, value: &str) -> &mut Request {
+        let first = self.headers.iter().position(|h| {
+            h.0.eq_ignore_ascii_case(name)
+        });
+        let i = match first {
+            Some(i) => i,
+            None => return self.header(name, value),
+        };
+        self.headers[i] = (name.to_string(), Some(value.to_string()));
+        let mut rest = self.headers.split_off(i + 1);
+        rest.retain(|&(ref n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.extend(rest);
+        self
+    }
+
+    /// Removes all headers named `name` which were added to this request.
+    ///
+    /// Names are compared case-insensitively. This only affects headers added
+    /// with `header` or `set_header`, and undoes `unset_header`; headers
+    /// libcurl generates itself, such as `Host` or `Accept`, are still sent.
+    pub fn remove_header(&mut self, name: &str) -> &mut Request {
+        self.headers.retain(|&(ref n, _)| !n.eq_ignore_ascii_case(name));
+        self
+    }
+
+    /// Prevents a header named `name` from being sent at all, even one that
+    /// libcurl would generate itself such as `Accept`, `Expect` or
+    /// `Content-Type`.
+    ///
+    /// Headers of the same name added previously are removed, and adding one
+    /// again later on with `header` cancels this. Note that headers libcurl
+    /// needs to frame the request, like `Host` or `Content-Length`, shouldn't
+    /// be unset.
+    pub fn unset_header(&mut self, name: &str) -> &mut Request {
+        self.remove_header(name);
+        self.headers.push((name.to_string(), None));
+        self
+    }
+
+    /// Sets the body to upload with this request.
+    ///
+    /// This replaces a body set with `body_reader`, `body_stream` or
+    /// `body_chunks`. The body is sent with any method but `HEAD`, for which
+    /// libcurl doesn't support sending one.
+    pub fn body<B: Into<Vec<u8>>>(&mut self, body: B) -> &mut Request {
+        self.body = Some(body.into());
+        self.stream = None;
+        self
+    }
+
+    /// Sets a reader to stream the body of this request from, such as a
+    /// `File` or an `io::Cursor`.
+    ///
+    /// The body consists of everything from the current position of `reader`
+    /// to its end, and is read while the request is performed rather than
+    /// held in memory. Since the reader can seek, the body is rewound
+    /// whenever libcurl needs to send it again, for example when a `PUT`
+    /// is redirected with a `307` or meets an authentication challenge, and
+    /// the request can be performed any number of times.
+    ///
+    /// This replaces a body set with `body`, `body_stream` or `body_chunks`.
+    /// If the reader can't tell its position or length an error is returned.
+    pub fn body_reader<R>(&mut self, mut reader: R)
+                          -> io::Result<&mut Request>
+        where R: Read + Seek + Send + 'static
+    {
+        let start = try!(reader.seek(SeekFrom::Current(0)));
+        let end = try!(reader.seek(SeekFrom::End(0)));
+        try!(reader.seek(SeekFrom::Start(start)));
+        let source = Source::Seekable(Box::new(reader), start);
+        Ok(self.set_stream(source, Some(end.saturating_sub(start))))
+    }
+
+    /// Sets a reader to stream the body of this request from which can't be
+    /// rewound, such as a pipe or a socket.
+    ///
+    /// If `len` is `None`, the body is sent with the chunked transfer
+    /// encoding. The body can only be sent once: if libcurl needs to rewind
+    /// it, or the request is performed again, the transfer fails with an
+    /// error for which `is_send_fail_rewind` returns `true` instead of
+    /// sending a truncated body.
+    ///
+    /// This replaces a body set with `body`, `body_reader` or `body_chunks`.
+    pub fn body_stream<R>(&mut self, reader: R, len: Option<u64>)
+                          -> &mut Request
+        where R: Read + Send + 'static
+    {
+        self.set_stream(Source::Unseekable(Box::new(reader), false), len)
+    }
+
+    /// Sets an iterator of byte chunks to stream the body of this request
+    /// from, such as rows of a CSV export or serialized records generated on
+    /// the fly.
+    ///
+    /// The chunks are sent with the chunked transfer encoding as libcurl asks
+    /// for more data, so the body is never collected in memory as a whole. A
+    /// closure producing chunks can be used through `std::iter::from_fn`. As
+    /// with `body_stream`, the body can only be sent once.
+    ///
+    /// This replaces a body set with `body`, `body_reader` or `body_stream`.
+    ///
+    /// ```no_run
+    /// use curl::easy::Easy;
+    /// use curl::http::Request;
+    ///
+    /// let rows = (0..1000).map(|i| format!("{},{}\n", i, i * i));
+    /// let mut handle = Easy::new();
+    /// Request::post("https://example.com/import")
+    ///     .header("Content-Type", "text/csv")
+    ///     .body_chunks(rows)
+    ///     .perform(&mut handle)
+    ///     .unwrap();
+    /// ```
+    pub fn body_chunks<I>(&mut self, chunks: I) -> &mut Request
+        where I: IntoIterator,
+              I::IntoIter: Send + 'static,
+              I::Item: AsRef<[u8]> + Send + 'static,
+    {
+        let reader = Chunks { iter: chunks.into_iter(), chunk: None, pos: 0 };
+        self.body_stream(reader, None)
+    }
+
+    /// Compresses the body of this request with `encoding` as it's sent,
+    /// along with a `Content-Encoding` header naming it.
+    ///
+    /// A body set with `body` is compressed as a whole each time the request
+    /// is performed, while one streamed from a reader or iterator is
+    /// compressed through the read callback, as libcurl asks for more data,
+    /// and sent with the chunked transfer encoding since its compressed length
+    /// isn't known in advance. A compressed stream can only be rewound to its
+    /// start. A `Content-Encoding` header added to the request takes
+    /// precedence, and `to_curl_command` renders the body uncompressed.
+    ///
+    /// The server has to accept compressed request bodies, which many don't.
+    /// By default the body is sent as it is.
+    ///
+    /// ```no_run
+    /// use curl::easy::Easy;
+    /// use curl::http::{Encoding, Request};
+    ///
+    /// let mut handle = Easy::new();
+    /// Request::post("https://logs.example.com/ingest")
+    ///     .header("Content-Type", "application/json")
+    ///     .body(&br#"{"level":"info","message":"started"}"#[..])
+    ///     .compress_body(Encoding::Gzip)
+    ///     .perform(&mut handle)
+    ///     .unwrap();
+    /// ```
+    pub fn compress_body(&mut self, encoding: Encoding) -> &mut Request {
+        self.compression = Some(encoding);
+        self
+    }
+
+    fn set_stream(&mut self, source: Source, len: Option<u64>)
+                  -> &mut Request {
+        self.body = None;
+        self.stream = Some(Stream {
+            source: Arc::new(Mutex::new(source)),
+            len: len,
+        });
+        self
+    }
+
+    /// Serializes `value` as JSON and sets it as the body of this request,
+    /// along with a `Content-Type: application/json` header which replaces
+    /// any `Content-Type` added before.
+    ///
+    /// This is only available with the `json` feature of this crate.
+    #[cfg(feature = "json")]
+    pub fn json<T: ?Sized + Serialize>(&mut self, value: &T)
+                                       -> Result<&mut Request, JsonError> {
+        let body = try!(serde_json::to_vec(value).map_err(JsonError::Json));
+        self.set_header("Content-Type", "application/json");
+        Ok(self.body(body))
+    }
+
+    /// Sets the `User-Agent` to send with this request.
+    ///
+    /// By default `DEFAULT_USER_AGENT` is sent, as some servers reject
+    /// requests without a `User-Agent`. This corresponds to
+    /// `Easy::useragent`, so a `User-Agent` configured on the handle itself
+    /// is replaced when the request is performed. A `User-Agent` header added
+    /// to the request, or unset with `unset_header`, takes precedence.
+    pub fn user_agent(&mut self, user_agent: &str) -> &mut Request {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Sets the `Referer` to send with this request.
+    ///
+    /// By default no `Referer` is sent and this corresponds to
+    /// `Easy::referer`.
+    pub fn referer(&mut self, referer: &str) -> &mut Request {
+        self.referer = Some(referer.to_string());
+        self
+    }
+
+    /// Configures whether the `Referer` is updated automatically when a
+    /// redirect is followed, so that each hop is sent the URL which redirected
+    /// to it.
+    ///
+    /// This only has an effect if the handle follows redirects, see
+    /// `Easy::follow_location`. By default this is `false` and it corresponds
+    /// to `Easy::autoreferer`.
+    pub fn auto_referer(&mut self, enable: bool) -> &mut Request {
+        self.auto_referer = enable;
+        self
+    }
+
+    /// Makes this request conditional on the resource having been modified
+    /// after `time`.
+    ///
+    /// This sends an `If-Modified-Since` header, and if the server answers
+    /// with `304 Not Modified` then `Response::not_modified` returns `true`.
+    /// Only whole seconds are sent, and times before the Unix epoch are sent
+    /// as the epoch itself.
+    ///
+    /// By default requests aren't conditional, and this corresponds to
+    /// `Easy::time_condition` and `Easy::time_value`.
+    pub fn if_modified_since(&mut self, time: SystemTime) -> &mut Request {
+        self.if_modified_since = Some(time);
+        self
+    }
+
+    /// Makes this request conditional on the resource not matching `etag`,
+    /// by sending an `If-None-Match` header.
+    ///
+    /// This is typically given the `ETag` of a previous response, in which
+    /// case `Response::not_modified` returns `true` if the resource hasn't
+    /// changed since. A value set previously is replaced.
+    pub fn if_none_match(&mut self, etag: &str) -> &mut Request {
+        self.set_header("If-None-Match", etag)
+    }
+
+    /// Makes this request conditional on the resource matching `etag`, by
+    /// sending an `If-Match` header.
+    ///
+    /// Servers answer with `412 Precondition Failed` if the resource has
+    /// changed, which is useful to avoid overwriting concurrent modifications.
+    /// A value set previously is replaced.
+    pub fn if_match(&mut self, etag: &str) -> &mut Request {
+        self.set_header("If-Match", etag)
+    }
+
+    /// Configures whether the response body must be length-delimited.
+    ///
+    /// An HTTP/1 response which has neither a `Content-Length` nor a chunked
+    /// `Transfer-Encoding` is delimited by the server closing the connection,
+    /// which makes a truncated body indistinguishable from a complete one. If
+    /// this is enabled such responses are rejected with an error as soon as
+    /// their headers arrive, before any of the body is read.
+    ///
+    /// By default this is `false`, and `Response::is_eof_delimited` can be
+    /// used to detect such responses after the fact.
+    pub fn require_length_delimited(&mut self, require: bool)
+                                    -> &mut Request {
+        self.require_length = require;
+        self
+    }
+
+    /// Configures whether the `Content-Length` header of the response is
+    /// ignored.
+    ///
+    /// This is a workaround for servers which send a bogus length: the body
+    /// of a response which isn't chunked is then read until the server closes
+    /// the connection, and the response counts as EOF-delimited for the
+    /// purposes of `require_length_delimited` and
+    /// `Response::is_eof_delimited`.
+    ///
+    /// By default this is `false` and it corresponds to
+    /// `Easy::ignore_content_length`.
+    pub fn ignore_content_length(&mut self, ignore: bool) -> &mut Request {
+        self.ignore_length = ignore;
+        self
+    }
+
+    /// Configures whether the chunked transfer encoding of the response is
+    /// decoded.
+    ///
+    /// If disabled, the body of a chunked response is returned including the
+    /// chunk framing, exactly as it was sent on the wire.
+    ///
+    /// By default this is `true` and it corresponds to
+    /// `Easy::http_transfer_decoding`.
+    pub fn transfer_decoding(&mut self, enable: bool) -> &mut Request {
+        self.transfer_decoding = enable;
+        self
+    }
+
+    /// Configures whether a compressed response body is decoded.
+    ///
+    /// Bodies are only decompressed if the handle asks for compression
+    /// through `Easy::accept_encoding`, so this lets a body requested that
+    /// way be stored still compressed.
+    ///
+    /// By default this is `true` and it corresponds to
+    /// `Easy::http_content_decoding`.
+    pub fn content_decoding(&mut self, enable: bool) -> &mut Request {
+        self.content_decoding = enable;
+        self
+    }
+
+    /// Configures whether HTTP error responses are turned into errors.
+    ///
+    /// If enabled, a final response with a status of 400 or above makes
+    /// `perform` return an error for which `Error::kind` returns
+    /// `ErrorKind::Http` and `Error::body` returns the body of the response.
+    /// Unlike `Easy::fail_on_error` the whole response is still received
+    /// first.
+    ///
+    /// By default this is `false`.
+    pub fn fail_on_error(&mut self, fail: bool) -> &mut Request {
+        self.fail_on_error = fail;
+        self
+    }
+
+    /// Limits how much of the body of an HTTP error response is received.
+    ///
+    /// If set, the transfer of a final response with a status of 400 or
+    /// above is cut short once `limit` bytes of its body have arrived, which
+    /// avoids downloading large error pages which nobody reads. A limit of 0
+    /// stops the transfer right after the headers. The response is returned
+    /// as usual, or as an error if `fail_on_error` is enabled, and
+    /// `Response::is_truncated` tells whether part of the body was dropped.
+    /// A connection cut short this way can't be reused.
+    ///
+    /// By default this is `None`, and the whole body is always received.
+    pub fn error_body_limit(&mut self, limit: Option<usize>) -> &mut Request {
+        self.error_body_limit = limit;
+        self
+    }
+
+    /// Configures whether the body keeps being sent when the server responds
+    /// with an error before it has received all of it.
+    ///
+    /// This lets the detailed error response of servers which reject the
+    /// request early, but expect the whole body anyway, be received.
+    ///
+    /// By default this is `false` and it corresponds to
+    /// `Easy::keep_sending_on_error`.
+    pub fn keep_sending_on_error(&mut self, keep_sending: bool)
+                                 -> &mut Request {
+        self.keep_sending = keep_sending;
+        self
+    }
+
+    /// Configures whether this request must be made over a brand new
+    /// connection rather than one cached by the handle.
+    ///
+    /// By default this is `false` and it corresponds to
+    /// `Easy::fresh_connect`.
+    pub fn fresh_connect(&mut self, enable: bool) -> &mut Request {
+        self.fresh_connect = enable;
+        self
+    }
+
+    /// Configures whether the connection used by this request is closed once
+    /// the request is done instead of being kept for later reuse.
+    ///
+    /// By default this is `false` and it corresponds to
+    /// `Easy::forbid_reuse`.
+    pub fn forbid_reuse(&mut self, enable: bool) -> &mut Request {
+        self.forbid_reuse = enable;
+        self
+    }
+
+    /// Configures whether sequences like `/../` and `/./` in the path of the
+    /// URL are sent verbatim rather than squashed first.
+    ///
+    /// By default this is `false` and it corresponds to `Easy::path_as_is`.
+    pub fn path_as_is(&mut self, as_is: bool) -> &mut Request {
+        self.path_as_is = as_is;
+        self
+    }
+
+    /// Registers a callback which is invoked with the name and value of each
+    /// header of a response as soon as it arrives.
+    ///
+    /// This runs before any of the body is received, so it can be used to
+    /// inspect headers such as `Content-Type` or `Content-Length` and decide
+    /// whether the rest of the response is wanted at all. Returning `false`
+    /// aborts the transfer, making `perform` return an error for which
+    /// `is_write_error` returns `true`.
+    ///
+    /// The callback sees the headers of every final response, including
+    /// redirects followed by the handle, but not those of informational
+    /// `1xx` responses or trailers.
+    pub fn on_header<F>(&mut self, f: F) -> &mut Request
+        where F: Fn(&str, &str) -> bool + Send + Sync + 'static
+    {
+        self.on_header = Some(Callback(Arc::new(f)));
+        self
+    }
+
+    /// Registers a callback which is invoked with the status code and headers
+    /// of each informational `1xx` response received before the final one.
+    ///
+    /// The most interesting of these is `103 Early Hints`, whose `Link`
+    /// headers let a client start preloading resources while the server is
+    /// still preparing the final response. A `100 Continue` sent in reply to
+    /// a request with a body is reported as well. Informational responses
+    /// never end up in the returned `Response`.
+    pub fn on_informational<F>(&mut self, f: F) -> &mut Request
+        where F: Fn(u32, Headers) + Send + Sync + 'static
+    {
+        self.on_informational = Some(Callback(Arc::new(f)));
+        self
+    }
+
+    /// Registers a callback which is invoked with the announced length of
+    /// each response body once its headers are complete, before any of the
+    /// body arrives.
+    ///
+    /// The length is the value of the `Content-Length` header, or `None` if
+    /// the response doesn't announce one. This lets a consumer allocate room
+    /// for the body up front, or reject a download which is too large before
+    /// it's received. Returning `false` aborts the transfer, making `perform`
+    /// return an error for which `is_write_error` returns `true`.
+    ///
+    /// Like `on_header`, the callback is invoked for every final response,
+    /// including redirects followed by the handle, and it's invoked before
+    /// the first call to the callback registered with `on_body`.
+    pub fn on_length<F>(&mut self, f: F) -> &mut Request
+        where F: Fn(Option<u64>) -> bool + Send + Sync + 'static
+    {
+        self.on_length = Some(Callback(Arc::new(f)));
+        self
+    }
+
+    /// Registers a callback which is invoked with each chunk of the body of
+    /// the final response as it arrives.
+    ///
+    /// The chunks are the same ones which end up in `Response::body`, so this
+    /// can feed a digest or progress meter of choice without going over the
+    /// body again once the transfer is done. If the request fails over to a
+    /// mirror, the callback sees the body of each attempt.
+    pub fn on_body<F>(&mut self, f: F) -> &mut Request
+        where F: Fn(&[u8]) + Send + Sync + 'static
+    {
+        self.on_body = Some(Callback(Arc::new(f)));
+        self
+    }
+
+    /// Verifies the body of a successful response against a checksum.
+    ///
+    /// The body is hashed with `algorithm` while it streams in, and if the
+    /// digest of a `2xx` response doesn't match `hex` once the transfer is
+    /// done, `perform` returns an error for which `is_write_error` returns
+    /// `true`, with both digests in its `extra_description`. Responses with
+    /// other statuses, and those to a `HEAD` request, aren't checked. `hex`
+    /// is compared case-insensitively.
+    ///
+    /// By default no checksum is verified.
+    pub fn expect_checksum(&mut self, algorithm: Algorithm, hex: &str)
+                           -> &mut Request {
+        self.checksum = Some((algorithm, hex.to_lowercase()));
+        self
+    }
+
+    /// Registers a hook which signs the request just before it's sent, by
+    /// returning headers to add to it.
+    ///
+    /// The hook is invoked each time the request is performed, including for
+    /// each mirror it fails over to, once everything else about the request
+    /// has been configured. It sees the final method, URL and headers,
+    /// including the `User-Agent` and any `Easy::default_header`, so that a
+    /// signature covers what's actually sent. The headers it returns are
+    /// sent after all others. This is meant for HMAC schemes such as [HTTP
+    /// Message Signatures][sig] or the signatures of many internal APIs.
+    ///
+    /// [sig]: https://www.rfc-editor.org/rfc/rfc9421
+    ///
+    /// Headers which libcurl adds on its own, such as `Host`, `Accept` and
+    /// `Content-Length`, aren't seen by the hook. Set them explicitly with
+    /// `header` for them to be signed. Since the signature is computed while
+    /// performing the request, `to_curl_command` leaves its headers out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use curl::http::Request;
+    /// use curl::sink::to_hex;
+    ///
+    /// let mut request = Request::post("https://example.com/api");
+    /// request.body("{}").sign(|req| {
+    ///     let digest = req.body_digest().map(to_hex).unwrap_or_default();
+    ///     let line = format!("{} {} {}", req.method(), req.url(), digest);
+    ///     // A real hook would compute an HMAC of `line` here.
+    ///     vec![("X-Signature".to_string(), line)]
+    /// });
+    /// ```
+    pub fn sign<F>(&mut self, f: F) -> &mut Request
+        where F: Fn(&Signable) -> Vec<(String, String)> + Send + Sync +
+                 'static
+    {
+        self.signer = Some(Callback(Arc::new(f)));
+        self
+    }
+
+    /// Sets a numeric libcurl option on the handle when this request is
+    /// performed.
+    ///
+    /// This is for options without a dedicated method, see
+    /// `Easy::raw_option` for which options are accepted. Options are applied
+    /// in the order they were added, after everything else about the request
+    /// has been configured, so they can override it. Once the request is
+    /// done they're put back to libcurl's defaults, so they don't leak into
+    /// whatever is performed on the handle next, even if the handle had been
+    /// configured with a different value before.
+    pub fn raw_option(&mut self, opt: curl_sys::CURLoption, value: i64)
+                      -> &mut Request {
+        self.raw_options.push((opt, value));
+        self
+    }
+
+    /// Renders this request as an equivalent `curl` command line, suitable
+    /// for pasting into a shell.
+    ///
+    /// The method, URL, headers, user agent, referer, conditions, body and
+    /// flags such as `fail_on_error` are included, while options set with
+    /// `raw_option` and the options of the handle the request is eventually
+    /// performed on aren't. Neither are `error_body_limit`,
+    /// `keep_sending_on_error` and `expect_checksum`, which have no `curl`
+    /// flag, nor any callbacks. Bodies which aren't valid UTF-8 are rendered
+    /// lossily, and bodies streamed from a reader as `-T -`, which reads them
+    /// from standard input.
+    ///
+    /// If `redact` is set, credentials in the URL are removed and the values
+    /// of the `Authorization`, `Proxy-Authorization` and `Cookie` headers are
+    /// replaced, so the command can be shared in bug reports.
+    ///
+    /// ```
+    /// use curl::http::Request;
+    ///
+    /// let cmd = Request::get("https://example.com/")
+    ///     .header("Authorization", "Bearer secret")
+    ///     .user_agent("example/1.0")
+    ///     .to_curl_command(true);
+    /// assert_eq!(cmd, "curl https://example.com/ \
+    ///                  -H 'Authorization: <redacted>' -A example/1.0");
+    /// ```
+    pub fn to_curl_command(&self, redact: bool) -> String {
+        let mut args = vec!["curl".to_string()];
+        let head = self.method.eq_ignore_ascii_case("HEAD");
+        let implied = match (&self.body, &self.stream) {
+            (&Some(_), _) => "POST",
+            (_, &Some(_)) => "PUT",
+            _ => "GET",
+        };
+        if head {
+            args.push("--head".to_string());
+        } else if self.method != implied {
+            args.push("-X".to_string());
+            args.push(shell_quote(&self.method));
+        }
+        if redact {
+            args.push(shell_quote(&::error::redact_url(&self.url)));
+        } else {
+            args.push(shell_quote(&self.url));
+        }
+        for &(ref name, ref value) in self.headers.iter() {
+            let secret = name.eq_ignore_ascii_case("authorization") ||
+                name.eq_ignore_ascii_case("proxy-authorization") ||
+                name.eq_ignore_ascii_case("cookie");
+            let header = match *value {
+                Some(_) if redact && secret => format!("{}: <redacted>", name),
+                Some(ref value) if value.is_empty() => format!("{};", name),
+                Some(ref value) => format!("{}: {}", name, value),
+                None => format!("{}:", name),
+            };
+            args.push("-H".to_string());
+            args.push(shell_quote(&header));
+        }
+        args.push("-A".to_string());
+        match self.user_agent {
+            Some(ref user_agent) => args.push(shell_quote(user_agent)),
+            None => args.push(shell_quote(DEFAULT_USER_AGENT)),
+        }
+        match (&self.referer, self.auto_referer) {
+            (&Some(ref referer), true) => {
+                args.push("-e".to_string());
+                args.push(shell_quote(&format!("{};auto", referer)));
+            }
+            (&Some(ref referer), false) => {
+                args.push("-e".to_string());
+                args.push(shell_quote(referer));
+            }
+            (&None, true) => {
+                args.push("-e".to_string());
+                args.push(shell_quote(";auto"));
+            }
+            (&None, false) => {}
+        }
+        if let Some(time) = self.if_modified_since {
+            args.push("-z".to_string());
+            args.push(shell_quote(&format_http_date(time)));
+        }
+        if self.fail_on_error {
+            args.push("--fail".to_string());
+        }
+        if self.path_as_is {
+            args.push("--path-as-is".to_string());
+        }
+        if self.ignore_length {
+            args.push("--ignore-content-length".to_string());
+        }
+        if !self.transfer_decoding && !self.content_decoding {
+            args.push("--raw".to_string());
+        }
+        if let Some(ref body) = self.body {
+            args.push("--data-binary".to_string());
+            args.push(shell_quote(&String::from_utf8_lossy(body)));
+        }
+        if self.stream.is_some() {
+            args.push("-T".to_string());
+            args.push("-".to_string());
+        }
+        args.join(" ")
+    }
+
+    /// Configures `handle` for this request and performs it, collecting the
+    /// response in memory.
+    ///
+    /// The request's URL, method, headers, user agent, referer, body and
+    /// connection controls are set on the handle, replacing any header list
+    /// configured with `Easy::http_headers`. Headers added with
+    /// `Easy::default_header` are sent as well, unless the request adds or
+    /// unsets a header of the same name. Any other options of the handle are
+    /// used as they are. Each request sets all of the options above anew, so
+    /// one request never picks up the method, body or referer of another one
+    /// performed on the same handle before it. Options set with `raw_option`,
+    /// and progress reporting if it was turned on for metrics, are put back
+    /// to libcurl's defaults afterwards. The transfer is made through
+    /// `Easy::transfer`, so the write and header callbacks of the handle
+    /// aren't invoked.
+    ///
+    /// Once the headers of the final response arrive, the body is allocated
+    /// up front according to its `Content-Length` (within reason), so large
+    /// bodies are received without repeated reallocation. Each chunk of data
+    /// is copied exactly once, and `Response::into_body` hands the buffer out
+    /// without copying it again.
+    pub fn perform(&self, handle: &mut Easy) -> Result<Response, Error> {
+        let mut response = Response::default();
+        try!(self.perform_into(handle, &mut response));
+        Ok(response)
+    }
+
+    /// Performs this request like `perform`, but collects the response into
+    /// an existing `Response` rather than a new one.
+    ///
+    /// `response` is cleared first, keeping the allocations of its body and
+    /// header storage. Performing many requests into the same `Response`
+    /// therefore avoids allocating these buffers anew for each of them once
+    /// they've grown large enough.
+    ///
+    /// If an error is returned, `response` holds whatever was received before
+    /// the error occurred.
+    pub fn perform_into(&self, handle: &mut Easy, response: &mut Response)
+                        -> Result<(), Error> {
+        let metrics = handle.installed_metrics().cloned();
+        if let Some(ref metrics) = metrics {
+            metrics.on_request_start(self);
+        }
+        let result = self.perform_urls(handle, response,
+                                       metrics.as_ref().map(|m| &**m));
+        if let Some(ref metrics) = metrics {
+            match result {
+                Ok(()) => metrics.on_complete(self, Ok(response)),
+                Err(ref e) => metrics.on_complete(self, Err(e)),
+            }
+        }
+        result
+    }
+
+    fn perform_urls(&self, handle: &mut Easy, response: &mut Response,
+                    metrics: Option<&Metrics>) -> Result<(), Error> {
+        let mut urls = iter::once(&self.url).chain(&self.mirrors)
+                                            .enumerate()
+                                            .peekable();
+        while let Some((i, url)) = urls.next() {
+            let result = self.perform_url(url, handle, response, metrics);
+            let result = result.and(self.reset(handle, metrics.is_some()));
+            let fail_over = response.code >= 500 || match result {
+                Err(ref e) => {
+                    e.is_couldnt_resolve_host() || e.is_couldnt_connect() ||
+                        e.is_operation_timedout()
+                }
+                Ok(()) => false,
+            };
+            if fail_over && urls.peek().is_some() {
+                continue
+            }
+            response.mirror = i.checked_sub(1);
+            return result
+        }
+        unreachable!()
+    }
+
+    fn perform_url(&self, url: &str, handle: &mut Easy,
+                   response: &mut Response, metrics: Option<&Metrics>)
+                   -> Result<(), Error> {
+        try!(self.configure(url, handle));
+        if metrics.is_some() {
+            try!(handle.progress(true));
+        }
+
+        response.clear();
+        let head = self.method.eq_ignore_ascii_case("HEAD");
+        let response = RefCell::new(response);
+        let rejected = Cell::new(false);
+        let aborted = Cell::new(false);
+        let refused = Cell::new(false);
+        let truncated = Cell::new(false);
+        let unrewindable = Cell::new(false);
+        let hasher = RefCell::new(self.checksum.as_ref().map(|c| {
+            Hasher::new(c.0)
+        }));
+        let sent = Cell::new(0);
+        let received = Cell::new(0);
+        let report = |uploaded: f64, downloaded: f64| {
+            if let Some(metrics) = metrics {
+                let (uploaded, downloaded) = (uploaded as u64,
+                                              downloaded as u64);
+                if uploaded > sent.get() {
+                    metrics.on_bytes_sent(uploaded - sent.get());
+                    sent.set(uploaded);
+                }
+                if downloaded > received.get() {
+                    metrics.on_bytes_received(downloaded - received.get());
+                    received.set(downloaded);
+                }
+            }
+        };
+        let upload = self.stream.as_ref().map(|stream| {
+            RefCell::new(Upload::new(stream, self.compression))
+        });
+        let result = {
+            let mut transfer = handle.transfer();
+            if metrics.is_some() {
+                try!(transfer.progress_function(|_, dlnow, _, ulnow| {
+                    report(ulnow, dlnow);
+                    true
+                }));
+            }
+            if let Some(ref upload) = upload {
+                if !upload.borrow_mut().rewind(0) {
+                    return Err(error_with_url(stream_error(), url))
+                }
+                let unrewindable = &unrewindable;
+                try!(transfer.read_function(move |buf| {
+                    upload.borrow_mut().read(buf)
+                }));
+                try!(transfer.seek_function(move |from| {
+                    let ok = match from {
+                        SeekFrom::Start(offset) => {
+                            upload.borrow_mut().rewind(offset)
+                        }
+                        _ => false,
+                    };
+                    if ok {
+                        SeekResult::Ok
+                    } else {
+                        unrewindable.set(true);
+                        SeekResult::Fail
+                    }
+                }));
+            }
+            try!(transfer.header_function(|line| {
+                let mut response = response.borrow_mut();
+                let count = response.headers.len();
+                let done = response.parse_header(line);
+                if let Some(Callback(ref f)) = self.on_header {
+                    if response.code >= 200 && response.headers.len() > count {
+                        let (ref name, ref value) = response.headers[count];
+                        if !f(name, value) {
+                            aborted.set(true);
+                            return false
+                        }
+                    }
+                }
+                if let Some(Callback(ref f)) = self.on_informational {
+                    if done && response.code >= 100 && response.code < 200 {
+                        f(response.code, response.headers());
+                    }
+                }
+                if done && response.code >= 200 {
+                    if let Some(Callback(ref f)) = self.on_length {
+                        if !f(response.content_length()) {
+                            refused.set(true);
+                            return false
+                        }
+                    }
+                    response.eof_delimited =
+                        !head && response.lacks_framing(self.ignore_length);
+                    if self.require_length && response.eof_delimited {
+                        rejected.set(true);
+                        return false
+                    }
+                    if !head && !self.ignore_length {
+                        let limit = match self.error_body_limit {
+                            Some(limit) if response.code >= 400 => limit,
+                            _ => usize::max_value(),
+                        };
+                        response.presize_body(limit);
+                    }
+                }
+                true
+            }));
+            try!(transfer.write_function(|data| {
+                let mut response = response.borrow_mut();
+                let room = match self.error_body_limit {
+                    Some(limit) if response.code >= 400 => {
+                        limit.saturating_sub(response.body.len())
+                    }
+                    _ => data.len(),
+                };
+                if room < data.len() {
+                    // Taking less than all of the data aborts the transfer.
+                    truncated.set(true);
+                }
+                let data = &data[..cmp::min(room, data.len())];
+                response.body.extend_from_slice(data);
+                if let Some(ref mut hasher) = *hasher.borrow_mut() {
+                    hasher.update(data);
+                }
+                if let Some(Callback(ref f)) = self.on_body {
+                    f(data);
+                }
+                Ok(data.len())
+            }));
+            transfer.perform()
+        };
+        if metrics.is_some() {
+            // The final progress update may not have been made if the
+            // transfer failed, so catch up with the totals of the handle.
+            if let (Ok(up), Ok(down)) = (handle.upload_size(),
+                                         handle.download_size()) {
+                report(up as f64, down as f64);
+            }
+        }
+        match result {
+            Ok(()) => {}
+            Err(..) if truncated.get() => {}
+            Err(..) if unrewindable.get() => {
+                return Err(error_with_url(stream_error(), url))
+            }
+            Err(..) if aborted.get() => {
+                let msg = "transfer aborted by header callback";
+                let err = error_with_extra(curl_sys::CURLE_WRITE_ERROR,
+                                           msg.into());
+                return Err(error_with_url(err, url))
+            }
+            Err(..) if refused.get() => {
+                let msg = "transfer aborted by length callback";
+                let err = error_with_extra(curl_sys::CURLE_WRITE_ERROR,
+                                           msg.into());
+                return Err(error_with_url(err, url))
+            }
+            Err(..) if rejected.get() => {
+                let msg = "response body is not length-delimited";
+                let err = error_with_extra(curl_sys::CURLE_WRITE_ERROR,
+                                           msg.into());
+                return Err(error_with_url(err, url))
+            }
+            Err(e) => return Err(e),
+        }
+        let response = response.into_inner();
+        response.truncated = truncated.get();
+        response.info = try!(ResponseInfo::from_handle(handle));
+        response.effective_url =
+            try!(handle.effective_url()).map(|s| s.to_string());
+        response.primary_ip = try!(handle.primary_ip()).map(|s| s.to_string());
+        response.primary_port = try!(handle.primary_port());
+        response.local_ip = try!(handle.local_ip()).map(|s| s.to_string());
+        response.local_port = try!(handle.local_port());
+        response.condition_unmet = try!(handle.condition_unmet());
+        if let (Some(hasher), Some(&(_, ref expected))) =
+            (hasher.into_inner(), self.checksum.as_ref()) {
+            let actual = sink::to_hex(&hasher.finish());
+            if !head && response.code >= 200 && response.code < 300 &&
+               actual != *expected {
+                let msg = format!("checksum mismatch: expected {}, got {}",
+                                  expected, actual);
+                let err = error_with_extra(curl_sys::CURLE_WRITE_ERROR,
+                                           msg.into());
+                return Err(error_with_url(err, url))
+            }
+        }
+        if self.fail_on_error && response.code >= 400 {
+            return Err(response.to_error())
+        }
+        Ok(())
+    }
+
+    /// Puts the options which only some requests set back to libcurl's
+    /// defaults, so they don't apply to the next request on the handle.
+    fn reset(&self, handle: &mut Easy, metrics: bool) -> Result<(), Error> {
+        if metrics {
+            try!(handle.progress(false));
+        }
+        for &(opt, _) in self.raw_options.iter() {
+            try!(handle.raw_option(opt, default_value(opt)));
+        }
+        Ok(())
+    }
+
+    fn configure(&self, url: &str, handle: &mut Easy) -> Result<(), Error> {
+        try!(handle.url(url));
+        // Everything a request configures is set again each time, so nothing
+        // carries over from the previous request performed on the handle.
+        try!(::clear_option(handle, curl_sys::CURLOPT_CUSTOMREQUEST));
+        if let Some(ref stream) = self.stream {
+            // Uploads are sent like a `PUT`, but under whatever name the
+            // method has.
+            try!(handle.upload(true));
+            try!(handle.custom_request(&self.method));
+            let len = match stream.len {
+                Some(len) if self.compression.is_none() => len as i64,
+                _ => -1,
+            };
+            try!(handle.raw_option(curl_sys::CURLOPT_INFILESIZE_LARGE, len));
+        } else {
+            try!(handle.upload(false));
+            match &self.method[..] {
+                "GET" => try!(handle.get(true)),
+                "HEAD" => try!(handle.nobody(true)),
+                "POST" => try!(handle.post(true)),
+                method => {
+                    // Other methods are a `GET` under a different name, unless
+                    // they have a body, rather than whatever was sent last.
+                    try!(handle.get(true));
+                    try!(handle.custom_request(method));
+                }
+            }
+        }
+        let compressed = match (&self.body, self.compression) {
+            (&Some(ref body), Some(encoding)) => {
+                Some(zlib::compress(body, encoding == Encoding::Gzip))
+            }
+            _ => None,
+        };
+        let body = compressed.as_ref().or(self.body.as_ref()).map(|b| &b[..]);
+        match body {
+            // libcurl never sends a body along with a `HEAD` request.
+            Some(_) if self.method == "HEAD" => {}
+            Some(body) => {
+                try!(handle.post_fields_copy(body));
+                // A body turns the transfer into a `POST`, so any other
+                // method, `GET` included, has to be named again.
+                if self.method != "POST" {
+                    try!(handle.custom_request(&self.method));
+                }
+            }
+            // Don't send the body of a previous `POST` again.
+            None if self.stream.is_none() && self.method == "POST" => {
+                try!(handle.post_fields_copy(&[]))
+            }
+            None => {}
+        }
+        match self.user_agent {
+            Some(ref user_agent) => try!(handle.useragent(user_agent)),
+            None => try!(handle.useragent(DEFAULT_USER_AGENT)),
+        }
+        match self.referer {
+            Some(ref referer) => try!(handle.referer(referer)),
+            None => try!(::clear_option(handle, curl_sys::CURLOPT_REFERER)),
+        }
+        try!(handle.autoreferer(self.auto_referer));
+        match self.if_modified_since {
+            Some(time) => {
+                let secs = time.duration_since(UNIX_EPOCH)
+                               .map(|d| d.as_secs())
+                               .unwrap_or(0);
+                try!(handle.time_condition(TimeCondition::IfModifiedSince));
+                try!(handle.time_value(secs as i64));
+            }
+            None => try!(handle.time_condition(TimeCondition::None)),
+        }
+        try!(handle.fresh_connect(self.fresh_connect));
+        try!(handle.forbid_reuse(self.forbid_reuse));
+        try!(handle.path_as_is(self.path_as_is));
+        try!(handle.keep_sending_on_error(self.keep_sending));
+        try!(handle.ignore_content_length(self.ignore_length));
+        try!(handle.http_transfer_decoding(self.transfer_decoding));
+        try!(handle.http_content_decoding(self.content_decoding));
+        let mut list = List::new();
+        {
+            let mut headers = Vec::new();
+            for &(ref name, ref value) in handle.default_headers() {
+                if self.headers.iter()
+                               .any(|h| h.0.eq_ignore_ascii_case(name)) {
+                    continue
+                }
+                headers.push((&name[..], Some(&value[..])));
+            }
+            for &(ref name, ref value) in self.headers.iter() {
+                headers.push((&name[..], value.as_ref().map(|v| &v[..])));
+            }
+            if let Some(encoding) = self.compression {
+                let has_body = body.is_some() || self.stream.is_some();
+                let named = headers.iter().any(|h| {
+                    h.0.eq_ignore_ascii_case("content-encoding")
+                });
+                if has_body && !named {
+                    headers.push(("Content-Encoding", Some(encoding.token())));
+                }
+            }
+            let signed = match self.signer {
+                Some(Callback(ref sign)) => {
+                    sign(&self.signable(url, &headers, body))
+                }
+                None => Vec::new(),
+            };
+            for &(name, value) in headers.iter() {
+                try!(list.append(&format_header(name, value)));
+            }
+            for &(ref name, ref value) in signed.iter() {
+                try!(list.append(&format_header(name, Some(value))));
+            }
+        }
+        try!(handle.http_headers(list));
+        for &(opt, value) in self.raw_options.iter() {
+            try!(handle.raw_option(opt, value));
+        }
+        Ok(())
+    }
+
+    /// Collects what a signing hook sees of this request when it's sent to
+    /// `url` with `headers`.
+    fn signable<'a>(&'a self, url: &'a str,
+                    headers: &[(&'a str, Option<&'a str>)],
+                    body: Option<&'a [u8]>) -> Signable<'a> {
+        let user_agent = match self.user_agent {
+            Some(ref user_agent) => &user_agent[..],
+            None => DEFAULT_USER_AGENT,
+        };
+        let mut sent = vec![("User-Agent", user_agent)];
+        sent.extend(headers.iter().filter_map(|&(name, value)| {
+            value.map(|value| (name, value))
+        }));
+        let digested = match (body, &self.stream) {
+            (Some(body), _) => Some(body),
+            (None, &None) => Some(&[][..]),
+            (None, &Some(_)) => None,
+        };
+        Signable {
+            method: &self.method,
+            url: url,
+            headers: sent,
+            body: body,
+            body_digest: digested.map(|body| {
+                let mut sha = Sha256::new();
+                sha.update(body);
+                sha.finish()
+            }),
+        }
+    }
+}
+
+impl Stream {
+    /// Moves the reader to `offset` bytes into the body, returning whether
+    /// that was possible.
+    fn rewind(&self, offset: u64) -> bool {
+        match *self.source.lock().unwrap() {
+            Source::Seekable(ref mut reader, start) => {
+                reader.seek(SeekFrom::Start(start + offset)).is_ok()
+            }
+            Source::Unseekable(_, read) => offset == 0 && !read,
+        }
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        let result = match *self.source.lock().unwrap() {
+            Source::Seekable(ref mut reader, _) => reader.read(buf),
+            Source::Unseekable(ref mut reader, ref mut read) => {
+                *read = true;
+                reader.read(buf)
+            }
+        };
+        result.map_err(|_| ReadError::Abort)
+    }
+}
+
+/// The state of sending a body streamed from a reader during one transfer.
+struct Upload<'a> {
+    stream: &'a Stream,
+    encoder: Option<Encoder>,
+}
+
+/// A compressor along with the data read from the stream but not yet
+/// compressed.
+struct Encoder {
+    compressor: Compressor,
+    input: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl<'a> Upload<'a> {
+    fn new(stream: &'a Stream, encoding: Option<Encoding>) -> Upload<'a> {
+        Upload {
+            stream: stream,
+            encoder: encoding.map(|encoding| Encoder {
+                compressor: Compressor::new(encoding == Encoding::Gzip),
+                input: Vec::new(),
+                pos: 0,
+                eof: false,
+                done: false,
+            }),
+        }
+    }
+
+    /// Moves back to `offset` bytes into the body as it's sent, returning
+    /// whether that was possible.
+    fn rewind(&mut self, offset: u64) -> bool {
+        let encoder = match self.encoder {
+            Some(ref mut encoder) => encoder,
+            None => return self.stream.rewind(offset),
+        };
+        // There's no telling where in the reader an offset into the
+        // compressed body is, so only starting over works.
+        if offset != 0 || !self.stream.rewind(0) {
+            return false
+        }
+        encoder.compressor.reset();
+        encoder.input.clear();
+        encoder.pos = 0;
+        encoder.eof = false;
+        encoder.done = false;
+        true
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ReadError> {
+        let encoder = match self.encoder {
+            Some(ref mut encoder) => encoder,
+            None => return self.stream.read(buf),
+        };
+        if buf.is_empty() {
+            return Ok(0)
+        }
+        loop {
+            if encoder.done {
+                return Ok(0)
+            }
+            if encoder.pos == encoder.input.len() && !encoder.eof {
+                encoder.input.resize(16 * 1024, 0);
+                let n = try!(self.stream.read(&mut encoder.input));
+                encoder.input.truncate(n);
+                encoder.pos = 0;
+                encoder.eof = n == 0;
+            }
+            let (consumed, produced, done) = {
+                let input = &encoder.input[encoder.pos..];
+                encoder.compressor.compress(input, buf, encoder.eof)
+            };
+            encoder.pos += consumed;
+            encoder.done = done;
+            if produced > 0 {
+                return Ok(produced)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stream").field("len", &self.len).finish()
+    }
+}
+
+/// Formats a header for `Easy::http_headers`, where a value of `None` stops
+/// libcurl from sending the header at all.
+fn format_header(name: &str, value: Option<&str>) -> String {
+    // libcurl drops headers without a value after the colon, including its
+    // own ones, and sends headers ending in a semicolon as empty.
+    match value {
+        Some("") => format!("{};", name),
+        Some(value) => format!("{}: {}", name, value),
+        None => format!("{}:", name),
+    }
+}
+
+/// Returns libcurl's default for a numeric option, which is 0 for all but a
+/// few of them.
+fn default_value(opt: curl_sys::CURLoption) -> i64 {
+    match opt {
+        curl_sys::CURLOPT_INFILESIZE |
+        curl_sys::CURLOPT_INFILESIZE_LARGE |
+        curl_sys::CURLOPT_POSTFIELDSIZE |
+        curl_sys::CURLOPT_POSTFIELDSIZE_LARGE |
+        curl_sys::CURLOPT_MAXREDIRS |
+        curl_sys::CURLOPT_PROTOCOLS |
+        curl_sys::CURLOPT_SSH_AUTH_TYPES => -1,
+        curl_sys::CURLOPT_NOPROGRESS |
+        curl_sys::CURLOPT_SSL_VERIFYPEER |
+        curl_sys::CURLOPT_DOH_SSL_VERIFYPEER |
+        curl_sys::CURLOPT_FTP_USE_EPSV |
+        curl_sys::CURLOPT_FTP_USE_EPRT |
+        curl_sys::CURLOPT_FTP_SKIP_PASV_IP |
+        curl_sys::CURLOPT_FTP_FILEMETHOD |
+        curl_sys::CURLOPT_SSL_SESSIONID_CACHE |
+        curl_sys::CURLOPT_TCP_NODELAY |
+        curl_sys::CURLOPT_HTTP_TRANSFER_DECODING |
+        curl_sys::CURLOPT_HTTP_CONTENT_DECODING |
+        curl_sys::CURLOPT_RTSP_REQUEST => 1,
+        curl_sys::CURLOPT_SSL_VERIFYHOST |
+        curl_sys::CURLOPT_DOH_SSL_VERIFYHOST => 2,
+        curl_sys::CURLOPT_HTTPAUTH |
+        curl_sys::CURLOPT_PROXYAUTH => curl_sys::CURLAUTH_BASIC as i64,
+        curl_sys::CURLOPT_REDIR_PROTOCOLS => {
+            (curl_sys::CURLPROTO_HTTP | curl_sys::CURLPROTO_HTTPS |
+             curl_sys::CURLPROTO_FTP | curl_sys::CURLPROTO_FTPS) as i64
+        }
+        curl_sys::CURLOPT_MAXCONNECTS => 5,
+        curl_sys::CURLOPT_DNS_CACHE_TIMEOUT |
+        curl_sys::CURLOPT_TCP_KEEPIDLE |
+        curl_sys::CURLOPT_TCP_KEEPINTVL => 60,
+        curl_sys::CURLOPT_MAXAGE_CONN => 118,
+        curl_sys::CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS => 200,
+        curl_sys::CURLOPT_TFTP_BLKSIZE => 512,
+        curl_sys::CURLOPT_NEW_FILE_PERMS => 0o644,
+        curl_sys::CURLOPT_NEW_DIRECTORY_PERMS => 0o755,
+        curl_sys::CURLOPT_BUFFERSIZE => 16 * 1024,
+        curl_sys::CURLOPT_UPLOAD_BUFFERSIZE => 64 * 1024,
+        curl_sys::CURLOPT_UPKEEP_INTERVAL_MS => 60 * 1000,
+        _ => 0,
+    }
+}
+
+fn stream_error() -> Error {
+    let msg = "request body can't be rewound";
+    error_with_extra(curl_sys::CURLE_SEND_FAIL_REWIND, msg.into())
+}
+
+impl<F: ?Sized> Clone for Callback<F> {
+    fn clone(&self) -> Callback<F> {
+        Callback(self.0.clone())
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+impl Response {
+    /// Creates a response out of thin air, as if it had been received over
+    /// HTTP/1.1 with the given status code, headers and body.
+    ///
+    /// This is meant for `Transport`s which don't talk to a server, such as
+    /// mocks in tests. The status line gets the canonical reason phrase of
+    /// `code`, and the transfer information is left empty.
+    pub fn from_parts(code: u32, headers: &[(&str, &str)], body: Vec<u8>)
+                      -> Response {
+        let reason = StatusCode::new(code).canonical_reason().unwrap_or("");
+        Response {
+            code: code,
+            status_line: format!("HTTP/1.1 {} {}", code, reason),
+            http1: true,
+            headers: headers.iter()
+                            .map(|&(n, v)| (n.to_string(), v.to_string()))
+                            .collect(),
+            headers_done: true,
+            body: body,
+            ..Response::default()
+        }
+    }
+
+    /// Returns the response code of the final response.
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+
+    /// Returns the status of the final response.
+    pub fn status(&self) -> StatusCode {
+        StatusCode::new(self.code)
+    }
+
+    /// Returns the status line of the final response, such as
+    /// `"HTTP/1.1 404 Not Found"`, without the trailing line break.
+    pub fn status_line(&self) -> &str {
+        &self.status_line
+    }
+
+    /// Returns the reason phrase the server sent in the status line of the
+    /// final response.
+    ///
+    /// This is empty if the server didn't send one, which is always the case
+    /// for HTTP/2. Use `StatusCode::canonical_reason` for the standard phrase
+    /// of a status instead.
+    pub fn reason(&self) -> &str {
+        self.status_line.splitn(3, ' ').nth(2).unwrap_or("").trim()
+    }
+
+    /// Returns the value of the first header of the final response named
+    /// `name`.
+    ///
+    /// Header names are compared case-insensitively, so `"content-type"`
+    /// matches a `Content-Type` header sent by the server.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers()
+            .find(|&(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns the values of all headers of the final response named `name`,
+    /// in the order they were received.
+    ///
+    /// Like `header`, the name is compared case-insensitively.
+    pub fn header_all(&self, name: &str) -> Vec<&str> {
+        self.headers()
+            .filter(|&(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    /// Returns an iterator over the name and value of each header of the
+    /// final response, in the order they were received.
+    ///
+    /// Names are returned as the server sent them, and values have
+    /// surrounding whitespace removed.
+    pub fn headers(&self) -> Headers {
+        Headers { inner: self.headers.iter() }
+    }
+
+    /// Returns an iterator over the trailers of the final response, which are
+    /// the headers a server sent after a chunked body.
+    ///
+    /// Trailers aren't included in `headers`.
+    pub fn trailers(&self) -> Headers {
+        Headers { inner: self.trailers.iter() }
+    }
+
+    /// Returns the value of the first trailer named `name`, compared
+    /// case-insensitively.
+    pub fn trailer(&self, name: &str) -> Option<&str> {
+        self.trailers()
+            .find(|&(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Returns the parsed `Content-Type` header of the response, if any.
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.header("content-type").and_then(ContentType::parse)
+    }
+
+    /// Returns the value of the `Content-Length` header of the response, if
+    /// one was sent and is valid.
+    ///
+    /// For the number of bytes actually received use `body().len()`.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("content-length").and_then(|v| v.parse().ok())
+    }
+
+    /// Returns the `Last-Modified` header of the response, if one was sent
+    /// and is a valid HTTP-date.
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.header("last-modified").and_then(parse_http_date)
+    }
+
+    /// Returns the value of the `Location` header of the response, if any.
+    ///
+    /// The value is returned as sent and may be relative to `effective_url`.
+    pub fn location(&self) -> Option<&str> {
+        self.header("location")
+    }
+
+    /// Returns the value of the `ETag` header of the response, if any.
+    ///
+    /// The value is returned as sent, including the quotes and any `W/`
+    /// prefix, so it can be passed to `Request::if_none_match` as is.
+    pub fn etag(&self) -> Option<&str> {
+        self.header("etag")
+    }
+
+    /// Returns the cookies set by the `Set-Cookie` headers of the response.
+    ///
+    /// Headers which can't be parsed are skipped.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.header_all("set-cookie")
+            .into_iter()
+            .filter_map(Cookie::parse)
+            .collect()
+    }
+
+    /// Returns the ranges of the resource carried by a `206 Partial Content`
+    /// response.
+    ///
+    /// When several ranges are requested, such as with a `Range` header of
+    /// `bytes=0-99,500-599`, the server replies with a `multipart/byteranges`
+    /// body, which is split back into its parts here. A response to a
+    /// request for a single range is returned as one part described by its
+    /// `Content-Range` header.
+    ///
+    /// Returns `None` if the response has another status, or its body or
+    /// headers are malformed.
+    pub fn byte_ranges(&self) -> Option<Vec<ByteRange>> {
+        if self.code != 206 {
+            return None
+        }
+        let content_type = self.header("content-type").unwrap_or("");
+        if let Some(parts) = parse_byteranges(content_type, &self.body) {
+            return Some(parts)
+        }
+        let range = self.header("content-range").and_then(parse_content_range);
+        let (start, end, total) = match range {
+            Some(range) => range,
+            None => return None,
+        };
+        Some(vec![ByteRange {
+            start: start,
+            end: end,
+            total: total,
+            content_type: self.header("content-type").map(|s| s.to_string()),
+            data: self.body.clone(),
+        }])
+    }
+
+    /// Returns the body of the response.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Consumes this response, returning its body.
+    pub fn into_body(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Deserializes the body of the response as JSON.
+    ///
+    /// The response must have a JSON `Content-Type`, either
+    /// `application/json` or one ending in `+json`, as otherwise the body is
+    /// likely an error page rather than what the caller expects.
+    ///
+    /// This is only available with the `json` feature of this crate.
+    #[cfg(feature = "json")]
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, JsonError> {
+        let content_type = self.content_type();
+        let is_json = content_type.as_ref().map(|c| {
+            c.mime() == "application/json" || c.mime().ends_with("+json")
+        });
+        if is_json != Some(true) {
+            let mime = content_type.map(|c| c.mime().to_string());
+            return Err(JsonError::ContentType(mime))
+        }
+        serde_json::from_slice(&self.body).map_err(JsonError::Json)
+    }
+
+    /// Decodes the body of the response as text.
+    ///
+    /// The body is decoded with the charset of the `Content-Type` header, or
+    /// as UTF-8 if there is none. Besides UTF-8, the UTF-16 charsets and
+    /// `ISO-8859-1` along with its aliases are supported. The latter are
+    /// decoded as `windows-1252`, as browsers do.
+    ///
+    /// An error for which `Error::is_conv_failed` returns `true` is returned
+    /// if the charset isn't supported or the body isn't valid in it.
+    pub fn text(&self) -> Result<String, Error> {
+        let charset = self.content_type().and_then(|c| {
+            c.charset().map(|s| s.to_string())
+        });
+        let charset = charset.as_ref().map(|s| &s[..]).unwrap_or("utf-8");
+        match decode(&self.body, charset, false) {
+            Some(text) => Ok(text),
+            None => {
+                let msg = format!("body isn't valid {} text", charset);
+                Err(error_with_extra(curl_sys::CURLE_CONV_FAILED,
+                                     msg.into_boxed_str()))
+            }
+        }
+    }
+
+    /// Like `text`, except that invalid sequences are replaced with
+    /// `U+FFFD REPLACEMENT CHARACTER` and unsupported charsets fall back to
+    /// UTF-8.
+    pub fn text_lossy(&self) -> String {
+        let charset = self.content_type().and_then(|c| {
+            c.charset().map(|s| s.to_string())
+        });
+        let charset = charset.as_ref().map(|s| &s[..]).unwrap_or("utf-8");
+        decode(&self.body, charset, true).unwrap_or_else(|| {
+            String::from_utf8_lossy(&self.body).into_owned()
+        })
+    }
+
+    /// Returns whether the body of this response was delimited by the server
+    /// closing the connection.
+    ///
+    /// This is the case for HTTP/1 responses which carry a body but neither a
+    /// `Content-Length` nor a chunked `Transfer-Encoding`, as is common with
+    /// HTTP/1.0 servers. If the connection was cut short the body of such a
+    /// response is silently truncated, unlike for length-delimited bodies
+    /// where libcurl reports an error.
+    pub fn is_eof_delimited(&self) -> bool {
+        self.eof_delimited
+    }
+
+    /// Returns the position of the URL the response came from among the
+    /// mirrors configured with `Request::mirrors`, or `None` if it came from
+    /// the URL of the request itself.
+    pub fn mirror(&self) -> Option<usize> {
+        self.mirror
+    }
+
+    /// Returns whether the body was cut short because of
+    /// `Request::error_body_limit`.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns whether the resource wasn't transferred because it's
+    /// unchanged.
+    ///
+    /// This is the case if the server answered with `304 Not Modified`, for
+    /// example to a request made with `Request::if_modified_since`, and
+    /// corresponds to `Easy::condition_unmet`.
+    pub fn not_modified(&self) -> bool {
+        self.code == 304 || self.condition_unmet
+    }
+
+    /// Returns the URL the response was fetched from.
+    ///
+    /// This differs from the URL of the request if redirects were followed,
+    /// for example with `Easy::follow_location`.
+    pub fn effective_url(&self) -> Option<&str> {
+        self.effective_url.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the IP address of the server or proxy the response was
+    /// received from.
+    pub fn primary_ip(&self) -> Option<&str> {
+        self.primary_ip.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the port of the server or proxy the response was received
+    /// from.
+    pub fn primary_port(&self) -> u16 {
+        self.primary_port
+    }
+
+    /// Returns the local IP address of the connection the response was
+    /// received over.
+    pub fn local_ip(&self) -> Option<&str> {
+        self.local_ip.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the local port of the connection the response was received
+    /// over.
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+
+    /// Returns how long the phases of the transfer took.
+    pub fn timing(&self) -> Timing {
+        self.info.timing
+    }
+
+    /// Returns the average speed the response body was downloaded at, in
+    /// bytes per second.
+    ///
+    /// This is measured by libcurl over the whole transfer and corresponds to
+    /// `CURLINFO_SPEED_DOWNLOAD`.
+    pub fn download_speed(&self) -> f64 {
+        self.info.download_speed
+    }
+
+    /// Returns the average speed the request body was uploaded at, in bytes
+    /// per second.
+    ///
+    /// This is measured by libcurl over the whole transfer and corresponds to
+    /// `CURLINFO_SPEED_UPLOAD`.
+    pub fn upload_speed(&self) -> f64 {
+        self.info.upload_speed
+    }
+
+    /// Returns the status, timings, sizes and other statistics of the
+    /// transfer in one go.
+    pub fn info(&self) -> &ResponseInfo {
+        &self.info
+    }
+
+    fn to_error(&self) -> Error {
+        let msg = format!("The requested URL returned error: {}", self.code);
+        let mut err = error_with_extra(curl_sys::CURLE_HTTP_RETURNED_ERROR,
+                                       msg.into_boxed_str());
+        err = error_with_status(err, self.code);
+        if let Some(ref url) = self.effective_url {
+            err = error_with_url(err, url);
+        }
+        error_with_body(err, self.body.clone())
+    }
+
+    /// Resets this response to its default state, keeping the allocations of
+    /// the body and header vectors.
+    fn clear(&mut self) {
+        self.body.clear();
+        self.clear_headers();
+    }
+
+    /// Like `clear`, but keeps the body received so far.
+    fn clear_headers(&mut self) {
+        self.code = 0;
+        self.status_line.clear();
+        self.http1 = false;
+        self.headers.clear();
+        self.headers_done = false;
+        self.trailers.clear();
+        self.truncated = false;
+        self.mirror = None;
+        self.eof_delimited = false;
+        self.info = ResponseInfo::default();
+        self.effective_url = None;
+        self.primary_ip = None;
+        self.primary_port = 0;
+        self.local_ip = None;
+        self.local_port = 0;
+        self.condition_unmet = false;
+    }
+
+    /// Feeds a line received by a header callback into this response,
+    /// returning whether it was the blank line ending a header block.
+    fn parse_header(&mut self, line: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        if line.is_empty() {
+            // Anything after the end of the final header block is a trailer.
+            if self.code >= 200 {
+                self.headers_done = true;
+            }
+            return true
+        }
+        // A new status line starts a new response, for example after a `100
+        // Continue` or a redirect.
+        if let Some(code) = parse_status_code(line) {
+            self.clear_headers();
+            self.code = code;
+            self.status_line.push_str(line);
+            self.http1 = line.starts_with("HTTP/1.");
+            return false
+        }
+        let mut parts = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            let header = (name.trim().to_string(), value.trim().to_string());
+            if self.headers_done {
+                self.trailers.push(header);
+            } else {
+                self.headers.push(header);
+            }
+        }
+        false
+    }
+
+    fn presize_body(&mut self, limit: usize) {
+        if let Some(len) = self.content_length() {
+            let len = cmp::min(cmp::min(len, MAX_PRESIZE) as usize, limit);
+            let additional = len.saturating_sub(self.body.len());
+            self.body.reserve(additional);
+        }
+    }
+
+    fn lacks_framing(&self, ignore_length: bool) -> bool {
+        if !self.http1 || self.code == 204 || self.code == 304 {
+            return false
+        }
+        let chunked = self.header("transfer-encoding").map(|v| {
+            v.to_lowercase().contains("chunked")
+        });
+        chunked != Some(true) &&
+            (ignore_length || self.header("content-length").is_none())
+    }
+}
+
+impl<'a> Iterator for Headers<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        self.inner.next().map(|&(ref n, ref v)| (&n[..], &v[..]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> Signable<'a> {
+    /// Returns the method of the request.
+    pub fn method(&self) -> &str {
+        self.method
+    }
+
+    /// Returns the URL the request is sent to, which is that of a mirror if
+    /// the request failed over to one.
+    pub fn url(&self) -> &str {
+        self.url
+    }
+
+    /// Returns the names and values of the headers sent with the request, in
+    /// the order they're sent.
+    ///
+    /// The `User-Agent` comes first, followed by the default headers of the
+    /// handle and the headers of the request. Headers unset with
+    /// `Request::unset_header` aren't included.
+    pub fn headers(&self) -> &[(&'a str, &'a str)] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header named `name`, compared
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|&&(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|&(_, v)| v)
+    }
+
+    /// Returns the body of the request, or `None` if it has none or it's
+    /// streamed from a reader.
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body
+    }
+
+    /// Returns the SHA-256 digest of the body of the request.
+    ///
+    /// A request without a body has the digest of an empty one. This is
+    /// `None` for a body streamed from a reader, as it isn't known before
+    /// it's sent.
+    pub fn body_digest(&self) -> Option<&[u8]> {
+        self.body_digest.as_ref().map(|d| &d[..])
+    }
+}
+
+impl<'a> Iterator for RequestHeaders<'a> {
+    type Item = (&'a str, Option<&'a str>);
+
+    fn next(&mut self) -> Option<(&'a str, Option<&'a str>)> {
+        self.inner.next().map(|&(ref n, ref v)| {
+            (&n[..], v.as_ref().map(|v| &v[..]))
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl Timing {
+    /// Collects the timing information of the last transfer made with
+    /// `handle`.
+    pub fn from_handle(handle: &mut Easy) -> Result<Timing, Error> {
+        Ok(Timing {
+            namelookup: try!(handle.namelookup_time()),
+            connect: try!(handle.connect_time()),
+            appconnect: try!(handle.appconnect_time()),
+            pretransfer: try!(handle.pretransfer_time()),
+            starttransfer: try!(handle.starttransfer_time()),
+            total: try!(handle.total_time()),
+            redirect: try!(handle.redirect_time()),
+        })
+    }
+
+    /// Time until name resolution completed.
+    pub fn namelookup(&self) -> Duration {
+        self.namelookup
+    }
+
+    /// Time until the connection to the remote host or proxy completed.
+    pub fn connect(&self) -> Duration {
+        self.connect
+    }
+
+    /// Time until the TLS or SSH handshake completed.
+    ///
+    /// This is zero for plain text connections.
+    pub fn appconnect(&self) -> Duration {
+        self.appconnect
+    }
+
+    /// Time until the request was just about to be sent.
+    pub fn pretransfer(&self) -> Duration {
+        self.pretransfer
+    }
+
+    /// Time until the first byte of the response was received.
+    pub fn starttransfer(&self) -> Duration {
+        self.starttransfer
+    }
+
+    /// Time the whole transfer took, including redirects.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Time spent following redirects before the final request started.
+    pub fn redirect(&self) -> Duration {
+        self.redirect
+    }
+}
+
+impl ResponseInfo {
+    /// Collects the information about the last transfer made with `handle`.
+    pub fn from_handle(handle: &mut Easy) -> Result<ResponseInfo, Error> {
+        Ok(ResponseInfo {
+            status: try!(handle.response_code()),
+            effective_url: try!(handle.effective_url()).map(|s| s.to_string()),
+            // Older versions of libcurl don't know the scheme.
+            scheme: handle.scheme().ok()
+                          .and_then(|s| s.map(|s| s.to_string())),
+            timing: try!(Timing::from_handle(handle)),
+            download_size: try!(handle.download_size()),
+            upload_size: try!(handle.upload_size()),
+            header_size: try!(handle.header_size()),
+            request_size: try!(handle.request_size()),
+            download_speed: try!(handle.download_speed()),
+            upload_speed: try!(handle.upload_speed()),
+            num_connects: try!(handle.num_connects()),
+            redirect_count: try!(handle.redirect_count()),
+        })
+    }
+
+    /// The response code of the final response, or 0 if none was received.
+    pub fn status(&self) -> u32 {
+        self.status
+    }
+
+    /// The URL of the final request, after any redirects.
+    pub fn effective_url(&self) -> Option<&str> {
+        self.effective_url.as_ref().map(|s| &s[..])
+    }
+
+    /// The scheme of the final request in upper case, such as `"HTTPS"`.
+    ///
+    /// This is `None` with libcurl older than 7.52.0.
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_ref().map(|s| &s[..])
+    }
+
+    /// How long the phases of the transfer took.
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// The number of body bytes received.
+    pub fn download_size(&self) -> u64 {
+        self.download_size
+    }
+
+    /// The number of body bytes sent.
+    pub fn upload_size(&self) -> u64 {
+        self.upload_size
+    }
+
+    /// The total size of all headers received, including those of responses
+    /// to redirects.
+    pub fn header_size(&self) -> u64 {
+        self.header_size
+    }
+
+    /// The total size of all requests sent, including those following
+    /// redirects.
+    pub fn request_size(&self) -> u64 {
+        self.request_size
+    }
+
+    /// The average download speed in bytes per second.
+    pub fn download_speed(&self) -> f64 {
+        self.download_speed
+    }
+
+    /// The average upload speed in bytes per second.
+    pub fn upload_speed(&self) -> f64 {
+        self.upload_speed
+    }
+
+    /// The number of new connections made, which is 0 if a cached connection
+    /// was reused.
+    pub fn num_connects(&self) -> u32 {
+        self.num_connects
+    }
+
+    /// The number of redirects followed.
+    pub fn redirect_count(&self) -> u32 {
+        self.redirect_count
+    }
+}
+
+impl StatusCode {
+    /// Wraps a raw status code.
+    pub fn new(code: u32) -> StatusCode {
+        StatusCode { code: code }
+    }
+
+    /// Returns the raw status code.
+    pub fn as_u32(&self) -> u32 {
+        self.code
+    }
+
+    /// Returns whether this is a `1xx` status.
+    pub fn is_informational(&self) -> bool {
+        self.code >= 100 && self.code < 200
+    }
+
+    /// Returns whether this is a `2xx` status.
+    pub fn is_success(&self) -> bool {
+        self.code >= 200 && self.code < 300
+    }
+
+    /// Returns whether this is a `3xx` status.
+    pub fn is_redirect(&self) -> bool {
+        self.code >= 300 && self.code < 400
+    }
+
+    /// Returns whether this is a `4xx` status.
+    pub fn is_client_error(&self) -> bool {
+        self.code >= 400 && self.code < 500
+    }
+
+    /// Returns whether this is a `5xx` status.
+    pub fn is_server_error(&self) -> bool {
+        self.code >= 500 && self.code < 600
+    }
+
+    /// Returns the reason phrase registered for this status, such as
+    /// `"Not Found"` for 404.
+    ///
+    /// This is `None` for unregistered codes. The phrase a server actually
+    /// sent may differ.
+    pub fn canonical_reason(&self) -> Option<&'static str> {
+        let reason = match self.code {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            102 => "Processing",
+            103 => "Early Hints",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            207 => "Multi-Status",
+            208 => "Already Reported",
+            226 => "IM Used",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            305 => "Use Proxy",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Payload Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Entity",
+            423 => "Locked",
+            424 => "Failed Dependency",
+            425 => "Too Early",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            506 => "Variant Also Negotiates",
+            507 => "Insufficient Storage",
+            508 => "Loop Detected",
+            510 => "Not Extended",
+            511 => "Network Authentication Required",
+            _ => return None,
+        };
+        Some(reason)
+    }
+}
+
+impl From<u32> for StatusCode {
+    fn from(code: u32) -> StatusCode {
+        StatusCode::new(code)
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.canonical_reason() {
+            Some(reason) => write!(f, "{} {}", self.code, reason),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+impl ContentType {
+    /// Parses the value of a `Content-Type` header such as
+    /// `text/html; charset=UTF-8`.
+    ///
+    /// Returns `None` if the media type isn't of the form `type/subtype`.
+    pub fn parse(value: &str) -> Option<ContentType> {
+        let mut parts = value.split(';');
+        let mime = parts.next().unwrap_or("").trim().to_lowercase();
+        {
+            let mut halves = mime.splitn(2, '/');
+            match (halves.next(), halves.next()) {
+                (Some(a), Some(b)) if !a.is_empty() && !b.is_empty() => {}
+                _ => return None,
+            }
+        }
+        let mut charset = None;
+        for param in parts {
+            let mut kv = param.splitn(2, '=');
+            if let (Some(k), Some(v)) = (kv.next(), kv.next()) {
+                if k.trim().eq_ignore_ascii_case("charset") {
+                    charset = Some(v.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+        Some(ContentType { mime: mime, charset: charset })
+    }
+
+    /// Returns the media type in lowercase, such as `"text/html"`.
+    pub fn mime(&self) -> &str {
+        &self.mime
+    }
+
+    /// Returns the `charset` parameter as sent, if there was one.
+    pub fn charset(&self) -> Option<&str> {
+        self.charset.as_ref().map(|s| &s[..])
+    }
+}
+
+impl Cookie {
+    /// Parses the value of a `Set-Cookie` header, such as
+    /// `id=a3fWa; Path=/; Secure; HttpOnly`.
+    ///
+    /// Unknown attributes are ignored, as are `Expires` dates which can't be
+    /// parsed. Returns `None` if there's no `name=value` pair or the name is
+    /// empty.
+    pub fn parse(header: &str) -> Option<Cookie> {
+        let mut parts = header.split(';');
+        let mut pair = parts.next().unwrap_or("").splitn(2, '=');
+        let (name, value) = match (pair.next(), pair.next()) {
+            (Some(name), Some(value)) if !name.trim().is_empty() => {
+                (name.trim(), value.trim().trim_matches('"'))
+            }
+            _ => return None,
+        };
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: None,
+            path: None,
+            expires: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+        };
+        for attr in parts {
+            let mut kv = attr.splitn(2, '=');
+            let key = kv.next().unwrap_or("").trim().to_lowercase();
+            let value = kv.next().map(|v| v.trim()).unwrap_or("");
+            match &key[..] {
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_matches('.').to_lowercase();
+                    cookie.domain = Some(domain);
+                }
+                "path" if value.starts_with('/') => {
+                    cookie.path = Some(value.to_string());
+                }
+                "expires" => cookie.expires = parse_http_date(value),
+                "max-age" => cookie.max_age = value.parse().ok(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+        Some(cookie)
+    }
+
+    /// Returns the name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the `Domain` attribute without any leading dot, if set.
+    ///
+    /// Without this attribute the cookie only applies to the exact host the
+    /// response came from.
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the `Path` attribute, if set.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the `Expires` attribute, if set to a valid date.
+    pub fn expires(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// Returns the `Max-Age` attribute in seconds, if set.
+    ///
+    /// This takes precedence over `expires`, and zero or a negative value
+    /// means the cookie should be removed right away.
+    pub fn max_age(&self) -> Option<i64> {
+        self.max_age
+    }
+
+    /// Returns whether the `Secure` attribute was set.
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Returns whether the `HttpOnly` attribute was set.
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+}
+
+impl ByteRange {
+    /// Returns the offset of the first byte of this range.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the offset of the last byte of this range, which is included
+    /// in it.
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Returns the size of the complete resource, if the server sent it.
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Returns the `Content-Type` of this part, if it had one.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the bytes of this range.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes this range, returning its bytes.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Splits a `multipart/byteranges` body into its parts.
+///
+/// `content_type` is the value of the `Content-Type` header of the response,
+/// which names the boundary separating the parts. Each part has to carry a
+/// `Content-Range` header.
+///
+/// Returns `None` if `content_type` isn't `multipart/byteranges` with a
+/// boundary, or the body is malformed.
+///
+/// ```
+/// use curl::http;
+///
+/// let body = b"--sep\r\n\
+///              Content-Range: bytes 0-3/20\r\n\
+///              \r\n\
+///              abcd\r\n\
+///              --sep\r\n\
+///              Content-Range: bytes 10-11/20\r\n\
+///              \r\n\
+///              kl\r\n\
+///              --sep--\r\n";
+/// let parts = http::parse_byteranges("multipart/byteranges; boundary=sep",
+///                                    body).unwrap();
+/// assert_eq!(parts.len(), 2);
+/// assert_eq!((parts[1].start(), parts[1].end()), (10, 11));
+/// assert_eq!(parts[1].data(), b"kl");
+/// ```
+pub fn parse_byteranges(content_type: &str, body: &[u8])
+                        -> Option<Vec<ByteRange>> {
+    let mut params = content_type.split(';');
+    let mime = params.next().unwrap_or("").trim();
+    if !mime.eq_ignore_ascii_case("multipart/byteranges") {
+        return None
+    }
+    let boundary = params.filter_map(|param| {
+        let mut kv = param.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some(k), Some(v)) if k.trim().eq_ignore_ascii_case("boundary") => {
+                Some(v.trim().trim_matches('"'))
+            }
+            _ => None,
+        }
+    }).next();
+    let boundary = match boundary {
+        Some(boundary) if !boundary.is_empty() => boundary,
+        _ => return None,
+    };
+    let delimiter = format!("\n--{}", boundary).into_bytes();
+
+    // The first delimiter may start the body rather than follow a newline.
+    let mut rest = if body.starts_with(&delimiter[1..]) {
+        &body[delimiter.len() - 1..]
+    } else {
+        match find_bytes(body, &delimiter) {
+            Some(i) => &body[i + delimiter.len()..],
+            None => return None,
+        }
+    };
+    let mut parts = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Some(parts)
+        }
+        // Skip any padding after the delimiter, then read the headers.
+        let mut range = None;
+        let mut part_type = None;
+        let mut first = true;
+        loop {
+            let line_end = match rest.iter().position(|&b| b == b'\n') {
+                Some(i) => i,
+                None => return None,
+            };
+            let line = str::from_utf8(&rest[..line_end]);
+            rest = &rest[line_end + 1..];
+            let line = match line {
+                Ok(line) => line.trim(),
+                Err(..) => return None,
+            };
+            if first {
+                first = false;
+                continue
+            }
+            if line.is_empty() {
+                break
+            }
+            if let Some(value) = header_value(line, "content-range") {
+                range = parse_content_range(value);
+            } else if let Some(value) = header_value(line, "content-type") {
+                part_type = Some(value.to_string());
+            }
+        }
+        let (start, end, total) = match range {
+            Some(range) => range,
+            None => return None,
+        };
+        let data_end = match find_bytes(rest, &delimiter) {
+            Some(i) => i,
+            None => return None,
+        };
+        let mut data = &rest[..data_end];
+        if data.ends_with(b"\r") {
+            data = &data[..data.len() - 1];
+        }
+        parts.push(ByteRange {
+            start: start,
+            end: end,
+            total: total,
+            content_type: part_type,
+            data: data.to_vec(),
+        });
+        rest = &rest[data_end + delimiter.len()..];
+    }
+}
+
+/// Parses the value of a `Content-Range` header such as `bytes 0-99/1234`
+/// into the first and last offset and the total size, if known.
+fn parse_content_range(value: &str) -> Option<(u64, u64, Option<u64>)> {
+    let value = value.trim();
+    match value.get(..6) {
+        Some(unit) if unit.eq_ignore_ascii_case("bytes ") => {}
+        _ => return None,
+    }
+    let mut parts = value[6..].splitn(2, '/');
+    let range = parts.next().unwrap_or("");
+    let total = match parts.next().map(|t| t.trim()) {
+        Some("*") => None,
+        Some(total) => match total.parse() {
+            Ok(total) => Some(total),
+            Err(..) => return None,
+        },
+        None => return None,
+    };
+    let mut bounds = range.splitn(2, '-').map(|s| s.trim().parse().ok());
+    match (bounds.next(), bounds.next()) {
+        (Some(Some(start)), Some(Some(end))) if start <= end => {
+            Some((start, end, total))
+        }
+        _ => None,
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+impl Encoding {
+    /// Returns the name of this coding in a `Content-Encoding` header.
+    fn token(&self) -> &'static str {
+        match *self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+impl ETagCache {
+    /// Creates a new, empty cache.
+    pub fn new() -> ETagCache {
+        ETagCache::default()
+    }
+
+    /// Returns the `ETag` last recorded for `url`, if any.
+    pub fn get(&self, url: &str) -> Option<&str> {
+        self.etags.get(url).map(|s| &s[..])
+    }
+
+    /// Records `etag` for `url`, replacing the previous one.
+    pub fn insert(&mut self, url: &str, etag: &str) {
+        self.etags.insert(url.to_string(), etag.to_string());
+    }
+
+    /// Forgets the `ETag` of `url`, returning it.
+    pub fn remove(&mut self, url: &str) -> Option<String> {
+        self.etags.remove(url)
+    }
+
+    /// Performs `request` on `handle`, making it conditional on the `ETag`
+    /// known for its URL.
+    ///
+    /// An `If-None-Match` header added to the request itself takes precedence
+    /// over the cache. Afterwards the `ETag` of a successful response, if it
+    /// has one, is recorded for the URL of the request, while other responses
+    /// leave the cache untouched.
+    pub fn perform(&mut self, request: &Request, handle: &mut Easy)
+                   -> Result<Response, Error> {
+        let url = request.url().to_string();
+        let explicit = request.headers.iter().any(|&(ref name, _)| {
+            name.eq_ignore_ascii_case("if-none-match")
+        });
+        let response = match self.etags.get(&url) {
+            Some(etag) if !explicit => {
+                let mut request = request.clone();
+                request.if_none_match(etag);
+                try!(request.perform(handle))
+            }
+            _ => try!(request.perform(handle)),
+        };
+        if response.status().is_success() {
+            if let Some(etag) = response.etag() {
+                self.etags.insert(url, etag.to_string());
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Something which can perform a `Request`, such as an `Easy` handle.
+///
+/// Code which takes a transport rather than a handle can be tested without a
+/// server: closures taking a `&Request` are transports as well, so a test can
+/// pass one which checks the request and returns a canned `Response`.
+///
+/// ```
+/// use curl::Error;
+/// use curl::http::{Request, Response, Transport};
+///
+/// fn greeting<T: Transport>(transport: &mut T) -> Result<String, Error> {
+///     let response = try!(transport.execute(&Request::get("http://x/hi")));
+///     response.text()
+/// }
+///
+/// let mut mock = |request: &Request| {
+///     assert_eq!(request.url(), "http://x/hi");
+///     Ok(Response::from_parts(200, &[("Content-Type", "text/plain")],
+///                             b"hello".to_vec()))
+/// };
+/// assert_eq!(greeting(&mut mock).unwrap(), "hello");
+/// ```
+pub trait Transport {
+    /// Performs `request`, returning its response.
+    fn execute(&mut self, request: &Request) -> Result<Response, Error>;
+}
+
+/// Hooks which are told about the requests performed on a handle, for
+/// feeding counters and histograms.
+///
+/// Hooks are installed with `Easy::metrics` and invoked for every `Request`
+/// performed on the handle, including those made through a `Transport`, so
+/// they don't need to wrap every call. All methods do nothing by default.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use curl::easy::Easy;
+/// use curl::http::{Metrics, Request, Response};
+/// use curl::Error;
+///
+/// #[derive(Default)]
+/// struct Counters {
+///     received: AtomicUsize,
+///     failures: AtomicUsize,
+/// }
+///
+/// impl Metrics for Counters {
+///     fn on_bytes_received(&self, count: u64) {
+///         self.received.fetch_add(count as usize, Ordering::SeqCst);
+///     }
+///
+///     fn on_complete(&self, _: &Request, result: Result<&Response, &Error>) {
+///         match result {
+///             Ok(response) if response.code() < 500 => {}
+///             _ => { self.failures.fetch_add(1, Ordering::SeqCst); }
+///         }
+///     }
+/// }
+///
+/// let counters = Arc::new(Counters::default());
+/// let mut handle = Easy::new();
+/// handle.metrics(counters.clone());
+/// ```
+pub trait Metrics: Send + Sync {
+    /// Called when `request` is about to be performed.
+    ///
+    /// This is called once per request, even if it fails over to its
+    /// mirrors.
+    fn on_request_start(&self, _request: &Request) {}
+
+    /// Called as bytes of the request body are sent.
+    ///
+    /// `count` is the number of bytes sent since the previous call.
+    fn on_bytes_sent(&self, _count: u64) {}
+
+    /// Called as bytes of the response body are received.
+    ///
+    /// `count` is the number of bytes received since the previous call.
+    fn on_bytes_received(&self, _count: u64) {}
+
+    /// Called once `request` is done, with its response or the error which
+    /// ended it.
+    ///
+    /// The status and timings of the transfer are available through
+    /// `Response::info`.
+    fn on_complete(&self, _request: &Request,
+                   _result: Result<&Response, &Error>) {}
+}
+
+impl Transport for Easy {
+    fn execute(&mut self, request: &Request) -> Result<Response, Error> {
+        request.perform(self)
+    }
+}
+
+impl<F> Transport for F
+    where F: FnMut(&Request) -> Result<Response, Error>
+{
+    fn execute(&mut self, request: &Request) -> Result<Response, Error> {
+        self(request)
+    }
+}
+
+/// Performs a request over and over again, as done to long-poll a server for
+/// updates.
+///
+/// Each response is handed to a callback together with the request, which
+/// the callback can change before the next poll, for example to ask for the
+/// updates after the last one it saw. Failed requests are retried with an
+/// exponentially growing delay instead. The loop goes on until the callback
+/// returns `false`, it's cancelled through a `Cancel`, or too many requests
+/// in a row have failed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use curl::easy::Easy;
+/// use curl::http::{PollLoop, Request};
+///
+/// let mut handle = Easy::new();
+/// handle.timeout(Duration::from_secs(60)).unwrap();
+/// let mut offset = 0;
+/// let url = "https://api.telegram.org/bot123/getUpdates?timeout=50";
+/// let mut request = Request::get(&format!("{}&offset={}", url, offset));
+/// PollLoop::new()
+///     .backoff(Duration::from_secs(1), Duration::from_secs(30))
+///     .run(&mut handle, &mut request, |response, request| {
+///         println!("{}", response.text().unwrap());
+///         offset += 1;
+///         *request = Request::get(&format!("{}&offset={}", url, offset));
+///         true
+///     })
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PollLoop {
+    interval: Duration,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    max_failures: Option<u32>,
+    cancel: Option<Cancel>,
+}
+
+/// A flag which stops a `PollLoop`, possibly from another thread.
+///
+/// Clones of a `Cancel` share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct Cancel {
+    state: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl PollLoop {
+    /// Creates a new loop which polls again as soon as a response arrives.
+    pub fn new() -> PollLoop {
+        PollLoop {
+            interval: Duration::new(0, 0),
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_failures: None,
+            cancel: None,
+        }
+    }
+
+    /// Sets how long to wait after a successful poll before making the next
+    /// one.
+    ///
+    /// Servers which support long-polling hold on to a request until there's
+    /// something to report, so there's no need to wait in between. For ones
+    /// which answer right away this keeps the loop from flooding them.
+    ///
+    /// By default there's no delay.
+    pub fn interval(&mut self, interval: Duration) -> &mut PollLoop {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the delays to wait after failed polls.
+    ///
+    /// The first retry waits for `min`, and the delay doubles with each
+    /// failure in a row up to `max`. A `Retry-After` header with a number of
+    /// seconds in a failed response overrides the delay, though it's capped
+    /// at `max` as well. A poll fails if
+    /// performing the request returns an error, or if the response has a
+    /// `5xx` or `429 Too Many Requests` status, so those responses never reach
+    /// the callback.
+    ///
+    /// By default the delays range from one second to one minute.
+    pub fn backoff(&mut self, min: Duration, max: Duration) -> &mut PollLoop {
+        self.min_backoff = min;
+        self.max_backoff = cmp::max(min, max);
+        self
+    }
+
+    /// Gives up after `max` polls in a row have failed, making `run` return
+    /// the error of the last one.
+    ///
+    /// By default failed polls are retried indefinitely.
+    pub fn max_failures(&mut self, max: u32) -> &mut PollLoop {
+        self.max_failures = Some(max);
+        self
+    }
+
+    /// Stops the loop once `cancel` is cancelled.
+    ///
+    /// The flag is checked before each poll, and cancelling it cuts short any
+    /// delay the loop is waiting out. A request that's already being
+    /// performed is completed first, so the timeouts of the handle bound how
+    /// long stopping takes.
+    pub fn cancel_on(&mut self, cancel: &Cancel) -> &mut PollLoop {
+        self.cancel = Some(cancel.clone());
+        self
+    }
+
+    /// Polls `request` on `handle` until the loop is stopped, invoking `f`
+    /// with each response.
+    ///
+    /// `f` returns whether to keep polling and may change the request for
+    /// the next poll. Returns `Ok` if `f` or a `Cancel` stopped the loop, and
+    /// the error of the last poll if it gave up after too many failures.
+    pub fn run<F>(&self, handle: &mut Easy, request: &mut Request, mut f: F)
+                  -> Result<(), Error>
+        where F: FnMut(&Response, &mut Request) -> bool
+    {
+        let mut response = Response::default();
+        let mut failures = 0;
+        while !self.cancelled() {
+            let result = request.perform_into(handle, &mut response);
+            let (err, retry_after) = match result {
+                Ok(()) if response.code != 429 && response.code < 500 => {
+                    failures = 0;
+                    if !f(&response, request) {
+                        return Ok(())
+                    }
+                    self.wait(self.interval);
+                    continue
+                }
+                Ok(()) => {
+                    let retry_after = response.header("retry-after")
+                        .and_then(|v| v.trim().parse().ok())
+                        .map(|secs| {
+                            cmp::min(Duration::from_secs(secs),
+                                     self.max_backoff)
+                        });
+                    (response.to_error(), retry_after)
+                }
+                Err(e) => (e, None),
+            };
+            failures += 1;
+            if self.max_failures.map_or(false, |max| failures >= max) {
+                return Err(err)
+            }
+            self.wait(retry_after.unwrap_or_else(|| self.delay(failures)));
+        }
+        Ok(())
+    }
+
+    /// Returns the delay before retrying after `failures` failed polls in a
+    /// row.
+    fn delay(&self, failures: u32) -> Duration {
+        let mut delay = cmp::min(self.min_backoff, self.max_backoff);
+        for _ in 1..failures {
+            if delay >= self.max_backoff {
+                break
+            }
+            delay = match delay.checked_mul(2) {
+                Some(delay) => cmp::min(delay, self.max_backoff),
+                None => self.max_backoff,
+            };
+        }
+        delay
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().map_or(false, |c| c.is_cancelled())
+    }
+
+    fn wait(&self, delay: Duration) {
+        if delay == Duration::new(0, 0) {
+            return
+        }
+        match self.cancel {
+            Some(ref cancel) => cancel.wait(delay),
+            None => thread::sleep(delay),
+        }
+    }
+}
+
+impl Default for PollLoop {
+    fn default() -> PollLoop {
+        PollLoop::new()
+    }
+}
+
+impl Cancel {
+    /// Creates a new flag which isn't cancelled yet.
+    pub fn new() -> Cancel {
+        Cancel::default()
+    }
+
+    /// Cancels the flag, stopping the loops watching it.
+    pub fn cancel(&self) {
+        let &(ref cancelled, ref cvar) = &*self.state;
+        *cancelled.lock().unwrap() = true;
+        cvar.notify_all();
+    }
+
+    /// Returns whether `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.0.lock().unwrap()
+    }
+
+    /// Waits for `delay` to pass, or the flag to be cancelled.
+    fn wait(&self, delay: Duration) {
+        let &(ref cancelled, ref cvar) = &*self.state;
+        // A delay too long to be represented only ends by cancelling.
+        let deadline = Instant::now().checked_add(delay);
+        let mut guard = cancelled.lock().unwrap();
+        while !*guard {
+            let deadline = match deadline {
+                Some(deadline) => deadline,
+                None => {
+                    guard = cvar.wait(guard).unwrap();
+                    continue
+                }
+            };
+            let now = Instant::now();
+            if now >= deadline {
+                break
+            }
+            guard = cvar.wait_timeout(guard, deadline - now).unwrap().0;
+        }
+    }
+}
+
+/// Quotes `arg` for a POSIX shell, unless it only consists of characters
+/// which don't need quoting.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty() && arg.chars().all(|c| {
+        c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c)
+    });
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Splits `command` into words like a POSIX shell, returning `None` if a
+/// quote isn't closed.
+fn shell_words(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\r' | '\n' => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\\' => {
+                match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => word.get_or_insert_with(String::new).push(c),
+                    None => return None,
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return None,
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            match chars.next() {
+                                Some('\n') => {}
+                                Some(c) if "\"\\$`".contains(c) => {
+                                    word.push(c)
+                                }
+                                Some(c) => {
+                                    word.push('\\');
+                                    word.push(c);
+                                }
+                                None => return None,
+                            }
+                        }
+                        Some(c) => word.push(c),
+                        None => return None,
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                // Escapes stand for bytes, which only together have to form
+                // valid UTF-8.
+                let mut bytes = Vec::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => {
+                            if ansi_c_escape(&mut chars, &mut bytes).is_none() {
+                                return None
+                            }
+                        }
+                        Some(c) => {
+                            let mut buf = [0; 4];
+                            bytes.extend_from_slice(c.encode_utf8(&mut buf)
+                                                     .as_bytes());
+                        }
+                        None => return None,
+                    }
+                }
+                match String::from_utf8(bytes) {
+                    Ok(s) => word.get_or_insert_with(String::new).push_str(&s),
+                    Err(..) => return None,
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(word) = word {
+        words.push(word);
+    }
+    Some(words)
+}
+
+/// Decodes the escape sequence following a backslash in a `$'...'` string,
+/// appending the bytes it stands for to `bytes`.
+fn ansi_c_escape(chars: &mut iter::Peekable<str::Chars>, bytes: &mut Vec<u8>)
+                 -> Option<()> {
+    let c = match chars.next() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some('x') => {
+            let mut n = 0;
+            let mut digits = 0;
+            while digits < 2 {
+                match chars.peek().and_then(|c| c.to_digit(16)) {
+                    Some(d) => n = n * 16 + d,
+                    None => break,
+                }
+                chars.next();
+                digits += 1;
+            }
+            if digits == 0 {
+                return None
+            }
+            bytes.push(n as u8);
+            return Some(())
+        }
+        Some(c) => c,
+        None => return None,
+    };
+    let mut buf = [0; 4];
+    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    Some(())
+}
+
+/// Percent-encodes everything in `s` except for unreserved characters, the
+/// way `curl --data-urlencode` does.
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' |
+            b'-' | b'.' | b'_' | b'~' => encoded.push(b as char),
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Decodes `bytes` in `charset`, returning `None` if the charset isn't
+/// supported or, unless `lossy` is set, the bytes aren't valid in it.
+fn decode(bytes: &[u8], charset: &str, lossy: bool) -> Option<String> {
+    match &charset.to_lowercase()[..] {
+        "utf-8" | "utf8" | "unicode-1-1-utf-8" => {
+            if lossy {
+                Some(String::from_utf8_lossy(bytes).into_owned())
+            } else {
+                str::from_utf8(bytes).ok().map(|s| s.to_string())
+            }
+        }
+        "utf-16" | "utf-16le" => decode_utf16(bytes, false, lossy),
+        "utf-16be" => decode_utf16(bytes, true, lossy),
+        "iso-8859-1" | "iso8859-1" | "latin1" | "l1" | "us-ascii" |
+        "ascii" | "windows-1252" | "cp1252" => {
+            Some(bytes.iter().map(|&b| windows_1252(b)).collect())
+        }
+        _ => None,
+    }
+}
+
+fn decode_utf16(mut bytes: &[u8], mut big_endian: bool, lossy: bool)
+                -> Option<String> {
+    if bytes.starts_with(&[0xff, 0xfe]) {
+        big_endian = false;
+        bytes = &bytes[2..];
+    } else if bytes.starts_with(&[0xfe, 0xff]) {
+        big_endian = true;
+        bytes = &bytes[2..];
+    }
+    if bytes.len() % 2 != 0 && !lossy {
+        return None
+    }
+    let units = bytes.chunks(2).map(|c| {
+        match (c.len(), big_endian) {
+            (2, true) => (c[0] as u16) << 8 | c[1] as u16,
+            (2, false) => (c[1] as u16) << 8 | c[0] as u16,
+            // A dangling odd byte, which can only get here if lossy.
+            _ => 0xfffd,
+        }
+    }).collect::<Vec<_>>();
+    if lossy {
+        Some(String::from_utf16_lossy(&units))
+    } else {
+        String::from_utf16(&units).ok()
+    }
+}
+
+fn windows_1252(b: u8) -> char {
+    const HIGH: [u16; 32] = [
+        0x20ac, 0x0081, 0x201a, 0x0192, 0x201e, 0x2026, 0x2020, 0x2021,
+        0x02c6, 0x2030, 0x0160, 0x2039, 0x0152, 0x008d, 0x017d, 0x008f,
+        0x0090, 0x2018, 0x2019, 0x201c, 0x201d, 0x2022, 0x2013, 0x2014,
+        0x02dc, 0x2122, 0x0161, 0x203a, 0x0153, 0x009d, 0x017e, 0x0178,
+    ];
+    match b {
+        0x80..=0x9f => {
+            ::std::char::from_u32(HIGH[(b - 0x80) as usize] as u32).unwrap()
+        }
+        b => b as char,
+    }
+}
+
+/// Parses an HTTP-date as used by headers such as `Last-Modified`.
+///
+/// The preferred `Sun, 06 Nov 1994 08:49:37 GMT` format is accepted along
+/// with the obsolete RFC 850 and asctime formats that RFC 7231 requires
+/// recipients to understand. Dates before 1970 or after 9999, and days
+/// which don't exist such as `31 Feb`, are rejected.
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use curl::http::parse_http_date;
+///
+/// let t = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+/// assert_eq!(t, UNIX_EPOCH + Duration::from_secs(784111777));
+/// ```
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let tokens = date.split(|c| c == ' ' || c == ',' || c == '-')
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>();
+    // Skip the day of the week, it's implied by the date anyway.
+    let (day, month, year, time) = match tokens.len() {
+        6 => (tokens[1], tokens[2], tokens[3], tokens[4]),
+        5 => (tokens[2], tokens[1], tokens[4], tokens[3]),
+        _ => return None,
+    };
+    let day = match day.parse::<u64>() {
+        Ok(day) => day,
+        Err(_) => return None,
+    };
+    let mut year = match year.parse::<u64>() {
+        Ok(year) => year,
+        Err(_) => return None,
+    };
+    if year < 100 {
+        year += if year < 70 {2000} else {1900};
+    }
+    const MONTHS: [&'static str; 12] = ["jan", "feb", "mar", "apr", "may",
+                                         "jun", "jul", "aug", "sep", "oct",
+                                         "nov", "dec"];
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month));
+    let month = match month {
+        Some(i) => i as u64 + 1,
+        None => return None,
+    };
+    let hms = time.split(':')
+        .map(|t| t.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>();
+    let (h, m, sec) = match hms {
+        Some(ref hms) if hms.len() == 3 => (hms[0], hms[1], hms[2]),
+        _ => return None,
+    };
+    if h > 23 || m > 59 || sec > 60 {
+        return None
+    }
+    // HTTP-dates have four digit years, which also keeps the arithmetic
+    // below from overflowing.
+    if year < 1970 || year > 9999 {
+        return None
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if day == 0 || day > month_days {
+        return None
+    }
+
+    // Days since the epoch of a proleptic Gregorian calendar date.
+    let (y, mo) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * mo + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    let secs = days * 86400 + h * 3600 + m * 60 + sec;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Formats `time` as an HTTP-date in the preferred
+/// `Sun, 06 Nov 1994 08:49:37 GMT` format.
+///
+/// Fractions of a second are dropped, and times before the Unix epoch are
+/// formatted as the epoch itself.
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use curl::http::format_http_date;
+///
+/// let t = UNIX_EPOCH + Duration::from_secs(784111777);
+/// assert_eq!(format_http_date(t), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+pub fn format_http_date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH)
+                   .map(|d| d.as_secs())
+                   .unwrap_or(0);
+    let days = secs / 86400;
+    let rem = secs % 86400;
+
+    // The inverse of the conversion in `parse_http_date`.
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = yoe + era * 400 + if month <= 2 {1} else {0};
+
+    const DAYS: [&'static str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue",
+                                     "Wed"];
+    const MONTHS: [&'static str; 12] = ["Jan", "Feb", "Mar", "Apr", "May",
+                                         "Jun", "Jul", "Aug", "Sep", "Oct",
+                                         "Nov", "Dec"];
+    format!("{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            DAYS[(days % 7) as usize],
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            rem / 3600,
+            rem % 3600 / 60,
+            rem % 60)
+}
+
+/// Performs a transfer, following redirects manually and detecting loops.
+///
+/// libcurl's own redirect handling (`follow_location`) only gives up after
+/// `max_redirections` hops and then reports a generic "too many redirects"
+/// error. This function instead follows the `Location` of each response
+/// itself and fails as soon as a URL is visited a second time, returning the
+/// cycle that was found.
+///
+/// At most `max` redirects are followed. Each hop is performed with the
+/// options currently configured on `handle`, so the write and header
+/// callbacks see the responses of every hop. A `303 See Other` response
+/// switches the request to a `GET`, other redirects keep the method as is.
+///
+/// libcurl doesn't report the options a handle was configured with, so they
+/// can't be put back afterwards. Whether this succeeds or fails, the handle
+/// is left with `follow_location` disabled, its URL pointing at the last
+/// location requested and, if a `303` was followed, configured for a `GET`.
+pub fn follow_redirects(handle: &mut Easy, max: u32)
+                        -> Result<(), RedirectError> {
+    try!(handle.follow_location(false));
+    let mut visited = Vec::new();
+    loop {
+        try!(handle.perform());
+        let url = match try!(handle.effective_url()) {
+            Some(url) => url.to_string(),
+            None => return Ok(()),
+        };
+        visited.push(url);
+        let next = match try!(handle.redirect_url()) {
+            Some(next) => next.to_string(),
+            None => return Ok(()),
+        };
+        if let Some(i) = visited.iter().position(|u| *u == next) {
+            let mut cycle = visited.split_off(i);
+            cycle.push(next);
+            return Err(RedirectError::Loop(cycle))
+        }
+        if visited.len() > max as usize {
+            return Err(RedirectError::TooMany(visited))
+        }
+        if try!(handle.response_code()) == 303 {
+            try!(handle.get(true));
+        }
+        try!(handle.url(&next));
+    }
+}
+
+/// An error returned from `follow_redirects`.
+#[derive(Debug)]
+pub enum RedirectError {
+    /// One of the transfers failed.
+    Curl(Error),
+
+    /// A URL was redirected to for a second time.
+    ///
+    /// The list starts with the first visit of the repeated URL and ends with
+    /// the redirect back to it, so it always has at least two elements and
+    /// its first and last entries are equal.
+    Loop(Vec<String>),
+
+    /// More redirects than allowed were encountered without revisiting a URL.
+    ///
+    /// Contains the URLs visited so far, in order.
+    TooMany(Vec<String>),
+}
+
+impl RedirectError {
+    /// Returns the URLs making up the cycle if this is a redirect loop.
+    pub fn cycle(&self) -> Option<&[String]> {
+        match *self {
+            RedirectError::Loop(ref cycle) => Some(cycle),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for RedirectError {
+    fn from(e: Error) -> RedirectError {
+        RedirectError::Curl(e)
+    }
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RedirectError::Curl(ref e) => e.fmt(f),
+            RedirectError::Loop(ref cycle) => {
+                write!(f, "redirect loop: {}", cycle.join(" -> "))
+            }
+            RedirectError::TooMany(ref urls) => {
+                write!(f, "too many redirects ({} followed)", urls.len() - 1)
+            }
+        }
+    }
+}
+
+impl error::Error for RedirectError {
+    fn description(&self) -> &str {
+        match *self {
+            RedirectError::Curl(ref e) => error::Error::description(e),
+            RedirectError::Loop(..) => "redirect loop detected",
+            RedirectError::TooMany(..) => "too many redirects",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            RedirectError::Curl(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<RedirectError> for io::Error {
+    fn from(e: RedirectError) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, e)
+    }
+}
+
+/// An error returned from `Request::from_curl_command`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurlCommandError {
+    /// The command line couldn't be split into words, for example because a
+    /// quote isn't closed.
+    Syntax,
+
+    /// The command line doesn't start with `curl`.
+    NotCurl,
+
+    /// No URL is given.
+    MissingUrl,
+
+    /// The option is missing its value.
+    MissingValue(String),
+
+    /// The option isn't supported.
+    Unsupported(String),
+
+    /// The value given to the option isn't supported.
+    Invalid(String, String),
+}
+
+impl fmt::Display for CurlCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CurlCommandError::MissingValue(ref option) => {
+                write!(f, "option `{}` is missing its value", option)
+            }
+            CurlCommandError::Unsupported(ref option) => {
+                write!(f, "unsupported option `{}`", option)
+            }
+            CurlCommandError::Invalid(ref option, ref value) => {
+                write!(f, "unsupported value {:?} for option `{}`",
+                       value, option)
+            }
+            _ => error::Error::description(self).fmt(f),
+        }
+    }
+}
+
+impl error::Error for CurlCommandError {
+    fn description(&self) -> &str {
+        match *self {
+            CurlCommandError::Syntax => "invalid command line syntax",
+            CurlCommandError::NotCurl => "command isn't a curl command",
+            CurlCommandError::MissingUrl => "no URL given",
+            CurlCommandError::MissingValue(..) => "option is missing its value",
+            CurlCommandError::Unsupported(..) => "unsupported option",
+            CurlCommandError::Invalid(..) => "unsupported option value",
+        }
+    }
+}
+
+/// An error from the JSON helpers of `Request` and `Response`.
+///
+/// This is only available with the `json` feature of this crate.
+#[cfg(feature = "json")]
+#[derive(Debug)]
+pub enum JsonError {
+    /// The response didn't have a JSON content type. The media type it had
+    /// instead, if any, is included.
+    ContentType(Option<String>),
+    /// The value couldn't be serialized or deserialized.
+    Json(serde_json::Error),
+}
+
+#[cfg(feature = "json")]
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JsonError::ContentType(Some(ref mime)) => {
+                write!(f, "expected a JSON response, got {}", mime)
+            }
+            JsonError::ContentType(None) => {
+                write!(f, "expected a JSON response, got no content type")
+            }
+            JsonError::Json(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl error::Error for JsonError {
+    fn description(&self) -> &str {
+        match *self {
+            JsonError::ContentType(..) => "response isn't JSON",
+            JsonError::Json(..) => "invalid JSON",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            JsonError::ContentType(..) => None,
+            JsonError::Json(ref e) => Some(e),
+        }
+    }
+}
+
+/// Preferences used to pick one of the variants offered by a server.
+///
+/// Languages and media types are listed in decreasing order of preference.
+/// Media types may use wildcards such as `text/*` or `*/*`.
+#[derive(Clone, Debug, Default)]
+pub struct Preferences {
+    languages: Vec<String>,
+    types: Vec<String>,
+}
+
+/// A variant of a resource, as listed in an `Alternates` header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    url: String,
+    quality: f32,
+    content_type: Option<String>,
+    language: Option<String>,
+    charset: Option<String>,
+}
+
+impl Preferences {
+    /// Creates a new set of preferences which accepts any variant.
+    pub fn new() -> Preferences {
+        Preferences::default()
+    }
+
+    /// Adds a language, such as `en` or `de-CH`, to the list of preferred
+    /// languages.
+    pub fn language(&mut self, language: &str) -> &mut Preferences {
+        self.languages.push(language.to_lowercase());
+        self
+    }
+
+    /// Adds a media type, such as `text/html`, to the list of preferred
+    /// types.
+    pub fn content_type(&mut self, content_type: &str) -> &mut Preferences {
+        self.types.push(content_type.to_lowercase());
+        self
+    }
+
+    /// Picks the variant which best matches these preferences.
+    ///
+    /// Each variant is scored by its source quality multiplied by how early
+    /// its language and media type appear in the lists of preferences.
+    /// Variants whose language or type isn't listed at all are only picked if
+    /// nothing else matches. Returns `None` if `variants` is empty.
+    pub fn select<'a>(&self, variants: &'a [Variant]) -> Option<&'a Variant> {
+        let mut best = None;
+        let mut best_score = -1.0;
+        for variant in variants {
+            let lang = score(&self.languages, variant.language(), |p, l| {
+                l == p || l.starts_with(&format!("{}-", p))
+            });
+            let ty = score(&self.types, variant.content_type(), |p, t| {
+                p == "*/*" || p == t ||
+                    (p.ends_with("/*") && t.starts_with(&p[..p.len() - 1]))
+            });
+            let score = variant.quality * lang * ty;
+            if score > best_score {
+                best = Some(variant);
+                best_score = score;
+            }
+        }
+        best
+    }
+}
+
+fn score<F>(prefs: &[String], value: Option<&str>, matches: F) -> f32
+    where F: Fn(&str, &str) -> bool
+{
+    let value = match value {
+        Some(value) if !prefs.is_empty() => value.to_lowercase(),
+        _ => return 1.0,
+    };
+    match prefs.iter().position(|p| matches(p, &value)) {
+        Some(i) => 1.0 - i as f32 / (prefs.len() + 1) as f32,
+        None => 0.001,
+    }
+}
+
+impl Variant {
+    /// Returns the URL of this variant, as listed by the server.
+    ///
+    /// This may be relative to the URL of the negotiated resource.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the source quality of this variant, between 0 and 1.
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    /// Returns the media type of this variant, if listed.
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the language of this variant, if listed.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the charset of this variant, if listed.
+    pub fn charset(&self) -> Option<&str> {
+        self.charset.as_ref().map(|s| &s[..])
+    }
+}
+
+/// Parses the value of an `Alternates` header as described in RFC 2295.
+///
+/// Only the variant descriptions are returned. Fallback variants, other
+/// directives and malformed descriptions are skipped.
+///
+/// ```
+/// use curl::http;
+///
+/// let variants = http::parse_alternates(
+///     r#"{"paper.html.en" 0.9 {type text/html} {language en}},
+///        {"paper.html.fr" 0.7 {type text/html} {language fr}}"#);
+/// assert_eq!(variants.len(), 2);
+/// assert_eq!(variants[1].language(), Some("fr"));
+/// ```
+pub fn parse_alternates(header: &str) -> Vec<Variant> {
+    let mut variants = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in header.char_indices() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    start = i + 1;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(v) = parse_variant(&header[start..i]) {
+                        variants.push(v);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    variants
+}
+
+fn parse_variant(desc: &str) -> Option<Variant> {
+    let desc = desc.trim();
+    if !desc.starts_with('"') {
+        return None
+    }
+    let end = match desc[1..].find('"') {
+        Some(end) => end + 1,
+        None => return None,
+    };
+    let mut variant = Variant {
+        url: desc[1..end].to_string(),
+        quality: 1.0,
+        content_type: None,
+        language: None,
+        charset: None,
+    };
+    let mut rest = desc[end + 1..].trim();
+    let q_end = rest.find(|c: char| c.is_whitespace() || c == '{')
+                    .unwrap_or(rest.len());
+    if let Ok(q) = rest[..q_end].parse() {
+        variant.quality = q;
+        rest = &rest[q_end..];
+    }
+    for attr in rest.split('{').skip(1) {
+        let attr = attr.trim_matches(|c: char| {
+            c == '}' || c.is_whitespace()
+        });
+        let mut parts = attr.splitn(2, char::is_whitespace);
+        let value = match parts.nth(1) {
+            Some(value) => Some(value.trim().to_string()),
+            None => continue,
+        };
+        match attr.split_whitespace().next() {
+            Some("type") => variant.content_type = value,
+            Some("language") => variant.language = value,
+            Some("charset") => variant.charset = value,
+            _ => {}
+        }
+    }
+    Some(variant)
+}
+
+/// Performs a transfer, automatically picking a variant if the server
+/// responds with `300 Multiple Choices`.
+///
+/// If the response to the request is a `300`, the variants listed in its
+/// `Alternates` header are matched against `prefs` and a follow-up request is
+/// made for the best one. If no variants are listed the server's preferred
+/// choice given by the `Location` header is fetched instead, if any.
+///
+/// The `write` callback receives the body of the final response only, the
+/// body of a `300` response is discarded. This performs the transfers
+/// through `Easy::transfer`, so a header callback configured on `handle`
+/// isn't invoked.
+///
+/// Returns the URL of the variant that was fetched, or `None` if no
+/// negotiation took place.
+pub fn perform_negotiated<F>(handle: &mut Easy,
+                             prefs: &Preferences,
+                             mut write: F) -> Result<Option<String>, Error>
+    where F: FnMut(&[u8]) -> Result<usize, WriteError>
+{
+    let status = Cell::new(0);
+    let alternates = RefCell::new(None);
+    {
+        let mut transfer = handle.transfer();
+        try!(transfer.header_function(|line| {
+            let line = match str::from_utf8(line) {
+                Ok(line) => line.trim(),
+                Err(_) => return true,
+            };
+            if let Some(code) = parse_status_code(line) {
+                status.set(code);
+                *alternates.borrow_mut() = None;
+            } else if let Some(value) = header_value(line, "alternates") {
+                *alternates.borrow_mut() = Some(value.to_string());
+            }
+            true
+        }));
+        try!(transfer.write_function(|data| {
+            if status.get() == 300 {
+                Ok(data.len())
+            } else {
+                write(data)
+            }
+        }));
+        try!(transfer.perform());
+    }
+    if status.get() != 300 {
+        return Ok(None)
+    }
+
+    let base = try!(handle.effective_url()).unwrap_or("").to_string();
+    let variants = alternates.borrow().as_ref()
+                             .map(|a| parse_alternates(a))
+                             .unwrap_or(Vec::new());
+    let next = match prefs.select(&variants) {
+        Some(variant) => resolve_url(&base, variant.url()),
+        None => match try!(handle.redirect_url()) {
+            Some(url) => url.to_string(),
+            None => return Ok(None),
+        },
+    };
+    try!(handle.url(&next));
+    let mut transfer = handle.transfer();
+    try!(transfer.write_function(write));
+    try!(transfer.perform());
+    Ok(Some(next))
+}
+
+/// Parses the response code out of an HTTP status line such as
+/// `HTTP/1.1 200 OK`.
+fn parse_status_code(line: &str) -> Option<u32> {
+    if !line.starts_with("HTTP/") {
+        return None
+    }
+    line.split_whitespace().nth(1).and_then(|code| code.parse().ok())
+}
+
+/// Returns the value of the header on `line` if its name is `name`, compared
+/// case-insensitively.
+fn header_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let mut parts = line.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(n), Some(value)) if n.trim().eq_ignore_ascii_case(name) => {
+            Some(value.trim())
+        }
+        _ => None,
+    }
+}
+
+/// Resolves a possibly relative URL reference against `base`.
+fn resolve_url(base: &str, url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string()
+    }
+    let authority_start = base.find("://").map(|i| i + 3).unwrap_or(0);
+    let path_start = base[authority_start..].find('/')
+                                            .map(|i| i + authority_start)
+                                            .unwrap_or(base.len());
+    if url.starts_with('/') {
+        return format!("{}{}", &base[..path_start], url)
+    }
+    let path = &base[path_start..];
+    let path = &path[..path.find(|c| c == '?' || c == '#')
+                          .unwrap_or(path.len())];
+    match path.rfind('/') {
+        Some(i) => format!("{}{}{}", &base[..path_start], &path[..i + 1], url),
+        None => format!("{}/{}", &base[..path_start], url),
+    }
+}
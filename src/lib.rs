@@ -51,6 +51,7 @@
 
 extern crate curl_sys;
 extern crate libc;
+extern crate libz_sys;
 
 #[cfg(all(unix, not(target_os = "macos")))]
 extern crate openssl_sys;
@@ -58,32 +59,135 @@ extern crate openssl_sys;
 extern crate openssl_probe;
 #[cfg(windows)]
 extern crate winapi;
+#[cfg(feature = "json")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
 
 use std::ffi::CStr;
 use std::str;
 use std::sync::{Once, ONCE_INIT};
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::atomic::{ATOMIC_BOOL_INIT, ATOMIC_ISIZE_INIT};
 
-pub use error::{Error, ShareError, MultiError, FormError};
+pub use error::{Error, ErrorKind, ShareError, MultiError, FormError};
 mod error;
 
 pub use version::{Version, Protocols};
 mod version;
 
 mod panic;
+mod zlib;
 pub mod easy;
 pub mod multi;
+pub mod download;
+pub mod fixture;
+pub mod glob;
+pub mod head_cache;
+pub mod http;
+pub mod profile;
+pub mod sink;
+pub mod sse;
+pub mod upload;
+
+static INIT: Once = ONCE_INIT;
+static INIT_RESULT: AtomicIsize = ATOMIC_ISIZE_INIT;
+static INITIALIZED: AtomicBool = ATOMIC_BOOL_INIT;
+static CLEANED_UP: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Flags selecting which parts of libcurl's global state `init_with`
+/// initializes.
+///
+/// These correspond to the `CURL_GLOBAL_*` flags of `curl_global_init`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InitFlags {
+    bits: libc::c_long,
+}
+
+impl InitFlags {
+    /// Creates a new set of flags which doesn't initialize anything beyond
+    /// what libcurl always needs.
+    pub fn new() -> InitFlags {
+        InitFlags { bits: curl_sys::CURL_GLOBAL_NOTHING }
+    }
+
+    /// Creates a new set of flags which initializes everything, the default
+    /// used by `init`.
+    pub fn all() -> InitFlags {
+        InitFlags { bits: curl_sys::CURL_GLOBAL_ALL }
+    }
+
+    /// Initialize the SSL library libcurl was built with.
+    ///
+    /// Applications which initialize the SSL library themselves may disable
+    /// this, but then must do so before any transfer is made.
+    pub fn ssl(&mut self, on: bool) -> &mut InitFlags {
+        self.flag(curl_sys::CURL_GLOBAL_SSL, on)
+    }
+
+    /// Initialize the Win32 socket libraries.
+    ///
+    /// This has no effect on other platforms.
+    pub fn win32(&mut self, on: bool) -> &mut InitFlags {
+        self.flag(curl_sys::CURL_GLOBAL_WIN32, on)
+    }
+
+    fn flag(&mut self, bit: libc::c_long, on: bool) -> &mut InitFlags {
+        if on {
+            self.bits |= bit;
+        } else {
+            self.bits &= !bit;
+        }
+        self
+    }
+}
+
+impl Default for InitFlags {
+    fn default() -> InitFlags {
+        InitFlags::all()
+    }
+}
 
 /// Initializes the underlying libcurl library.
 ///
 /// It's not required to call this before the library is used, but it's
-/// recommended to do so as soon as the program starts.
+/// recommended to do so as soon as the program starts. This is the same as
+/// `init_with(&InitFlags::all())`, except that it panics if libcurl fails to
+/// initialize.
 pub fn init() {
-    static INIT: Once = ONCE_INIT;
+    if let Err(e) = init_with(&InitFlags::all()) {
+        panic!("failed to initialize libcurl: {}", e);
+    }
+}
+
+/// Initializes the underlying libcurl library with the given `flags`.
+///
+/// libcurl's global initialization isn't thread safe, so this crate makes
+/// sure it runs exactly once: only the first call to this function or `init`
+/// initializes libcurl, while all other calls, from any thread, wait for it
+/// to finish and return its result. Creating an `Easy` or `Multi` handle
+/// implicitly calls `init`, so to use other flags this must be called before
+/// the first handle is created.
+///
+/// An error is returned if libcurl failed to initialize, or if `cleanup` has
+/// already been called.
+pub fn init_with(flags: &InitFlags) -> Result<(), Error> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    fn platform_init() {
+        openssl_sys::init();
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    fn platform_init() {}
+
+    if CLEANED_UP.load(Ordering::SeqCst) {
+        return Err(Error::new(curl_sys::CURLE_FAILED_INIT))
+    }
     INIT.call_once(|| {
         platform_init();
-        unsafe {
-            assert_eq!(curl_sys::curl_global_init(curl_sys::CURL_GLOBAL_ALL), 0);
-        }
+        let rc = unsafe { curl_sys::curl_global_init(flags.bits) };
+        INIT_RESULT.store(rc as isize, Ordering::SeqCst);
+        INITIALIZED.store(rc == curl_sys::CURLE_OK, Ordering::SeqCst);
 
         // Note that we explicitly don't schedule a call to
         // `curl_global_cleanup`. The documentation for that function says
@@ -93,16 +197,49 @@ pub fn init() {
         // > no other thread that is using libcurl.
         //
         // We can't ever be sure of that, so unfortunately we can't call the
-        // function.
+        // function. Applications which can be sure may call `cleanup`
+        // themselves.
     });
+    cvt(INIT_RESULT.load(Ordering::SeqCst) as curl_sys::CURLcode)
+}
 
-    #[cfg(all(unix, not(target_os = "macos")))]
-    fn platform_init() {
-        openssl_sys::init();
+/// Releases the global resources acquired by libcurl when it was initialized.
+///
+/// This corresponds to `curl_global_cleanup`. It's only run if libcurl was
+/// initialized successfully, and at most once. libcurl can't be initialized
+/// again afterwards: `init_with` returns an error and creating a new handle
+/// panics.
+///
+/// # Safety
+///
+/// libcurl requires that no other thread in the program is running while
+/// this is called, not even one which doesn't use libcurl, and that no
+/// handle is used afterwards. It's typically only useful right before a
+/// program exits, or to unload libcurl cleanly from a dynamic library.
+pub unsafe fn cleanup() {
+    if CLEANED_UP.swap(true, Ordering::SeqCst) {
+        return
     }
+    if INITIALIZED.load(Ordering::SeqCst) {
+        curl_sys::curl_global_cleanup();
+    }
+}
 
-    #[cfg(not(all(unix, not(target_os = "macos"))))]
-    fn platform_init() {}
+/// Returns information about the version of libcurl this library is linked
+/// against and the features it supports.
+///
+/// This is the same as `Version::get`.
+///
+/// ```
+/// let version = curl::version();
+/// println!("libcurl {} with {}", version.version(),
+///          version.ssl_version().unwrap_or("no SSL"));
+/// if !version.feature_http2() {
+///     println!("HTTP/2 isn't available");
+/// }
+/// ```
+pub fn version() -> Version {
+    Version::get()
 }
 
 unsafe fn opt_str<'a>(ptr: *const libc::c_char) -> Option<&'a str> {
@@ -120,3 +257,12 @@ fn cvt(r: curl_sys::CURLcode) -> Result<(), Error> {
         Err(Error::new(r))
     }
 }
+
+/// Resets a string option of `handle` to its default of not being set.
+fn clear_option(handle: &mut easy::Easy, opt: curl_sys::CURLoption)
+                -> Result<(), Error> {
+    unsafe {
+        cvt(curl_sys::curl_easy_setopt(handle.raw(), opt,
+                                       0 as *const libc::c_char))
+    }
+}
@@ -238,6 +238,95 @@ impl Multi {
         }
     }
 
+    /// Set the size of the connection cache.
+    ///
+    /// The set number will be used as the maximum amount of simultaneously
+    /// open connections that libcurl may keep in its connection cache after
+    /// completed use. When the cache is full curl closes the oldest one to
+    /// make room for the new connection. A value of 0 restores the default.
+    ///
+    /// By default libcurl sizes the cache to four times the number of easy
+    /// handles added, and this corresponds to `CURLMOPT_MAXCONNECTS`.
+    pub fn max_connects(&mut self, max: usize) -> Result<(), MultiError> {
+        self.setopt_long(curl_sys::CURLMOPT_MAXCONNECTS, max as c_long)
+    }
+
+    /// Set the maximum number of connections to a single host.
+    ///
+    /// Transfers which would need another connection to a host which already
+    /// has this many open are queued until one of them is done or can be
+    /// reused.
+    ///
+    /// By default this option is 0, meaning no limit, and corresponds to
+    /// `CURLMOPT_MAX_HOST_CONNECTIONS`.
+    pub fn max_host_connections(&mut self, max: usize)
+                                -> Result<(), MultiError> {
+        self.setopt_long(curl_sys::CURLMOPT_MAX_HOST_CONNECTIONS,
+                         max as c_long)
+    }
+
+    /// Set the maximum number of simultaneously open connections.
+    ///
+    /// Transfers which would exceed this limit are queued until a connection
+    /// becomes available. Unlike `max_connects` this also bounds connections
+    /// which are in use, not just the ones kept around for reuse.
+    ///
+    /// By default this option is 0, meaning no limit, and corresponds to
+    /// `CURLMOPT_MAX_TOTAL_CONNECTIONS`.
+    pub fn max_total_connections(&mut self, max: usize)
+                                 -> Result<(), MultiError> {
+        self.setopt_long(curl_sys::CURLMOPT_MAX_TOTAL_CONNECTIONS,
+                         max as c_long)
+    }
+
+    /// Set whether transfers may share a connection.
+    ///
+    /// With `multiplex` enabled, transfers to a host which speaks HTTP/2 are
+    /// sent as concurrent streams of a single connection. `http_1` enables
+    /// HTTP/1.1 pipelining, where requests are sent on a connection without
+    /// waiting for the responses before them, which libcurl 7.62.0 and later
+    /// no longer support and ignore.
+    ///
+    /// Transfers only wait for a connection which is still being set up to
+    /// turn out to support multiplexing if they enable `Easy::http_pipewait`.
+    ///
+    /// By default only `multiplex` is enabled as of libcurl 7.62.0, neither
+    /// is before, and this corresponds to `CURLMOPT_PIPELINING`.
+    pub fn pipelining(&mut self, http_1: bool, multiplex: bool)
+                      -> Result<(), MultiError> {
+        let mut bits = curl_sys::CURLPIPE_NOTHING;
+        if http_1 {
+            bits |= curl_sys::CURLPIPE_HTTP1;
+        }
+        if multiplex {
+            bits |= curl_sys::CURLPIPE_MULTIPLEX;
+        }
+        self.setopt_long(curl_sys::CURLMOPT_PIPELINING, bits)
+    }
+
+    /// Set the maximum number of concurrent streams on a connection.
+    ///
+    /// Transfers beyond this many on a multiplexed connection wait for one
+    /// of its streams to finish, or use another connection as permitted by
+    /// `max_host_connections`. The server may allow fewer streams than this.
+    ///
+    /// By default this option is 100 and corresponds to
+    /// `CURLMOPT_MAX_CONCURRENT_STREAMS`, which requires libcurl 7.67.0 or
+    /// later.
+    pub fn max_concurrent_streams(&mut self, max: usize)
+                                  -> Result<(), MultiError> {
+        self.setopt_long(curl_sys::CURLMOPT_MAX_CONCURRENT_STREAMS,
+                         max as c_long)
+    }
+
+    fn setopt_long(&mut self,
+                   opt: curl_sys::CURLMoption,
+                   val: c_long) -> Result<(), MultiError> {
+        unsafe {
+            cvt(curl_sys::curl_multi_setopt(self.raw, opt, val))
+        }
+    }
+
     fn setopt_ptr(&mut self,
                   opt: curl_sys::CURLMoption,
                   val: *const c_char) -> Result<(), MultiError> {
@@ -457,6 +546,44 @@ impl Multi {
         }
     }
 
+    /// Waits for activity on any of the curl easy handles in this multi handle
+    ///
+    /// This function polls on all file descriptors used by the curl easy
+    /// handles contained in this multi handle. It blocks until activity is
+    /// detected on at least one of the handles or `timeout` has passed.
+    /// Unlike `fdset` this has no limitation on the number of file
+    /// descriptors.
+    ///
+    /// If libcurl has no descriptors to wait on, for example while a name is
+    /// still being resolved, this returns immediately instead of waiting for
+    /// the timeout. Unlike `curl_multi_poll`, which waits regardless, a loop
+    /// calling this should therefore sleep for a short while when it keeps
+    /// reporting no activity, rather than spin.
+    ///
+    /// Returns the number of file descriptors on which something interesting
+    /// happened. This function is typically used in a loop with `perform`.
+    ///
+    /// This corresponds to `curl_multi_wait` without any extra file
+    /// descriptors.
+    pub fn wait(&self, timeout: Duration) -> Result<u32, MultiError> {
+        let timeout_ms = timeout.as_secs() * 1000 +
+                         (timeout.subsec_nanos() / 1_000_000) as u64;
+        let timeout_ms = if timeout_ms > c_int::max_value() as u64 {
+            c_int::max_value()
+        } else {
+            timeout_ms as c_int
+        };
+        unsafe {
+            let mut ret = 0;
+            try!(cvt(curl_sys::curl_multi_wait(self.raw,
+                                               0 as *mut _,
+                                               0,
+                                               timeout_ms,
+                                               &mut ret)));
+            Ok(ret as u32)
+        }
+    }
+
     /// Extracts file descriptor information from a multi handle
     ///
     /// This function extracts file descriptor information from a given
@@ -0,0 +1,528 @@
+//! Reusable sets of handle configuration
+//!
+//! A `Profile` records a subset of the options which can be configured on an
+//! `Easy` handle: timeouts, proxy and TLS settings, and a list of default
+//! headers. Profiles can be saved to and loaded from a small TOML document,
+//! which makes it easy for command line tools to offer `--profile work` style
+//! switching between configurations.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::Read;
+//!
+//! use curl::easy::Easy;
+//! use curl::profile::Profile;
+//!
+//! let mut contents = String::new();
+//! File::open("work.toml").unwrap().read_to_string(&mut contents).unwrap();
+//! let profile = Profile::load(&contents).unwrap();
+//!
+//! let mut handle = Easy::new();
+//! profile.apply(&mut handle).unwrap();
+//! handle.url("https://www.rust-lang.org/").unwrap();
+//! handle.perform().unwrap();
+//! ```
+//!
+//! Secrets are never written out by `save`: credentials embedded in the proxy
+//! URL are stripped, and `Authorization`, `Proxy-Authorization` and `Cookie`
+//! headers are skipped.
+
+use std::env;
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use Error;
+use easy::{Easy, List};
+
+/// A set of options which can be applied to an `Easy` handle.
+///
+/// See the [module documentation](index.html) for more information.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<String>,
+    noproxy: Option<String>,
+    useragent: Option<String>,
+    cainfo: Option<PathBuf>,
+    capath: Option<PathBuf>,
+    ssl_verify_peer: Option<bool>,
+    ssl_verify_host: Option<bool>,
+    follow_location: Option<bool>,
+    max_redirections: Option<u32>,
+    verbose: Option<bool>,
+    headers: Vec<String>,
+}
+
+impl Profile {
+    /// Creates a new profile which doesn't configure anything.
+    pub fn new() -> Profile {
+        Profile::default()
+    }
+
+    /// Sets the value passed to `Easy::timeout`.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Profile {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the value passed to `Easy::connect_timeout`.
+    pub fn connect_timeout(&mut self, timeout: Duration) -> &mut Profile {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the value passed to `Easy::proxy`.
+    pub fn proxy(&mut self, url: &str) -> &mut Profile {
+        self.proxy = Some(url.to_string());
+        self
+    }
+
+    /// Sets the value passed to `Easy::noproxy`.
+    pub fn noproxy(&mut self, skip: &str) -> &mut Profile {
+        self.noproxy = Some(skip.to_string());
+        self
+    }
+
+    /// Sets the value passed to `Easy::useragent`.
+    pub fn useragent(&mut self, useragent: &str) -> &mut Profile {
+        self.useragent = Some(useragent.to_string());
+        self
+    }
+
+    /// Sets the value passed to `Easy::cainfo`.
+    pub fn cainfo<P: Into<PathBuf>>(&mut self, path: P) -> &mut Profile {
+        self.cainfo = Some(path.into());
+        self
+    }
+
+    /// Sets the value passed to `Easy::capath`.
+    pub fn capath<P: Into<PathBuf>>(&mut self, path: P) -> &mut Profile {
+        self.capath = Some(path.into());
+        self
+    }
+
+    /// Sets the value passed to `Easy::ssl_verify_peer`.
+    pub fn ssl_verify_peer(&mut self, verify: bool) -> &mut Profile {
+        self.ssl_verify_peer = Some(verify);
+        self
+    }
+
+    /// Sets the value passed to `Easy::ssl_verify_host`.
+    pub fn ssl_verify_host(&mut self, verify: bool) -> &mut Profile {
+        self.ssl_verify_host = Some(verify);
+        self
+    }
+
+    /// Sets the value passed to `Easy::follow_location`.
+    pub fn follow_location(&mut self, enable: bool) -> &mut Profile {
+        self.follow_location = Some(enable);
+        self
+    }
+
+    /// Sets the value passed to `Easy::max_redirections`.
+    pub fn max_redirections(&mut self, max: u32) -> &mut Profile {
+        self.max_redirections = Some(max);
+        self
+    }
+
+    /// Sets the value passed to `Easy::verbose`.
+    pub fn verbose(&mut self, verbose: bool) -> &mut Profile {
+        self.verbose = Some(verbose);
+        self
+    }
+
+    /// Adds a header, in the `Name: value` format, to be sent with every
+    /// request.
+    pub fn header(&mut self, header: &str) -> &mut Profile {
+        self.headers.push(header.to_string());
+        self
+    }
+
+    /// Creates a profile from environment variables.
+    ///
+    /// The following variables are read, and any which aren't set are left
+    /// unconfigured in the returned profile:
+    ///
+    /// * `CURL_TIMEOUT_MS` - `timeout`, in milliseconds
+    /// * `CURL_CONNECT_TIMEOUT_MS` - `connect_timeout`, in milliseconds
+    /// * `CURL_PROXY` - `proxy`
+    /// * `CURL_NOPROXY` - `noproxy`
+    /// * `CURL_USERAGENT` - `useragent`
+    /// * `CURL_CA_BUNDLE` - `cainfo`
+    /// * `CURL_CA_PATH` - `capath`
+    /// * `CURL_VERBOSE` - `verbose`, either `1`/`true` or `0`/`false`
+    ///
+    /// Note that libcurl itself already honors the conventional `http_proxy`,
+    /// `https_proxy`, `ALL_PROXY` and `NO_PROXY` variables, these are only
+    /// needed to override them.
+    ///
+    /// An error is returned if any of the variables holds an invalid value.
+    pub fn from_env() -> io::Result<Profile> {
+        let mut profile = Profile::new();
+        if let Some(ms) = try!(env_var("CURL_TIMEOUT_MS")) {
+            profile.timeout(try!(env_millis("CURL_TIMEOUT_MS", &ms)));
+        }
+        if let Some(ms) = try!(env_var("CURL_CONNECT_TIMEOUT_MS")) {
+            profile.connect_timeout(try!(env_millis("CURL_CONNECT_TIMEOUT_MS",
+                                                    &ms)));
+        }
+        if let Some(proxy) = try!(env_var("CURL_PROXY")) {
+            profile.proxy(&proxy);
+        }
+        if let Some(skip) = try!(env_var("CURL_NOPROXY")) {
+            profile.noproxy(&skip);
+        }
+        if let Some(useragent) = try!(env_var("CURL_USERAGENT")) {
+            profile.useragent(&useragent);
+        }
+        if let Some(path) = env::var_os("CURL_CA_BUNDLE") {
+            profile.cainfo(path);
+        }
+        if let Some(path) = env::var_os("CURL_CA_PATH") {
+            profile.capath(path);
+        }
+        if let Some(verbose) = try!(env_var("CURL_VERBOSE")) {
+            profile.verbose(match &verbose[..] {
+                "1" | "true" => true,
+                "0" | "false" | "" => false,
+                _ => return Err(invalid_env("CURL_VERBOSE")),
+            });
+        }
+        Ok(profile)
+    }
+
+    /// Configures `handle` with all options set in this profile.
+    ///
+    /// Options which aren't set in the profile are left untouched. If the
+    /// profile contains any headers they replace the handle's current list
+    /// configured with `http_headers`.
+    pub fn apply(&self, handle: &mut Easy) -> Result<(), Error> {
+        if let Some(t) = self.timeout {
+            try!(handle.timeout(t));
+        }
+        if let Some(t) = self.connect_timeout {
+            try!(handle.connect_timeout(t));
+        }
+        if let Some(ref proxy) = self.proxy {
+            try!(handle.proxy(proxy));
+        }
+        if let Some(ref skip) = self.noproxy {
+            try!(handle.noproxy(skip));
+        }
+        if let Some(ref useragent) = self.useragent {
+            try!(handle.useragent(useragent));
+        }
+        if let Some(ref path) = self.cainfo {
+            try!(handle.cainfo(path));
+        }
+        if let Some(ref path) = self.capath {
+            try!(handle.capath(path));
+        }
+        if let Some(verify) = self.ssl_verify_peer {
+            try!(handle.ssl_verify_peer(verify));
+        }
+        if let Some(verify) = self.ssl_verify_host {
+            try!(handle.ssl_verify_host(verify));
+        }
+        if let Some(enable) = self.follow_location {
+            try!(handle.follow_location(enable));
+        }
+        if let Some(max) = self.max_redirections {
+            try!(handle.max_redirections(max));
+        }
+        if let Some(verbose) = self.verbose {
+            try!(handle.verbose(verbose));
+        }
+        if !self.headers.is_empty() {
+            let mut list = List::new();
+            for header in self.headers.iter() {
+                try!(list.append(header));
+            }
+            try!(handle.http_headers(list));
+        }
+        Ok(())
+    }
+
+    /// Parses a profile from a TOML document, as written by `save`.
+    ///
+    /// Only a flat set of `key = value` pairs is supported, with the keys
+    /// named after the corresponding methods on this type. Timeouts are given
+    /// in milliseconds through `timeout_ms` and `connect_timeout_ms`, and
+    /// `headers` is an array of strings. Unknown keys are an error.
+    pub fn load(toml: &str) -> io::Result<Profile> {
+        let mut profile = Profile::new();
+        for (i, line) in toml.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => return Err(invalid(i, "expected `key = value`")),
+            };
+            let value = try!(Value::parse(value).ok_or_else(|| {
+                invalid(i, "invalid value")
+            }));
+            try!(profile.set(key, value).map_err(|msg| invalid(i, msg)));
+        }
+        Ok(profile)
+    }
+
+    fn set(&mut self, key: &str, value: Value) -> Result<(), &'static str> {
+        match key {
+            "timeout_ms" => self.timeout = Some(try!(value.millis())),
+            "connect_timeout_ms" => {
+                self.connect_timeout = Some(try!(value.millis()))
+            }
+            "proxy" => self.proxy = Some(try!(value.string())),
+            "noproxy" => self.noproxy = Some(try!(value.string())),
+            "useragent" => self.useragent = Some(try!(value.string())),
+            "cainfo" => self.cainfo = Some(try!(value.string()).into()),
+            "capath" => self.capath = Some(try!(value.string()).into()),
+            "ssl_verify_peer" => {
+                self.ssl_verify_peer = Some(try!(value.bool()))
+            }
+            "ssl_verify_host" => {
+                self.ssl_verify_host = Some(try!(value.bool()))
+            }
+            "follow_location" => {
+                self.follow_location = Some(try!(value.bool()))
+            }
+            "max_redirections" => {
+                let max = try!(value.int());
+                if max > u32::max_value() as u64 {
+                    return Err("integer out of range")
+                }
+                self.max_redirections = Some(max as u32)
+            }
+            "verbose" => self.verbose = Some(try!(value.bool())),
+            "headers" => self.headers = try!(value.array()),
+            _ => return Err("unknown key"),
+        }
+        Ok(())
+    }
+
+    /// Renders this profile as a TOML document which can be read back with
+    /// `load`.
+    ///
+    /// Secrets are omitted, see the [module documentation](index.html).
+    pub fn save(&self) -> String {
+        let mut out = String::new();
+        if let Some(t) = self.timeout {
+            writeln!(out, "timeout_ms = {}", millis(t)).unwrap();
+        }
+        if let Some(t) = self.connect_timeout {
+            writeln!(out, "connect_timeout_ms = {}", millis(t)).unwrap();
+        }
+        if let Some(ref proxy) = self.proxy {
+            writeln!(out, "proxy = {}", quote(&strip_userinfo(proxy))).unwrap();
+        }
+        if let Some(ref skip) = self.noproxy {
+            writeln!(out, "noproxy = {}", quote(skip)).unwrap();
+        }
+        if let Some(ref useragent) = self.useragent {
+            writeln!(out, "useragent = {}", quote(useragent)).unwrap();
+        }
+        if let Some(ref path) = self.cainfo {
+            let path = path.to_string_lossy();
+            writeln!(out, "cainfo = {}", quote(&path)).unwrap();
+        }
+        if let Some(ref path) = self.capath {
+            let path = path.to_string_lossy();
+            writeln!(out, "capath = {}", quote(&path)).unwrap();
+        }
+        if let Some(verify) = self.ssl_verify_peer {
+            writeln!(out, "ssl_verify_peer = {}", verify).unwrap();
+        }
+        if let Some(verify) = self.ssl_verify_host {
+            writeln!(out, "ssl_verify_host = {}", verify).unwrap();
+        }
+        if let Some(enable) = self.follow_location {
+            writeln!(out, "follow_location = {}", enable).unwrap();
+        }
+        if let Some(max) = self.max_redirections {
+            writeln!(out, "max_redirections = {}", max).unwrap();
+        }
+        if let Some(verbose) = self.verbose {
+            writeln!(out, "verbose = {}", verbose).unwrap();
+        }
+        let headers = self.headers.iter()
+                                  .filter(|h| !is_secret_header(h))
+                                  .map(|h| quote(h))
+                                  .collect::<Vec<_>>();
+        if !headers.is_empty() {
+            writeln!(out, "headers = [{}]", headers.join(", ")).unwrap();
+        }
+        out
+    }
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,
+                   format!("line {}: {}", line + 1, msg))
+}
+
+fn env_var(name: &str) -> io::Result<Option<String>> {
+    match env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => Err(invalid_env(name)),
+    }
+}
+
+fn env_millis(name: &str, value: &str) -> io::Result<Duration> {
+    value.trim().parse().map(Duration::from_millis).map_err(|_| {
+        invalid_env(name)
+    })
+}
+
+fn invalid_env(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput,
+                   format!("invalid value in environment variable `{}`", name))
+}
+
+/// Returns `dur` in milliseconds, saturating at `u64::MAX`.
+fn millis(dur: Duration) -> u64 {
+    let sub = (dur.subsec_nanos() / 1_000_000) as u64;
+    dur.as_secs().checked_mul(1000)
+       .and_then(|ms| ms.checked_add(sub))
+       .unwrap_or(u64::max_value())
+}
+
+fn is_secret_header(header: &str) -> bool {
+    let name = header.split(':').next().unwrap_or("").trim();
+    ["authorization", "proxy-authorization", "cookie"].iter().any(|s| {
+        name.eq_ignore_ascii_case(s)
+    })
+}
+
+fn strip_userinfo(url: &str) -> String {
+    let start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    let end = url[start..].find('/').map(|i| i + start).unwrap_or(url.len());
+    match url[start..end].rfind('@') {
+        Some(i) => format!("{}{}", &url[..start], &url[start + i + 1..]),
+        None => url.to_string(),
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+enum Value {
+    Bool(bool),
+    Int(u64),
+    Str(String),
+    Array(Vec<String>),
+}
+
+impl Value {
+    fn parse(s: &str) -> Option<Value> {
+        match s {
+            "true" => return Some(Value::Bool(true)),
+            "false" => return Some(Value::Bool(false)),
+            _ => {}
+        }
+        if let Ok(n) = s.parse() {
+            return Some(Value::Int(n))
+        }
+        if s.starts_with('"') {
+            return match unquote(s) {
+                Some((v, "")) => Some(Value::Str(v)),
+                _ => None,
+            }
+        }
+        if s.starts_with('[') && s.ends_with(']') {
+            let mut rest = s[1..s.len() - 1].trim();
+            let mut items = Vec::new();
+            while !rest.is_empty() {
+                let (item, tail) = match unquote(rest) {
+                    Some(pair) => pair,
+                    None => return None,
+                };
+                items.push(item);
+                rest = tail.trim();
+                if rest.starts_with(',') {
+                    rest = rest[1..].trim();
+                } else if !rest.is_empty() {
+                    return None
+                }
+            }
+            return Some(Value::Array(items))
+        }
+        None
+    }
+
+    fn bool(self) -> Result<bool, &'static str> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            _ => Err("expected a boolean"),
+        }
+    }
+
+    fn int(self) -> Result<u64, &'static str> {
+        match self {
+            Value::Int(n) => Ok(n),
+            _ => Err("expected an integer"),
+        }
+    }
+
+    fn millis(self) -> Result<Duration, &'static str> {
+        self.int().map(Duration::from_millis)
+    }
+
+    fn string(self) -> Result<String, &'static str> {
+        match self {
+            Value::Str(s) => Ok(s),
+            _ => Err("expected a string"),
+        }
+    }
+
+    fn array(self) -> Result<Vec<String>, &'static str> {
+        match self {
+            Value::Array(a) => Ok(a),
+            _ => Err("expected an array of strings"),
+        }
+    }
+}
+
+/// Parses a basic TOML string at the start of `s`, returning it along with the
+/// remaining input.
+fn unquote(s: &str) -> Option<(String, &str)> {
+    if !s.starts_with('"') {
+        return None
+    }
+    let mut out = String::new();
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &s[i + 1..])),
+            '\\' => {
+                match chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 't')) => out.push('\t'),
+                    _ => return None,
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    None
+}
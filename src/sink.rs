@@ -0,0 +1,600 @@
+//! Write callbacks for bodies whose bytes aren't needed
+//!
+//! Verification and sizing jobs often only care about how large a response
+//! body is, or what its digest is. The sinks in this module can be plugged
+//! into `write_function` to compute exactly that while discarding the data
+//! itself.
+//!
+//! When the body is needed as well, a `TeeSink` writes it to any number of
+//! writers at once, such as a file and one of the sinks above, and can keep
+//! a copy in memory, so a response which is cached and parsed only has to be
+//! downloaded once.
+//!
+//! Sinks are cheap to clone and all clones share the same state, so one clone
+//! can be moved into the callback while another is used to read the result
+//! once the transfer is done.
+//!
+//! ```no_run
+//! use curl::easy::Easy;
+//! use curl::sink::DigestSink;
+//!
+//! let sink = DigestSink::new();
+//! let mut handle = Easy::new();
+//! handle.url("https://www.rust-lang.org/").unwrap();
+//! let callback = sink.clone();
+//! handle.write_function(move |data| callback.write(data)).unwrap();
+//! handle.perform().unwrap();
+//! println!("{} bytes, sha256 {}", sink.count(), sink.hex_digest());
+//! ```
+
+use std::cmp;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use easy::WriteError;
+
+/// A sink which only counts the bytes written to it.
+#[derive(Clone, Debug, Default)]
+pub struct CountSink {
+    count: Arc<Mutex<u64>>,
+}
+
+/// A sink which computes the SHA-256 digest of the bytes written to it.
+#[derive(Clone)]
+pub struct DigestSink {
+    state: Arc<Mutex<(Sha256, u64)>>,
+}
+
+/// A sink which writes the bytes written to it to several writers in turn.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use curl::easy::Easy;
+/// use curl::sink::{DigestSink, TeeSink};
+///
+/// let digest = DigestSink::new();
+/// let mut tee = TeeSink::new();
+/// tee.writer(File::create("index.html").unwrap())
+///    .writer(digest.clone())
+///    .keep_body();
+///
+/// let mut handle = Easy::new();
+/// handle.url("https://www.rust-lang.org/").unwrap();
+/// let callback = tee.clone();
+/// handle.write_function(move |data| callback.write(data)).unwrap();
+/// handle.perform().unwrap();
+/// tee.flush().unwrap();
+/// let body = tee.take_body();
+/// println!("{} bytes, sha256 {}", body.len(), digest.hex_digest());
+/// ```
+#[derive(Clone)]
+pub struct TeeSink {
+    state: Arc<Mutex<Tee>>,
+}
+
+#[derive(Default)]
+struct Tee {
+    writers: Vec<Box<Write + Send>>,
+    body: Option<Vec<u8>>,
+    count: u64,
+    error: Option<io::Error>,
+}
+
+impl CountSink {
+    /// Creates a new sink with a count of zero.
+    pub fn new() -> CountSink {
+        CountSink::default()
+    }
+
+    /// Counts `data`, suitable for returning from a write callback.
+    pub fn write(&self, data: &[u8]) -> Result<usize, WriteError> {
+        *self.count.lock().unwrap() += data.len() as u64;
+        Ok(data.len())
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn count(&self) -> u64 {
+        *self.count.lock().unwrap()
+    }
+}
+
+impl DigestSink {
+    /// Creates a new sink which hasn't seen any data yet.
+    pub fn new() -> DigestSink {
+        DigestSink {
+            state: Arc::new(Mutex::new((Sha256::new(), 0))),
+        }
+    }
+
+    /// Hashes `data`, suitable for returning from a write callback.
+    pub fn write(&self, data: &[u8]) -> Result<usize, WriteError> {
+        let mut state = self.state.lock().unwrap();
+        state.0.update(data);
+        state.1 += data.len() as u64;
+        Ok(data.len())
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn count(&self) -> u64 {
+        self.state.lock().unwrap().1
+    }
+
+    /// Returns the SHA-256 digest of all bytes written so far.
+    pub fn digest(&self) -> [u8; 32] {
+        self.state.lock().unwrap().0.clone().finish()
+    }
+
+    /// Returns the SHA-256 digest of all bytes written so far as a lowercase
+    /// hex string.
+    pub fn hex_digest(&self) -> String {
+        to_hex(&self.digest())
+    }
+}
+
+impl Default for DigestSink {
+    fn default() -> DigestSink {
+        DigestSink::new()
+    }
+}
+
+impl Write for CountSink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        *self.count.lock().unwrap() += data.len() as u64;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for DigestSink {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.0.update(data);
+        state.1 += data.len() as u64;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TeeSink {
+    /// Creates a new sink without any writers, which discards what it's
+    /// given until some are added.
+    pub fn new() -> TeeSink {
+        TeeSink::default()
+    }
+
+    /// Adds a writer which receives every byte written to this sink, after
+    /// the writers added before it.
+    ///
+    /// `CountSink` and `DigestSink` implement `Write`, so they can be added
+    /// here to size or hash the body on the way through.
+    pub fn writer<W>(&mut self, writer: W) -> &mut TeeSink
+        where W: Write + Send + 'static
+    {
+        self.state.lock().unwrap().writers.push(Box::new(writer));
+        self
+    }
+
+    /// Keeps a copy of every byte written to this sink in memory, to be
+    /// retrieved with `take_body`.
+    pub fn keep_body(&mut self) -> &mut TeeSink {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.body.is_none() {
+                state.body = Some(Vec::new());
+            }
+        }
+        self
+    }
+
+    /// Writes `data` to every writer, suitable for returning from a write
+    /// callback.
+    ///
+    /// If a writer fails, the error is saved for `take_error` and the
+    /// transfer is aborted, and no further data is written to any writer.
+    pub fn write(&self, data: &[u8]) -> Result<usize, WriteError> {
+        let mut state = self.state.lock().unwrap();
+        if state.error.is_some() {
+            return Ok(0)
+        }
+        let mut result = Ok(());
+        for writer in state.writers.iter_mut() {
+            result = writer.write_all(data);
+            if result.is_err() {
+                break
+            }
+        }
+        if let Err(e) = result {
+            state.error = Some(e);
+            return Ok(0)
+        }
+        if let Some(ref mut body) = state.body {
+            body.extend_from_slice(data);
+        }
+        state.count += data.len() as u64;
+        Ok(data.len())
+    }
+
+    /// Flushes every writer, which should be done once the transfer is
+    /// complete for writers which buffer data.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for writer in state.writers.iter_mut() {
+            try!(writer.flush());
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes written to every writer so far.
+    pub fn count(&self) -> u64 {
+        self.state.lock().unwrap().count
+    }
+
+    /// Returns the copy of the body kept since the last call, which is empty
+    /// unless `keep_body` was called.
+    pub fn take_body(&self) -> Vec<u8> {
+        match self.state.lock().unwrap().body {
+            Some(ref mut body) => mem::replace(body, Vec::new()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the error a writer failed with, if any, which aborted the
+    /// transfer.
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.state.lock().unwrap().error.take()
+    }
+}
+
+impl Default for TeeSink {
+    fn default() -> TeeSink {
+        TeeSink {
+            state: Arc::new(Mutex::new(Tee::default())),
+        }
+    }
+}
+
+/// An incremental SHA-256 hasher.
+///
+/// ```
+/// use curl::sink::{self, Sha256};
+///
+/// let mut hasher = Sha256::new();
+/// hasher.update(b"abc");
+/// assert_eq!(sink::to_hex(&hasher.finish()),
+///            "ba7816bf8f01cfea414140de5dae2223\
+///             b00361a396177a9cb410ff61f20015ad");
+/// ```
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buf: [u8; 64],
+    buf_len: usize,
+    len: u64,
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+impl Sha256 {
+    /// Creates a new hasher.
+    pub fn new() -> Sha256 {
+        Sha256 {
+            state: [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+                    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19],
+            buf: [0; 64],
+            buf_len: 0,
+            len: 0,
+        }
+    }
+
+    /// Feeds `data` into the hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        if self.buf_len > 0 {
+            let start = self.buf_len;
+            let n = cmp::min(64 - start, data.len());
+            self.buf[start..start + n].copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+            if self.buf_len < 64 {
+                return
+            }
+            let block = self.buf;
+            self.compress(&block);
+            self.buf_len = 0;
+        }
+        while data.len() >= 64 {
+            self.compress(&data[..64]);
+            data = &data[64..];
+        }
+        self.buf[..data.len()].copy_from_slice(data);
+        self.buf_len = data.len();
+    }
+
+    /// Finishes hashing, returning the digest.
+    pub fn finish(mut self) -> [u8; 32] {
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.buf_len != 56 {
+            self.update(&[0]);
+        }
+        let mut len = [0; 8];
+        for (i, b) in len.iter_mut().enumerate() {
+            *b = (bits >> (56 - i * 8)) as u8;
+        }
+        self.update(&len);
+        let mut out = [0; 32];
+        for (i, word) in self.state.iter().enumerate() {
+            for j in 0..4 {
+                out[i * 4 + j] = (word >> (24 - j * 8)) as u8;
+            }
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = (block[i * 4] as u32) << 24 |
+                   (block[i * 4 + 1] as u32) << 16 |
+                   (block[i * 4 + 2] as u32) << 8 |
+                   block[i * 4 + 3] as u32;
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^
+                     (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^
+                     (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0)
+                            .wrapping_add(w[i - 7])
+                            .wrapping_add(s1);
+        }
+        let mut h = self.state;
+        for i in 0..64 {
+            let s1 = h[4].rotate_right(6) ^ h[4].rotate_right(11) ^
+                     h[4].rotate_right(25);
+            let ch = (h[4] & h[5]) ^ (!h[4] & h[6]);
+            let t1 = h[7].wrapping_add(s1)
+                         .wrapping_add(ch)
+                         .wrapping_add(K[i])
+                         .wrapping_add(w[i]);
+            let s0 = h[0].rotate_right(2) ^ h[0].rotate_right(13) ^
+                     h[0].rotate_right(22);
+            let maj = (h[0] & h[1]) ^ (h[0] & h[2]) ^ (h[1] & h[2]);
+            let t2 = s0.wrapping_add(maj);
+            h[7] = h[6];
+            h[6] = h[5];
+            h[5] = h[4];
+            h[4] = h[3].wrapping_add(t1);
+            h[3] = h[2];
+            h[2] = h[1];
+            h[1] = h[0];
+            h[0] = t1.wrapping_add(t2);
+        }
+        for (s, h) in self.state.iter_mut().zip(h.iter()) {
+            *s = s.wrapping_add(*h);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Sha256 {
+        Sha256::new()
+    }
+}
+
+/// An incremental MD5 hasher.
+///
+/// MD5 is broken as a cryptographic hash, but plenty of servers still publish
+/// MD5 checksums of their files, which this can verify.
+///
+/// ```
+/// use curl::sink::{self, Md5};
+///
+/// let mut hasher = Md5::new();
+/// hasher.update(b"abc");
+/// assert_eq!(sink::to_hex(&hasher.finish()),
+///            "900150983cd24fb0d6963f7d28e17f72");
+/// ```
+#[derive(Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    buf: [u8; 64],
+    buf_len: usize,
+    len: u64,
+}
+
+const MD5_S: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+const MD5_K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a,
+    0xa8304613, 0xfd469501, 0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be,
+    0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821, 0xf61e2562, 0xc040b340,
+    0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8,
+    0x676f02d9, 0x8d2a4c8a, 0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+    0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70, 0x289b7ec6, 0xeaa127fa,
+    0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92,
+    0xffeff47d, 0x85845dd1, 0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1,
+    0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+impl Md5 {
+    /// Creates a new hasher.
+    pub fn new() -> Md5 {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buf: [0; 64],
+            buf_len: 0,
+            len: 0,
+        }
+    }
+
+    /// Feeds `data` into the hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        if self.buf_len > 0 {
+            let start = self.buf_len;
+            let n = cmp::min(64 - start, data.len());
+            self.buf[start..start + n].copy_from_slice(&data[..n]);
+            self.buf_len += n;
+            data = &data[n..];
+            if self.buf_len < 64 {
+                return
+            }
+            let block = self.buf;
+            self.compress(&block);
+            self.buf_len = 0;
+        }
+        while data.len() >= 64 {
+            self.compress(&data[..64]);
+            data = &data[64..];
+        }
+        self.buf[..data.len()].copy_from_slice(data);
+        self.buf_len = data.len();
+    }
+
+    /// Finishes hashing, returning the digest.
+    pub fn finish(mut self) -> [u8; 16] {
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.buf_len != 56 {
+            self.update(&[0]);
+        }
+        let mut len = [0; 8];
+        for (i, b) in len.iter_mut().enumerate() {
+            *b = (bits >> (i * 8)) as u8;
+        }
+        self.update(&len);
+        let mut out = [0; 16];
+        for (i, word) in self.state.iter().enumerate() {
+            for j in 0..4 {
+                out[i * 4 + j] = (word >> (j * 8)) as u8;
+            }
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8]) {
+        let mut m = [0u32; 16];
+        for i in 0..16 {
+            m[i] = block[i * 4] as u32 |
+                   (block[i * 4 + 1] as u32) << 8 |
+                   (block[i * 4 + 2] as u32) << 16 |
+                   (block[i * 4 + 3] as u32) << 24;
+        }
+        let mut a = self.state[0];
+        let mut b = self.state[1];
+        let mut c = self.state[2];
+        let mut d = self.state[3];
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a)
+                     .wrapping_add(MD5_K[i])
+                     .wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_S[i]));
+        }
+        for (s, h) in self.state.iter_mut().zip([a, b, c, d].iter()) {
+            *s = s.wrapping_add(*h);
+        }
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Md5 {
+        Md5::new()
+    }
+}
+
+/// A hash algorithm built into this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    /// MD5, producing a 16-byte digest.
+    Md5,
+    /// SHA-256, producing a 32-byte digest.
+    Sha256,
+}
+
+/// An incremental hasher for an `Algorithm` chosen at runtime.
+#[derive(Clone)]
+pub enum Hasher {
+    /// An MD5 hasher.
+    Md5(Md5),
+    /// A SHA-256 hasher.
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    /// Creates a new hasher for `algorithm`.
+    pub fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(Md5::new()),
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Returns the algorithm of this hasher.
+    pub fn algorithm(&self) -> Algorithm {
+        match *self {
+            Hasher::Md5(..) => Algorithm::Md5,
+            Hasher::Sha256(..) => Algorithm::Sha256,
+        }
+    }
+
+    /// Feeds `data` into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        match *self {
+            Hasher::Md5(ref mut h) => h.update(data),
+            Hasher::Sha256(ref mut h) => h.update(data),
+        }
+    }
+
+    /// Finishes hashing, returning the digest.
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Md5(h) => h.finish().to_vec(),
+            Hasher::Sha256(h) => h.finish().to_vec(),
+        }
+    }
+}
+
+/// Formats `bytes` as a lowercase hex string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
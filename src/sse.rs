@@ -0,0 +1,400 @@
+//! A client for Server-Sent Events
+//!
+//! Servers which push updates over HTTP often do so with [Server-Sent
+//! Events][spec]: the response to a `GET` request is a never-ending
+//! `text/event-stream` body made up of events, each consisting of a type,
+//! some data and optionally an id. An `EventStream` performs such a request,
+//! parses the body as it streams in and hands each event to a callback.
+//!
+//! [spec]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+//!
+//! When the connection is closed or fails, the stream reconnects after a
+//! delay, which the server can change with a `retry` field, and sends the id
+//! of the last event it received in a `Last-Event-ID` header so that the
+//! server can pick up where it left off. A server ends the stream for good by
+//! replying with `204 No Content`.
+//!
+//! ```no_run
+//! use curl::easy::Easy;
+//! use curl::sse::EventStream;
+//!
+//! let mut handle = Easy::new();
+//! EventStream::new("https://example.com/updates")
+//!     .run(&mut handle, |event| {
+//!         println!("{}: {}", event.event(), event.data());
+//!         event.event() != "shutdown"
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::str;
+use std::thread;
+use std::time::Duration;
+
+use curl_sys;
+
+use Error;
+use easy::{Easy, List};
+use error::{error_with_extra, error_with_status, error_with_url};
+
+/// A `GET` request whose response is consumed as a stream of events.
+///
+/// See the [module documentation](index.html) for more information.
+#[derive(Clone, Debug)]
+pub struct EventStream {
+    url: String,
+    headers: Vec<(String, String)>,
+    last_event_id: Option<String>,
+    reconnect_delay: Duration,
+    max_reconnects: Option<u32>,
+}
+
+/// A single event received from an `EventStream`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+/// How a single connection of an `EventStream` ended.
+enum Outcome {
+    /// The callback asked to stop.
+    Cancelled,
+    /// The server replied with `204 No Content`.
+    Finished,
+    /// The connection was closed, possibly by an error, and should be made
+    /// again.
+    Closed(Option<Error>),
+}
+
+/// The state of parsing an event stream, which survives reconnecting.
+#[derive(Default)]
+struct Parser {
+    line: Vec<u8>,
+    after_cr: bool,
+    started: bool,
+    event: String,
+    data: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl EventStream {
+    /// Creates a new stream of the events served at `url`.
+    pub fn new(url: &str) -> EventStream {
+        EventStream {
+            url: url.to_string(),
+            headers: Vec::new(),
+            last_event_id: None,
+            reconnect_delay: Duration::from_secs(3),
+            max_reconnects: None,
+        }
+    }
+
+    /// Adds a header to send with each request of the stream.
+    ///
+    /// `Accept: text/event-stream` and `Cache-Control: no-cache` are always
+    /// sent, along with `Last-Event-ID` once an event with an id has been
+    /// received.
+    pub fn header(&mut self, name: &str, value: &str) -> &mut EventStream {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the id of the last event received, sent to the server in a
+    /// `Last-Event-ID` header when connecting.
+    ///
+    /// This resumes a stream which was consumed before, such as by an earlier
+    /// run of the program. `run` keeps track of the id itself, so running the
+    /// same `EventStream` again resumes it as well.
+    pub fn last_event_id(&mut self, id: &str) -> &mut EventStream {
+        self.last_event_id = Some(id.to_string());
+        self
+    }
+
+    /// Sets how long to wait before reconnecting after the connection was
+    /// closed or failed.
+    ///
+    /// A `retry` field sent by the server replaces this delay.
+    ///
+    /// By default this is 3 seconds.
+    pub fn reconnect_delay(&mut self, delay: Duration) -> &mut EventStream {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Limits how many times the stream reconnects before `run` gives up.
+    ///
+    /// Once the limit is reached, `run` returns the error the last connection
+    /// failed with, or `Ok` if the server closed it cleanly.
+    ///
+    /// By default the stream reconnects indefinitely.
+    pub fn max_reconnects(&mut self, max: u32) -> &mut EventStream {
+        self.max_reconnects = Some(max);
+        self
+    }
+
+    /// Connects to the server and invokes `f` with each event received,
+    /// until `f` returns `false` or the stream ends.
+    ///
+    /// The request is performed through `Easy::transfer` with the URL, method
+    /// and header list of `handle` replaced, while its other options, such
+    /// as timeouts, apply as they are. Events are dispatched as soon as the
+    /// blank line ending them arrives, and an event left incomplete when the
+    /// connection closes is dropped.
+    ///
+    /// The stream ends successfully when `f` returns `false` or the server
+    /// replies with `204 No Content`. A response with any other status than
+    /// `200`, or one which isn't a `text/event-stream`, makes this return an
+    /// error right away. Connection errors and closed connections lead to a
+    /// reconnect instead, within the limit set with `max_reconnects`.
+    pub fn run<F>(&mut self, handle: &mut Easy, mut f: F) -> Result<(), Error>
+        where F: FnMut(&Event) -> bool
+    {
+        let mut parser = Parser {
+            last_event_id: self.last_event_id.take(),
+            ..Parser::default()
+        };
+        let mut reconnects = 0;
+        loop {
+            let outcome = self.connect(handle, &mut parser, &mut f);
+            self.last_event_id = parser.last_event_id.clone();
+            if let Some(retry) = parser.retry.take() {
+                self.reconnect_delay = retry;
+            }
+            let last = match try!(outcome) {
+                Outcome::Cancelled | Outcome::Finished => return Ok(()),
+                Outcome::Closed(None) => Ok(()),
+                Outcome::Closed(Some(e)) => Err(e),
+            };
+            if self.max_reconnects.map_or(false, |max| reconnects >= max) {
+                return last
+            }
+            reconnects += 1;
+            thread::sleep(self.reconnect_delay);
+            parser.reset();
+        }
+    }
+
+    fn connect<F>(&self, handle: &mut Easy, parser: &mut Parser, f: &mut F)
+                  -> Result<Outcome, Error>
+        where F: FnMut(&Event) -> bool
+    {
+        try!(self.configure(handle, parser));
+        let code = Cell::new(0);
+        let content_type = RefCell::new(String::new());
+        let cancelled = Cell::new(false);
+        let rejected = Cell::new(false);
+        let result = {
+            let mut transfer = handle.transfer();
+            try!(transfer.header_function(|line| {
+                let line = match str::from_utf8(line) {
+                    Ok(line) => line.trim(),
+                    Err(..) => return true,
+                };
+                if line.starts_with("HTTP/") {
+                    let status = line.split(' ').nth(1);
+                    code.set(status.and_then(|s| s.parse().ok()).unwrap_or(0));
+                    content_type.borrow_mut().clear();
+                } else if let Some(i) = line.find(':') {
+                    if line[..i].eq_ignore_ascii_case("content-type") {
+                        *content_type.borrow_mut() =
+                            line[i + 1..].trim().to_string();
+                    }
+                }
+                true
+            }));
+            try!(transfer.write_function(|data| {
+                if code.get() != 200 {
+                    return Ok(data.len())
+                }
+                if !is_event_stream(&content_type.borrow()) {
+                    rejected.set(true);
+                    return Ok(0)
+                }
+                if parser.feed(data, &mut *f) {
+                    Ok(data.len())
+                } else {
+                    cancelled.set(true);
+                    Ok(0)
+                }
+            }));
+            transfer.perform()
+        };
+        if cancelled.get() {
+            return Ok(Outcome::Cancelled)
+        }
+        let code = code.get();
+        if rejected.get() ||
+           (code == 200 && !is_event_stream(&content_type.borrow())) {
+            let msg = "response is not an event stream";
+            let err = error_with_extra(curl_sys::CURLE_WRITE_ERROR,
+                                       msg.into());
+            return Err(error_with_url(err, &self.url))
+        }
+        if code == 204 {
+            return Ok(Outcome::Finished)
+        }
+        if code != 0 && code != 200 {
+            let msg = format!("event stream responded with status {}", code);
+            let err = error_with_extra(curl_sys::CURLE_HTTP_RETURNED_ERROR,
+                                       msg.into());
+            let err = error_with_url(err, &self.url);
+            return Err(error_with_status(err, code))
+        }
+        Ok(Outcome::Closed(result.err()))
+    }
+
+    fn configure(&self, handle: &mut Easy, parser: &Parser)
+                 -> Result<(), Error> {
+        try!(handle.url(&self.url));
+        // The handle may have been used for an upload or a custom method
+        // before, either of which would make this something other than a
+        // plain GET.
+        try!(handle.upload(false));
+        try!(::clear_option(handle, curl_sys::CURLOPT_CUSTOMREQUEST));
+        try!(handle.get(true));
+        let mut list = List::new();
+        try!(list.append("Accept: text/event-stream"));
+        try!(list.append("Cache-Control: no-cache"));
+        if let Some(ref id) = parser.last_event_id {
+            try!(list.append(&format!("Last-Event-ID: {}", id)));
+        }
+        for &(ref name, ref value) in self.headers.iter() {
+            try!(list.append(&format!("{}: {}", name, value)));
+        }
+        handle.http_headers(list)
+    }
+}
+
+impl Event {
+    /// Returns the type of the event, which is `"message"` unless the server
+    /// gave it an `event` field.
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    /// Returns the data of the event, with the lines of multiple `data`
+    /// fields joined by newlines.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Returns the id of the last event which had one, which is sent back to
+    /// the server when reconnecting.
+    ///
+    /// As in browsers, an event without an `id` field has the id of the one
+    /// before it.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(|s| &s[..])
+    }
+}
+
+impl Parser {
+    /// Parses another chunk of the body, invoking `f` with each complete
+    /// event and returning `false` as soon as it does.
+    fn feed<F>(&mut self, data: &[u8], f: &mut F) -> bool
+        where F: FnMut(&Event) -> bool
+    {
+        for &b in data {
+            let after_cr = mem::replace(&mut self.after_cr, false);
+            match b {
+                b'\n' if after_cr => {}
+                b'\n' | b'\r' => {
+                    self.after_cr = b == b'\r';
+                    let line = mem::replace(&mut self.line, Vec::new());
+                    let event = self.process(&line);
+                    self.line = line;
+                    self.line.clear();
+                    if let Some(event) = event {
+                        if !f(&event) {
+                            return false
+                        }
+                    }
+                }
+                _ => self.line.push(b),
+            }
+        }
+        true
+    }
+
+    fn process(&mut self, line: &[u8]) -> Option<Event> {
+        let mut line = line;
+        if !self.started {
+            self.started = true;
+            if line.starts_with(b"\xef\xbb\xbf") {
+                line = &line[3..];
+            }
+        }
+        if line.is_empty() {
+            return self.dispatch()
+        }
+        let line = String::from_utf8_lossy(line);
+        let (field, value) = match line.find(':') {
+            Some(0) => return None,
+            Some(i) => {
+                let value = &line[i + 1..];
+                let value = if value.starts_with(' ') {
+                    &value[1..]
+                } else {
+                    value
+                };
+                (&line[..i], value)
+            }
+            None => (&line[..], ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => {
+                self.last_event_id = if value.is_empty() {
+                    None
+                } else {
+                    Some(value.to_string())
+                };
+            }
+            "retry" if value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = mem::replace(&mut self.event, String::new());
+        let mut data = mem::replace(&mut self.data, String::new());
+        if data.is_empty() {
+            return None
+        }
+        data.pop();
+        Some(Event {
+            event: if event.is_empty() {"message".to_string()} else {event},
+            data: data,
+            id: self.last_event_id.clone(),
+        })
+    }
+
+    /// Drops anything of an incomplete event, as done when the connection
+    /// closes.
+    fn reset(&mut self) {
+        self.line.clear();
+        self.after_cr = false;
+        self.started = false;
+        self.event.clear();
+        self.data.clear();
+    }
+}
+
+fn is_event_stream(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("");
+    mime.trim().eq_ignore_ascii_case("text/event-stream")
+}
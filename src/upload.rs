@@ -0,0 +1,185 @@
+//! Uploading a file which continues where an earlier attempt stopped
+//!
+//! Restarting a large upload from the beginning each time a flaky link drops
+//! the connection may mean it never completes. A `ResumableUpload` first asks
+//! the server how much of the file it already has, with a `SIZE` command for
+//! FTP and a `HEAD` request for HTTP, and then sends only the rest: appended
+//! with `APPE` over FTP, or as a `PUT` with a `Content-Range` header over
+//! HTTP, which the server has to support.
+//!
+//! The file on the server is assumed to be a prefix of the local one, so the
+//! same source has to be uploaded to the same URL on every attempt.
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use curl::easy::Easy;
+//! use curl::upload::ResumableUpload;
+//!
+//! let file = File::open("backup.tar").unwrap();
+//! let mut handle = Easy::new();
+//! let summary = ResumableUpload::new("ftp://example.com/backup.tar")
+//!     .upload(&mut handle, file)
+//!     .unwrap();
+//! println!("sent {} of {} bytes", summary.len() - summary.resumed(),
+//!          summary.len());
+//! ```
+
+use std::cell::RefCell;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use curl_sys;
+
+use Error;
+use easy::{Easy, ReadError, SeekResult};
+use error::{error_with_extra, error_with_status, error_with_url};
+
+/// An upload to a single URL which resumes a previous incomplete attempt.
+///
+/// See the [module documentation](index.html) for more information.
+#[derive(Clone, Debug)]
+pub struct ResumableUpload {
+    url: String,
+}
+
+/// The outcome of a completed `ResumableUpload`.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    len: u64,
+    resumed: u64,
+}
+
+impl ResumableUpload {
+    /// Creates a new upload to `url`.
+    pub fn new(url: &str) -> ResumableUpload {
+        ResumableUpload { url: url.to_string() }
+    }
+
+    /// Uploads the contents of `source`, skipping as much of its beginning
+    /// as the server already has.
+    ///
+    /// The requests are performed through `Easy::transfer` with the URL,
+    /// `nobody`, `upload`, `resume_from` and `in_filesize` options of
+    /// `handle` replaced, while its other options, such as credentials and
+    /// timeouts, apply as they are. All but the URL are put back to their
+    /// defaults afterwards.
+    ///
+    /// If the file on the server is larger than `source`, nothing is sent and
+    /// an error is returned, as the two can't be the same file. If reading
+    /// `source` fails, the transfer is aborted and the error returned.
+    pub fn upload<R>(&self, handle: &mut Easy, mut source: R)
+                     -> io::Result<Summary>
+        where R: Read + Seek
+    {
+        let len = try!(source.seek(SeekFrom::End(0)));
+        let resumed = try!(self.remote_size(handle));
+        if resumed > len {
+            let msg = format!("remote file is larger than the {} bytes \
+                               being uploaded", len);
+            let err = error_with_extra(curl_sys::CURLE_BAD_DOWNLOAD_RESUME,
+                                       msg.into());
+            return Err(error_with_url(err, &self.url).into())
+        }
+        if resumed == len {
+            return Ok(Summary { len: len, resumed: resumed })
+        }
+        try!(source.seek(SeekFrom::Start(0)));
+
+        try!(handle.nobody(false));
+        try!(handle.upload(true));
+        try!(handle.resume_from(resumed));
+        try!(handle.in_filesize(len));
+        let error = RefCell::new(None);
+        let result = {
+            // libcurl skips what the server already has through the seek
+            // callback.
+            let source = RefCell::new(&mut source);
+            let mut transfer = handle.transfer();
+            try!(transfer.read_function(|buf| {
+                source.borrow_mut().read(buf).map_err(|e| {
+                    *error.borrow_mut() = Some(e);
+                    ReadError::Abort
+                })
+            }));
+            try!(transfer.seek_function(|to| {
+                match source.borrow_mut().seek(to) {
+                    Ok(_) => SeekResult::Ok,
+                    Err(_) => SeekResult::Fail,
+                }
+            }));
+            transfer.perform()
+        };
+        let reset = self.reset(handle);
+        if let Some(e) = error.into_inner() {
+            return Err(e)
+        }
+        try!(result);
+        try!(reset);
+        if self.is_http() {
+            let code = try!(handle.response_code());
+            if code < 200 || code >= 300 {
+                return Err(self.http_error(code))
+            }
+        }
+        Ok(Summary { len: len, resumed: resumed })
+    }
+
+    /// Returns how many bytes of the file the server has, which is none if
+    /// it doesn't exist yet.
+    fn remote_size(&self, handle: &mut Easy) -> io::Result<u64> {
+        try!(handle.url(&self.url));
+        try!(handle.upload(false));
+        try!(handle.resume_from(0));
+        try!(handle.nobody(true));
+        let result = {
+            let mut transfer = handle.transfer();
+            try!(transfer.write_function(|data| Ok(data.len())));
+            transfer.perform()
+        };
+        try!(handle.nobody(false));
+        match result {
+            Ok(()) => {}
+            Err(ref e) if e.is_remote_file_not_found() => return Ok(0),
+            Err(e) => return Err(e.into()),
+        }
+        if self.is_http() {
+            match try!(handle.response_code()) {
+                404 | 410 => return Ok(0),
+                200..=299 => {}
+                code => return Err(self.http_error(code)),
+            }
+        }
+        Ok(try!(handle.content_length_download()).unwrap_or(0))
+    }
+
+    /// Stops `handle` from uploading, and from resuming where this upload
+    /// did, once it's done.
+    fn reset(&self, handle: &mut Easy) -> Result<(), Error> {
+        try!(handle.upload(false));
+        try!(handle.resume_from(0));
+        handle.raw_option(curl_sys::CURLOPT_INFILESIZE_LARGE, -1)
+    }
+
+    fn is_http(&self) -> bool {
+        let scheme = self.url.split(':').next().unwrap_or("");
+        scheme.eq_ignore_ascii_case("http") ||
+            scheme.eq_ignore_ascii_case("https")
+    }
+
+    fn http_error(&self, code: u32) -> io::Error {
+        let err = Error::new(curl_sys::CURLE_HTTP_RETURNED_ERROR);
+        error_with_url(error_with_status(err, code), &self.url).into()
+    }
+}
+
+impl Summary {
+    /// Returns the size of the uploaded file.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns how many bytes the server already had from a previous attempt
+    /// and weren't sent again.
+    pub fn resumed(&self) -> u64 {
+        self.resumed
+    }
+}
@@ -135,10 +135,66 @@ impl Version {
         self.flag(curl_sys::CURL_VERSION_NTLM_WB)
     }
 
-    // /// Returns whether libcurl was built with support for HTTP2.
-    // pub fn feature_http2(&self) -> bool {
-    //     self.flag(curl_sys::CURL_VERSION_HTTP2)
-    // }
+    /// Returns whether libcurl was built with support for HTTP2.
+    pub fn feature_http2(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_HTTP2)
+    }
+
+    /// Returns whether libcurl was built with support for GSS-API.
+    pub fn feature_gssapi(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_GSSAPI)
+    }
+
+    /// Returns whether libcurl was built with support for Kerberos V5
+    /// authentication.
+    pub fn feature_kerberos5(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_KERBEROS5)
+    }
+
+    /// Returns whether libcurl was built with support for Unix domain sockets.
+    pub fn feature_unix_sockets(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_UNIX_SOCKETS)
+    }
+
+    /// Returns whether libcurl was built with support for the Public Suffix
+    /// List, used to reject cookies for top-level domains.
+    pub fn feature_psl(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_PSL)
+    }
+
+    /// Returns whether libcurl was built with support for HTTPS proxies.
+    pub fn feature_https_proxy(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_HTTPS_PROXY)
+    }
+
+    /// Returns whether libcurl was built with multiple SSL backends which can
+    /// be selected at runtime.
+    pub fn feature_multi_ssl(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_MULTI_SSL)
+    }
+
+    /// Returns whether libcurl was built with support for brotli compressed
+    /// content encoding.
+    pub fn feature_brotli(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_BROTLI)
+    }
+
+    /// Returns whether libcurl was built with support for the `Alt-Svc`
+    /// header.
+    pub fn feature_altsvc(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_ALTSVC)
+    }
+
+    /// Returns whether libcurl was built with support for HTTP3.
+    pub fn feature_http3(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_HTTP3)
+    }
+
+    /// Returns whether libcurl was built with support for zstd compressed
+    /// content encoding.
+    pub fn feature_zstd(&self) -> bool {
+        self.flag(curl_sys::CURL_VERSION_ZSTD)
+    }
 
     fn flag(&self, flag: c_int) -> bool {
         unsafe {
@@ -170,6 +226,14 @@ impl Version {
         }
     }
 
+    /// Returns whether this build of libcurl supports `protocol`, such as
+    /// `"https"` or `"ftp"`.
+    ///
+    /// Protocol names are compared case-insensitively.
+    pub fn supports_protocol(&self, protocol: &str) -> bool {
+        self.protocols().any(|p| p.eq_ignore_ascii_case(protocol))
+    }
+
     /// If available, the human readable version of ares that libcurl is linked
     /// against.
     pub fn ares_version(&self) -> Option<&str> {
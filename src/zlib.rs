@@ -0,0 +1,118 @@
+//! Compression of request bodies through zlib, which libcurl links to
+//! already for decoding responses.
+
+use std::cmp;
+use std::mem;
+
+use libc::{self, c_int, c_uint, c_void, size_t};
+use libz_sys as z;
+
+/// A deflate stream producing either the gzip or the zlib format.
+pub struct Compressor {
+    // zlib keeps a pointer back to the stream, so it mustn't move.
+    stream: Box<z::z_stream>,
+}
+
+impl Compressor {
+    /// Creates a compressor producing gzip if `gzip` is set, and the zlib
+    /// format which HTTP calls `deflate` otherwise.
+    pub fn new(gzip: bool) -> Compressor {
+        let mut stream = Box::new(z::z_stream {
+            next_in: 0 as *mut _,
+            avail_in: 0,
+            total_in: 0,
+            next_out: 0 as *mut _,
+            avail_out: 0,
+            total_out: 0,
+            msg: 0 as *mut _,
+            state: 0 as *mut _,
+            zalloc: zalloc,
+            zfree: zfree,
+            opaque: 0 as *mut _,
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        });
+        // Adding 16 to the window bits selects a gzip wrapper.
+        let window_bits = if gzip { 15 + 16 } else { 15 };
+        unsafe {
+            let rc = z::deflateInit2_(&mut *stream,
+                                      z::Z_DEFAULT_COMPRESSION,
+                                      z::Z_DEFLATED,
+                                      window_bits,
+                                      8,
+                                      z::Z_DEFAULT_STRATEGY,
+                                      z::zlibVersion(),
+                                      mem::size_of::<z::z_stream>() as c_int);
+            assert_eq!(rc, z::Z_OK, "failed to initialize zlib");
+        }
+        Compressor { stream: stream }
+    }
+
+    /// Compresses as much of `input` into `output` as fits, returning how
+    /// many bytes were consumed and produced and whether the stream is
+    /// complete.
+    ///
+    /// Once all input has been passed in, `finish` is set until this reports
+    /// the stream as complete.
+    pub fn compress(&mut self, input: &[u8], output: &mut [u8], finish: bool)
+                    -> (usize, usize, bool) {
+        // Lengths beyond `c_uint` are split over several calls.
+        let avail_in = cmp::min(input.len(), c_uint::max_value() as usize);
+        let avail_out = cmp::min(output.len(), c_uint::max_value() as usize);
+        let finish = finish && avail_in == input.len();
+        self.stream.next_in = input.as_ptr() as *mut _;
+        self.stream.avail_in = avail_in as c_uint;
+        self.stream.next_out = output.as_mut_ptr();
+        self.stream.avail_out = avail_out as c_uint;
+        let flush = if finish { z::Z_FINISH } else { z::Z_NO_FLUSH };
+        let rc = unsafe { z::deflate(&mut *self.stream, flush) };
+        // A buffer error only means no progress was possible.
+        assert!(rc == z::Z_OK || rc == z::Z_STREAM_END || rc == z::Z_BUF_ERROR,
+                "zlib failed to compress: {}", rc);
+        let consumed = avail_in - self.stream.avail_in as usize;
+        let produced = avail_out - self.stream.avail_out as usize;
+        (consumed, produced, rc == z::Z_STREAM_END)
+    }
+
+    /// Starts a new stream, discarding anything of the current one.
+    pub fn reset(&mut self) {
+        unsafe {
+            z::deflateReset(&mut *self.stream);
+        }
+    }
+}
+
+impl Drop for Compressor {
+    fn drop(&mut self) {
+        unsafe {
+            z::deflateEnd(&mut *self.stream);
+        }
+    }
+}
+
+/// Compresses all of `data` at once.
+pub fn compress(data: &[u8], gzip: bool) -> Vec<u8> {
+    let mut compressor = Compressor::new(gzip);
+    let mut out = Vec::with_capacity(data.len() / 2 + 64);
+    let mut buf = [0; 16 * 1024];
+    let mut data = data;
+    loop {
+        let (consumed, produced, done) =
+            compressor.compress(data, &mut buf, true);
+        data = &data[consumed..];
+        out.extend_from_slice(&buf[..produced]);
+        if done {
+            return out
+        }
+    }
+}
+
+unsafe extern "C" fn zalloc(_: *mut c_void, items: c_uint, size: c_uint)
+                            -> *mut c_void {
+    libc::calloc(items as size_t, size as size_t)
+}
+
+unsafe extern "C" fn zfree(_: *mut c_void, ptr: *mut c_void) {
+    libc::free(ptr)
+}
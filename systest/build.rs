@@ -39,19 +39,117 @@ fn main() {
         }
     });
     // cfg.fn_cname(|s, l| l.unwrap_or(s).to_string());
-    cfg.skip_type(|n| n == "__enum_ty");
+    cfg.skip_type(|n| {
+        // `curl_trailer_callback` was introduced in 7.64.0, the HSTS types in
+        // 7.74.0 and `curl_prereq_callback` in 7.80.0
+        n == "__enum_ty" || n == "curl_trailer_callback" ||
+            n == "CURLSTScode" || n == "curl_hstsread_callback" ||
+            n == "curl_hstswrite_callback" || n == "curl_prereq_callback"
+    });
+    cfg.skip_struct(|s| {
+        // introduced in 7.74.0
+        s == "curl_hstsentry" || s == "curl_index"
+    });
     cfg.skip_signededness(|s| {
         s.ends_with("callback") || s.ends_with("function")
     });
 
-    // Ubuntu Xenial 16.04 ships with version 7.47.0 of curl, so explicitly
-    // skip constants introduced after that.
-    // CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE was introduced in 7.49.0
-    cfg.skip_const(|s| s == "CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE");
+    let http2 = cfg!(feature = "http2");
+    cfg.skip_const(move |s| {
+        // Disable HTTP/2 checking if feature not enabled
+        if !http2 && s.starts_with("CURL_HTTP_VERSION_2") {
+            return true
+        }
+
+        // Ubuntu Xenial 16.04 ships with version 7.47.0 of curl, so explicitly
+        // skip constants introduced after that.
+        match s {
+            // introduced in 7.49.0
+            "CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE" |
+            "CURLOPT_TCP_FASTOPEN" => true,
+
+            // introduced in 7.51.0
+            "CURLOPT_KEEP_SENDING_ON_ERROR" => true,
+
+            // introduced in 7.52.0
+            "CURL_VERSION_HTTPS_PROXY" |
+            "CURLINFO_SCHEME" => true,
+
+            // introduced in 7.56.0
+            "CURL_VERSION_MULTI_SSL" => true,
+
+            // introduced in 7.57.0
+            "CURL_VERSION_BROTLI" => true,
+
+            // introduced in 7.59.0
+            "CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS" => true,
+
+            // introduced in 7.61.0
+            "CURLAUTH_BEARER" => true,
+
+            // introduced in 7.62.0
+            "CURLOPT_DOH_URL" |
+            "CURLOPT_UPLOAD_BUFFERSIZE" |
+            "CURLOPT_UPKEEP_INTERVAL_MS" => true,
 
-    // Disable HTTP/2 checking if feature not enabled
-    #[cfg(not(feature = "http2"))]
-    cfg.skip_const(|s| s.starts_with("CURL_HTTP_VERSION_2"));
+            // introduced in 7.64.0
+            "CURLOPT_TRAILERFUNCTION" |
+            "CURLOPT_TRAILERDATA" |
+            "CURL_TRAILERFUNC_OK" |
+            "CURL_TRAILERFUNC_ABORT" |
+            "CURLOPT_HTTP09_ALLOWED" => true,
+
+            // introduced in 7.64.1
+            "CURL_VERSION_ALTSVC" |
+            "CURLOPT_ALTSVC_CTRL" |
+            "CURLOPT_ALTSVC" |
+            "CURLALTSVC_READONLYFILE" |
+            "CURLALTSVC_H1" |
+            "CURLALTSVC_H2" |
+            "CURLALTSVC_H3" => true,
+
+            // introduced in 7.65.0
+            "CURLOPT_MAXAGE_CONN" => true,
+
+            // introduced in 7.66.0
+            "CURL_VERSION_HTTP3" => true,
+            "CURLOPT_SASL_AUTHZID" => true,
+
+            // introduced in 7.67.0
+            "CURLMOPT_MAX_CONCURRENT_STREAMS" => true,
+
+            // introduced in 7.72.0
+            "CURLINFO_EFFECTIVE_METHOD" |
+            "CURL_VERSION_ZSTD" => true,
+
+            // introduced in 7.74.0
+            "CURLHSTS_ENABLE" |
+            "CURLHSTS_READONLYFILE" |
+            "CURLSTS_OK" |
+            "CURLSTS_DONE" |
+            "CURLSTS_FAIL" |
+            "CURLOPT_HSTS_CTRL" |
+            "CURLOPT_HSTS" |
+            "CURLOPT_HSTSREADFUNCTION" |
+            "CURLOPT_HSTSREADDATA" |
+            "CURLOPT_HSTSWRITEFUNCTION" |
+            "CURLOPT_HSTSWRITEDATA" => true,
+
+            // introduced in 7.76.0
+            "CURLOPT_DOH_SSL_VERIFYPEER" |
+            "CURLOPT_DOH_SSL_VERIFYHOST" |
+            "CURLOPT_DOH_SSL_VERIFYSTATUS" => true,
+
+            // introduced in 7.80.0
+            "CURLOPT_PREREQFUNCTION" |
+            "CURLOPT_PREREQDATA" |
+            "CURL_PREREQFUNC_OK" |
+            "CURL_PREREQFUNC_ABORT" |
+            "CURLOPT_MAXLIFETIME_CONN" => true,
+
+            _ => false,
+        }
+    });
 
     cfg.generate("../curl-sys/lib.rs", "all.rs");
 }
@@ -0,0 +1,203 @@
+extern crate curl;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use curl::download::SegmentedDownload;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+/// A server for a single file which accepts any number of connections and
+/// records the `Range` headers it was sent.
+struct FileServer {
+    url: String,
+    ranges: Arc<Mutex<Vec<String>>>,
+}
+
+impl FileServer {
+    fn new(body: Vec<u8>, ranges_supported: bool) -> FileServer {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let url = format!("http://{}/file", t!(listener.local_addr()));
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+        let body = Arc::new(body);
+        thread::spawn(move || {
+            for socket in listener.incoming() {
+                let body = body.clone();
+                let seen = seen.clone();
+                thread::spawn(move || {
+                    serve(t!(socket), &body, ranges_supported, &seen)
+                });
+            }
+        });
+        FileServer { url: url, ranges: ranges }
+    }
+}
+
+fn serve(socket: TcpStream,
+         body: &[u8],
+         ranges_supported: bool,
+         seen: &Mutex<Vec<String>>) {
+    let mut reader = BufReader::new(t!(socket.try_clone()));
+    let mut request = String::new();
+    t!(reader.read_line(&mut request));
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        t!(reader.read_line(&mut line));
+        if line == "\r\n" || line.is_empty() {
+            break
+        }
+        if line.starts_with("Range: bytes=") {
+            range = Some(line[13..].trim().to_string());
+        }
+    }
+    let mut socket = socket;
+    if request.starts_with("HEAD") {
+        let accept = if ranges_supported {"bytes"} else {"none"};
+        t!(write!(socket, "HTTP/1.1 200 OK\r\n\
+                           Content-Length: {}\r\n\
+                           Accept-Ranges: {}\r\n\
+                           ETag: \"v1\"\r\n\
+                           Last-Modified: {}\r\n\
+                           Connection: close\r\n\r\n",
+                  body.len(), accept, MODIFIED));
+        return
+    }
+    match range {
+        Some(ref range) if ranges_supported => {
+            seen.lock().unwrap().push(range.clone());
+            let mut parts = range.split('-');
+            let start: usize = t!(parts.next().unwrap().parse());
+            let end: usize = t!(parts.next().unwrap().parse());
+            let part = &body[start..end + 1];
+            t!(write!(socket, "HTTP/1.1 206 Partial Content\r\n\
+                               Content-Length: {}\r\n\
+                               Content-Range: bytes {}-{}/{}\r\n\
+                               Connection: close\r\n\r\n",
+                      part.len(), start, end, body.len()));
+            t!(socket.write_all(part));
+        }
+        _ => {
+            t!(write!(socket, "HTTP/1.1 200 OK\r\n\
+                               Content-Length: {}\r\n\
+                               Last-Modified: {}\r\n\
+                               Connection: close\r\n\r\n",
+                      body.len(), MODIFIED));
+            t!(socket.write_all(body));
+        }
+    }
+}
+
+const MODIFIED: &'static str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+fn body() -> Vec<u8> {
+    (0..10000u32).map(|i| (i % 251) as u8).collect()
+}
+
+fn destination(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("curl-rust-{}-{}", name, process::id()))
+}
+
+fn sidecar(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(".segments");
+    path.with_file_name(name)
+}
+
+fn read(path: &Path) -> Vec<u8> {
+    let mut contents = Vec::new();
+    t!(t!(File::open(path)).read_to_end(&mut contents));
+    contents
+}
+
+#[test]
+fn segmented() {
+    let server = FileServer::new(body(), true);
+    let path = destination("segmented");
+    let summary = t!(SegmentedDownload::new(&server.url)
+                         .segments(3)
+                         .download(&path));
+    assert_eq!(summary.len(), 10000);
+    assert_eq!(summary.segments(), 3);
+    assert_eq!(summary.resumed(), 0);
+    assert_eq!(read(&path), body());
+    let mut ranges = server.ranges.lock().unwrap().clone();
+    ranges.sort();
+    assert_eq!(ranges, ["0-3333", "3334-6667", "6668-9999"]);
+    assert!(!sidecar(&path).exists());
+    t!(fs::remove_file(&path));
+}
+
+#[test]
+fn resumed() {
+    let server = FileServer::new(body(), true);
+    let path = destination("resumed");
+    let state_path = sidecar(&path);
+    let mut partial = body();
+    for b in partial[100..3334].iter_mut() {
+        *b = 0;
+    }
+    for b in partial[5000..].iter_mut() {
+        *b = 0;
+    }
+    t!(t!(File::create(&path)).write_all(&partial));
+    t!(t!(File::create(&state_path)).write_all(b"10000 \"v1\"\n\
+                                                 0 3333 100\n\
+                                                 3334 6667 1666\n\
+                                                 6668 9999 0\n"));
+
+    let summary = t!(SegmentedDownload::new(&server.url).download(&path));
+    assert_eq!(summary.segments(), 3);
+    assert_eq!(summary.resumed(), 1766);
+    assert_eq!(read(&path), body());
+    let mut ranges = server.ranges.lock().unwrap().clone();
+    ranges.sort();
+    assert_eq!(ranges, ["100-3333", "5000-6667", "6668-9999"]);
+    assert!(!state_path.exists());
+    t!(fs::remove_file(&path));
+}
+
+#[test]
+fn without_ranges() {
+    let server = FileServer::new(body(), false);
+    let path = destination("without-ranges");
+    let summary = t!(SegmentedDownload::new(&server.url).download(&path));
+    assert_eq!(summary.segments(), 1);
+    assert_eq!(read(&path), body());
+    assert!(server.ranges.lock().unwrap().is_empty());
+    t!(fs::remove_file(&path));
+}
+
+#[test]
+#[cfg(unix)]
+fn preserve_modified() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let modified = UNIX_EPOCH + Duration::from_secs(784111777);
+    for &ranges in [true, false].iter() {
+        let server = FileServer::new(body(), ranges);
+        let path = destination(&format!("preserve-modified-{}", ranges));
+        t!(SegmentedDownload::new(&server.url)
+               .preserve_modified(true)
+               .download(&path));
+        assert_eq!(read(&path), body());
+        assert_eq!(t!(t!(fs::metadata(&path)).modified()), modified);
+        t!(fs::remove_file(&path));
+
+        t!(SegmentedDownload::new(&server.url).download(&path));
+        assert!(t!(t!(fs::metadata(&path)).modified()) != modified);
+        t!(fs::remove_file(&path));
+    }
+}
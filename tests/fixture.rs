@@ -0,0 +1,149 @@
+extern crate curl;
+
+use std::io::BufReader;
+use std::time::Duration;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+use curl::easy::Easy;
+use curl::fixture::{Cassette, Recorder, Replayer};
+use curl::http::{Request, Response, Transport};
+
+use server::Server;
+mod server;
+
+fn handle() -> Easy {
+    let mut e = Easy::new();
+    t!(e.timeout(Duration::new(20, 0)));
+    return e
+}
+
+#[test]
+fn record_and_replay() {
+    let s = Server::new();
+    s.receive("\
+POST /upload HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+User-Agent: curl-rust/[..]\r\n\
+Accept: */*\r\n\
+X-Token: secret\r\n\
+Content-Length: 4\r\n\
+Content-Type: application/x-www-form-urlencoded\r\n\
+\r\n\
+a\nb\0");
+    s.send("HTTP/1.1 201 Created\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Length: 3\r\n\
+            \r\n\
+            \n\0\n");
+
+    let url = s.url("/upload");
+    let mut request = Request::post(&url);
+    request.header("X-Token", "secret")
+           .unset_header("Expect")
+           .body(&b"a\nb\0"[..]);
+
+    let mut recorder = Recorder::new(handle());
+    let recorded = t!(recorder.execute(&request));
+    assert_eq!(recorded.body(), b"\n\0\n");
+    assert_eq!(recorder.cassette().exchanges().len(), 1);
+
+    let mut saved = Vec::new();
+    t!(recorder.cassette().save(&mut saved));
+    let cassette = t!(Cassette::load(&saved[..]));
+    let exchange = &cassette.exchanges()[0];
+    assert_eq!(exchange.method(), "POST");
+    assert_eq!(exchange.url(), url);
+    assert_eq!(exchange.header("x-token"), Some("secret"));
+    assert_eq!(exchange.header("expect"), None);
+    assert_eq!(exchange.body(), Some(&b"a\nb\0"[..]));
+
+    let mut resaved = Vec::new();
+    t!(cassette.save(&mut resaved));
+    assert_eq!(saved, resaved);
+
+    let mut replayer = Replayer::new(cassette);
+    replayer.match_header("X-Token").match_body(true);
+    let replayed = t!(replayer.execute(&request));
+    assert_eq!(replayed.code(), 201);
+    assert_eq!(replayed.status_line(), "HTTP/1.1 201 Created");
+    assert_eq!(replayed.header("content-type"),
+               Some("application/octet-stream"));
+    assert_eq!(replayed.body(), recorded.body());
+    assert_eq!(replayer.remaining(), 0);
+
+    let err = replayer.execute(&request).unwrap_err();
+    assert_eq!(err.extra_description(),
+               Some(&format!("no recorded exchange matches POST {}", url)[..]));
+}
+
+#[test]
+fn matching_rules() {
+    let mut cassette = Cassette::new();
+    let mut a = Request::get("http://example.test/");
+    a.header("Accept-Language", "en");
+    let mut b = Request::get("http://example.test/");
+    b.header("Accept-Language", "de");
+    cassette.push(&a, &Response::from_parts(200, &[], b"hello".to_vec()));
+    cassette.push(&b, &Response::from_parts(200, &[], b"hallo".to_vec()));
+    cassette.push(&a, &Response::from_parts(304, &[], Vec::new()));
+
+    // Without a header rule the responses are played back in order.
+    let mut replayer = Replayer::new(cassette.clone());
+    assert_eq!(t!(replayer.execute(&b)).body(), b"hello");
+    assert_eq!(t!(replayer.execute(&b)).body(), b"hallo");
+    assert_eq!(t!(replayer.execute(&b)).code(), 304);
+
+    let mut replayer = Replayer::new(cassette);
+    replayer.match_header("accept-language");
+    assert_eq!(t!(replayer.execute(&b)).body(), b"hallo");
+    assert_eq!(t!(replayer.execute(&a)).body(), b"hello");
+    assert_eq!(t!(replayer.execute(&a)).code(), 304);
+    assert!(replayer.execute(&b).is_err());
+    assert!(replayer.execute(&Request::head("http://example.test/")).is_err());
+}
+
+#[test]
+fn malformed_cassettes() {
+    assert!(Cassette::load(&b"response 200\n"[..]).is_err());
+    assert!(Cassette::load(&b"request GET http://x/\n"[..]).is_err());
+    assert!(Cassette::load(&b"request GET http://x/\n\
+                              response 200\n\
+                              body 10\n\
+                              short\n"[..]).is_err());
+    assert!(Cassette::load(&b"request GET http://x/\n\
+                              bogus\n\
+                              response 200\n"[..]).is_err());
+    let cassette = t!(Cassette::load(&b"request GET http://x/\n\
+                                        response 204\n"[..]));
+    assert_eq!(cassette.exchanges()[0].response().code(), 204);
+}
+
+#[test]
+fn load_with_small_buffers() {
+    let mut cassette = Cassette::new();
+    let mut request = Request::get("http://example.test/a");
+    request.header("Accept", "text/plain");
+    cassette.push(&request, &Response::from_parts(200, &[], b"a".to_vec()));
+    cassette.push(&Request::get("http://example.test/b"),
+                  &Response::from_parts(404, &[], Vec::new()));
+    let mut saved = Vec::new();
+    t!(cassette.save(&mut saved));
+
+    // Keywords straddling a refill of the buffer mustn't confuse the parser.
+    for capacity in 1..16 {
+        let reader = BufReader::with_capacity(capacity, &saved[..]);
+        let loaded = t!(Cassette::load(reader));
+        let exchanges = loaded.exchanges();
+        assert_eq!(exchanges.len(), 2);
+        assert_eq!(exchanges[0].header("accept"), Some("text/plain"));
+        assert_eq!(exchanges[0].response().body(), b"a");
+        assert_eq!(exchanges[1].url(), "http://example.test/b");
+        assert_eq!(exchanges[1].response().code(), 404);
+    }
+}
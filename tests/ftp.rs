@@ -0,0 +1,447 @@
+extern crate curl;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use curl::easy::{Easy, FtpCreateDirs, List};
+use curl::upload::ResumableUpload;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+/// A minimal FTP server keeping its files in memory, which accepts any
+/// number of sessions and records the commands it was sent.
+struct FtpServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    commands: Vec<String>,
+    files: HashMap<String, Vec<u8>>,
+    dirs: HashSet<String>,
+}
+
+/// Where the data connection of the next transfer comes from.
+enum Data {
+    Passive(TcpListener),
+    Active(SocketAddr),
+}
+
+impl FtpServer {
+    fn new() -> FtpServer {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let addr = t!(listener.local_addr());
+        let mut state = State::default();
+        state.dirs.insert("/".to_string());
+        let state = Arc::new(Mutex::new(state));
+        let state2 = state.clone();
+        thread::spawn(move || {
+            for socket in listener.incoming() {
+                let state = state2.clone();
+                thread::spawn(move || session(t!(socket), &state));
+            }
+        });
+        FtpServer { addr: addr, state: state }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("ftp://{}{}", self.addr, path)
+    }
+
+    fn put(&self, path: &str, contents: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        for (i, _) in path.match_indices('/').skip(1) {
+            state.dirs.insert(path[..i].to_string());
+        }
+        state.files.insert(path.to_string(), contents.to_vec());
+    }
+
+    fn file(&self, path: &str) -> Option<Vec<u8>> {
+        self.state.lock().unwrap().files.get(path).cloned()
+    }
+
+    /// Returns the commands received so far, without their arguments.
+    fn commands(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.commands.iter()
+             .map(|c| c.split(' ').next().unwrap().to_string())
+             .collect()
+    }
+
+    /// Returns the commands received so far which start with `prefix`.
+    fn sent(&self, prefix: &str) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.commands.iter()
+             .filter(|c| c.starts_with(prefix))
+             .cloned()
+             .collect()
+    }
+}
+
+fn session(socket: TcpStream, state: &Mutex<State>) {
+    let mut reader = BufReader::new(t!(socket.try_clone()));
+    let mut socket = socket;
+    let mut cwd = "/".to_string();
+    let mut data = None;
+    let mut rename_from = None;
+    t!(socket.write_all(b"220 ready\r\n"));
+    loop {
+        let mut line = String::new();
+        if t!(reader.read_line(&mut line)) == 0 {
+            return
+        }
+        let line = line.trim().to_string();
+        state.lock().unwrap().commands.push(line.clone());
+        let (command, arg) = match line.find(' ') {
+            Some(i) => (&line[..i], &line[i + 1..]),
+            None => (&line[..], ""),
+        };
+        let path = if arg.starts_with('/') {
+            arg.to_string()
+        } else if cwd == "/" {
+            format!("/{}", arg)
+        } else {
+            format!("{}/{}", cwd, arg)
+        };
+        let reply = match command {
+            "USER" => "331 password please".to_string(),
+            "PASS" => "230 logged in".to_string(),
+            "PWD" => format!("257 \"{}\"", cwd),
+            "TYPE" | "NOOP" => "200 ok".to_string(),
+            "CWD" => {
+                if state.lock().unwrap().dirs.contains(&path) {
+                    cwd = path;
+                    "250 ok".to_string()
+                } else {
+                    "550 no such directory".to_string()
+                }
+            }
+            "MKD" => {
+                if state.lock().unwrap().dirs.insert(path.clone()) {
+                    format!("257 \"{}\" created", path)
+                } else {
+                    "550 exists".to_string()
+                }
+            }
+            "EPSV" => {
+                let listener = t!(TcpListener::bind("127.0.0.1:0"));
+                let port = t!(listener.local_addr()).port();
+                data = Some(Data::Passive(listener));
+                format!("229 Entering Extended Passive Mode (|||{}|)", port)
+            }
+            "PASV" => {
+                let listener = t!(TcpListener::bind("127.0.0.1:0"));
+                let port = t!(listener.local_addr()).port();
+                data = Some(Data::Passive(listener));
+                format!("227 Entering Passive Mode (127,0,0,1,{},{})",
+                        port >> 8, port & 0xff)
+            }
+            "EPRT" => {
+                let parts = arg.split('|').collect::<Vec<_>>();
+                let addr = format!("{}:{}", parts[2], parts[3]);
+                data = Some(Data::Active(t!(addr.parse())));
+                "200 ok".to_string()
+            }
+            "PORT" => {
+                let parts = arg.split(',').collect::<Vec<_>>();
+                let port = t!(parts[4].parse::<u16>()) * 256 +
+                           t!(parts[5].parse::<u16>());
+                let addr = format!("{}:{}", parts[..4].join("."), port);
+                data = Some(Data::Active(t!(addr.parse())));
+                "200 ok".to_string()
+            }
+            "MDTM" => {
+                if state.lock().unwrap().files.contains_key(&path) {
+                    "213 20240102030405".to_string()
+                } else {
+                    "550 no such file".to_string()
+                }
+            }
+            "SIZE" => {
+                match state.lock().unwrap().files.get(&path) {
+                    Some(contents) => format!("213 {}", contents.len()),
+                    None => "550 no such file".to_string(),
+                }
+            }
+            "RETR" => {
+                let contents = state.lock().unwrap().files.get(&path).cloned();
+                match contents {
+                    Some(contents) => {
+                        t!(socket.write_all(b"150 sending\r\n"));
+                        let mut conn = connect(data.take().unwrap());
+                        t!(conn.write_all(&contents));
+                        drop(conn);
+                        "226 done".to_string()
+                    }
+                    None => "550 no such file".to_string(),
+                }
+            }
+            "STOR" | "APPE" => {
+                t!(socket.write_all(b"150 go ahead\r\n"));
+                let mut conn = connect(data.take().unwrap());
+                let mut contents = Vec::new();
+                t!(conn.read_to_end(&mut contents));
+                let mut state = state.lock().unwrap();
+                let file = state.files.entry(path).or_insert_with(Vec::new);
+                if command == "STOR" {
+                    file.clear();
+                }
+                file.extend_from_slice(&contents);
+                "226 stored".to_string()
+            }
+            "DELE" => {
+                match state.lock().unwrap().files.remove(&path) {
+                    Some(_) => "250 deleted".to_string(),
+                    None => "550 no such file".to_string(),
+                }
+            }
+            "RNFR" => {
+                rename_from = Some(path);
+                "350 ready for RNTO".to_string()
+            }
+            "RNTO" => {
+                let mut state = state.lock().unwrap();
+                let from = rename_from.take().unwrap_or_default();
+                let file = state.files.remove(&from);
+                match file {
+                    Some(contents) => {
+                        state.files.insert(path, contents);
+                        "250 renamed".to_string()
+                    }
+                    None => "550 no such file".to_string(),
+                }
+            }
+            "QUIT" => {
+                t!(socket.write_all(b"221 bye\r\n"));
+                return
+            }
+            _ => "500 unknown command".to_string(),
+        };
+        t!(socket.write_all(format!("{}\r\n", reply).as_bytes()));
+    }
+}
+
+fn connect(data: Data) -> TcpStream {
+    match data {
+        Data::Passive(listener) => t!(listener.accept()).0,
+        Data::Active(addr) => t!(TcpStream::connect(addr)),
+    }
+}
+
+fn handle() -> Easy {
+    let mut e = Easy::new();
+    t!(e.timeout(Duration::new(20, 0)));
+    return e
+}
+
+fn upload(h: &mut Easy, url: &str, mut body: &[u8])
+          -> Result<(), curl::Error> {
+    try!(h.url(url));
+    try!(h.upload(true));
+    let mut transfer = h.transfer();
+    try!(transfer.read_function(|buf| Ok(body.read(buf).unwrap())));
+    transfer.perform()
+}
+
+fn download(h: &mut Easy, url: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    t!(h.url(url));
+    {
+        let mut transfer = h.transfer();
+        t!(transfer.write_function(|data| {
+            body.extend_from_slice(data);
+            Ok(data.len())
+        }));
+        t!(transfer.perform());
+    }
+    body
+}
+
+#[test]
+fn passive_modes() {
+    let s = FtpServer::new();
+    s.put("/file", b"hello");
+
+    let mut h = handle();
+    assert_eq!(download(&mut h, &s.url("/file")), b"hello");
+    assert!(s.commands().contains(&"EPSV".to_string()));
+    assert!(!s.commands().contains(&"PASV".to_string()));
+
+    let s = FtpServer::new();
+    s.put("/file", b"hello");
+    let mut h = handle();
+    t!(h.ftp_use_epsv(false));
+    assert_eq!(download(&mut h, &s.url("/file")), b"hello");
+    assert!(s.commands().contains(&"PASV".to_string()));
+    assert!(!s.commands().contains(&"EPSV".to_string()));
+}
+
+#[test]
+fn active_modes() {
+    let s = FtpServer::new();
+    s.put("/file", b"hello");
+
+    let mut h = handle();
+    t!(h.ftp_port("127.0.0.1"));
+    assert_eq!(download(&mut h, &s.url("/file")), b"hello");
+    let eprt = s.sent("EPRT");
+    assert_eq!(eprt.len(), 1);
+    assert!(eprt[0].starts_with("EPRT |1|127.0.0.1|"), "{}", eprt[0]);
+    assert!(s.sent("EPSV").is_empty());
+
+    let s = FtpServer::new();
+    s.put("/file", b"hello");
+    let mut h = handle();
+    t!(h.ftp_port("127.0.0.1"));
+    t!(h.ftp_use_eprt(false));
+    assert_eq!(download(&mut h, &s.url("/file")), b"hello");
+    let port = s.sent("PORT");
+    assert_eq!(port.len(), 1);
+    assert!(port[0].starts_with("PORT 127,0,0,1,"), "{}", port[0]);
+    assert!(s.sent("EPRT").is_empty());
+    assert_eq!(s.file("/file"), Some(b"hello".to_vec()));
+}
+
+#[test]
+fn create_missing_dirs() {
+    let s = FtpServer::new();
+    let url = s.url("/exports/2024-01-02/data.csv");
+
+    let mut h = handle();
+    let err = upload(&mut h, &url, b"a,b").unwrap_err();
+    assert!(err.is_remote_access_denied() || err.is_upload_failed(),
+            "{:?}", err);
+    assert_eq!(s.file("/exports/2024-01-02/data.csv"), None);
+
+    let mut h = handle();
+    t!(h.ftp_create_missing_dirs(FtpCreateDirs::Create));
+    t!(upload(&mut h, &url, b"a,b"));
+    assert_eq!(s.file("/exports/2024-01-02/data.csv"),
+               Some(b"a,b".to_vec()));
+    assert_eq!(s.sent("MKD"), ["MKD exports", "MKD 2024-01-02"]);
+}
+
+#[test]
+fn quote_commands() {
+    let s = FtpServer::new();
+    s.put("/old", b"stale");
+
+    let mut before = List::new();
+    t!(before.append("DELE old"));
+    t!(before.append("*DELE missing"));
+    let mut after = List::new();
+    t!(after.append("RNFR report.part"));
+    t!(after.append("RNTO report"));
+    let mut h = handle();
+    t!(h.quote(before));
+    t!(h.post_quote(after));
+    t!(upload(&mut h, &s.url("/report.part"), b"done"));
+    assert_eq!(s.file("/old"), None);
+    assert_eq!(s.file("/report.part"), None);
+    assert_eq!(s.file("/report"), Some(b"done".to_vec()));
+    assert_eq!(s.sent("DELE"), ["DELE old", "DELE missing"]);
+
+    let mut before = List::new();
+    t!(before.append("DELE missing"));
+    let mut h = handle();
+    t!(h.quote(before));
+    let err = upload(&mut h, &s.url("/other"), b"x").unwrap_err();
+    assert!(err.is_quote_error(), "{:?}", err);
+    assert_eq!(s.file("/other"), None);
+}
+
+#[test]
+fn append() {
+    let s = FtpServer::new();
+    s.put("/log", b"one\n");
+    let mut h = handle();
+    t!(h.append(true));
+    t!(upload(&mut h, &s.url("/log"), b"two\n"));
+    t!(upload(&mut h, &s.url("/new"), b"three\n"));
+    assert_eq!(s.file("/log"), Some(b"one\ntwo\n".to_vec()));
+    assert_eq!(s.file("/new"), Some(b"three\n".to_vec()));
+    assert_eq!(s.sent("APPE").len(), 2);
+    assert!(s.sent("STOR").is_empty());
+}
+
+#[test]
+fn resumable_upload() {
+    let s = FtpServer::new();
+    s.put("/big", b"hello");
+    let upload = ResumableUpload::new(&s.url("/big"));
+    let mut h = handle();
+    let summary = t!(upload.upload(&mut h, Cursor::new(&b"hello world"[..])));
+    assert_eq!(summary.len(), 11);
+    assert_eq!(summary.resumed(), 5);
+    assert_eq!(s.file("/big"), Some(b"hello world".to_vec()));
+    assert_eq!(s.sent("APPE").len(), 1);
+
+    // Nothing is left to send the second time around.
+    let summary = t!(upload.upload(&mut h, Cursor::new(&b"hello world"[..])));
+    assert_eq!(summary.resumed(), 11);
+    assert_eq!(s.sent("APPE").len(), 1);
+
+    let upload = ResumableUpload::new(&s.url("/fresh"));
+    let summary = t!(upload.upload(&mut h, Cursor::new(&b"data"[..])));
+    assert_eq!(summary.resumed(), 0);
+    assert_eq!(s.file("/fresh"), Some(b"data".to_vec()));
+
+    let err = upload.upload(&mut h, Cursor::new(&b"da"[..])).unwrap_err();
+    assert!(err.to_string().contains("larger"), "{}", err);
+    assert_eq!(s.file("/fresh"), Some(b"data".to_vec()));
+}
+
+/// A source whose contents can be sought through but not read.
+struct Unreadable(Cursor<&'static [u8]>);
+
+impl Read for Unreadable {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "disk on fire"))
+    }
+}
+
+impl Seek for Unreadable {
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        self.0.seek(to)
+    }
+}
+
+#[test]
+fn resumable_upload_read_error() {
+    let s = FtpServer::new();
+    s.put("/big", b"hello");
+    let upload = ResumableUpload::new(&s.url("/big"));
+    let mut h = handle();
+    let source = Unreadable(Cursor::new(&b"hello world"[..]));
+    let err = upload.upload(&mut h, source).unwrap_err();
+    assert_eq!(err.to_string(), "disk on fire");
+
+    // The handle is back to downloading from the start.
+    assert_eq!(download(&mut h, &s.url("/big")), b"hello");
+}
+
+#[test]
+fn stat() {
+    let s = FtpServer::new();
+    s.put("/pub/file", b"contents");
+
+    let mut h = handle();
+    let meta = t!(h.stat(&s.url("/pub/file")));
+    assert_eq!(meta.size(), Some(8));
+    assert_eq!(meta.modified(),
+               Some(UNIX_EPOCH + Duration::from_secs(1704164645)));
+    assert!(s.sent("RETR").is_empty());
+
+    let err = h.stat(&s.url("/pub/missing")).unwrap_err();
+    assert!(err.is_remote_file_not_found(), "{:?}", err);
+}
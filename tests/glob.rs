@@ -0,0 +1,93 @@
+extern crate curl;
+
+use curl::glob::{GlobError, UrlGlob};
+use curl::http::Request;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+fn urls(pattern: &str) -> Vec<String> {
+    t!(UrlGlob::parse(pattern)).iter().map(|m| m.url().to_string()).collect()
+}
+
+#[test]
+fn plain_url() {
+    let glob = t!(UrlGlob::parse("http://example.com/"));
+    assert!(!glob.is_glob());
+    assert_eq!(glob.len(), 1);
+    assert_eq!(urls("http://example.com/"), ["http://example.com/"]);
+}
+
+#[test]
+fn sets() {
+    assert_eq!(urls("http://{a,b,}.example.com/{x,y}"),
+               ["http://a.example.com/x",
+                "http://a.example.com/y",
+                "http://b.example.com/x",
+                "http://b.example.com/y",
+                "http://.example.com/x",
+                "http://.example.com/y"]);
+}
+
+#[test]
+fn ranges() {
+    assert_eq!(urls("/[1-3]"), ["/1", "/2", "/3"]);
+    assert_eq!(urls("/[08-10]"), ["/08", "/09", "/10"]);
+    assert_eq!(urls("/[0-10:5]"), ["/0", "/5", "/10"]);
+    assert_eq!(urls("/[a-e:2]"), ["/a", "/c", "/e"]);
+    assert_eq!(t!(UrlGlob::parse("/[1-100]/[001-100]")).len(), 10000);
+}
+
+#[test]
+fn escapes_and_ipv6() {
+    assert_eq!(urls("/\\{a,b\\}/\\[1-2\\]"), ["/{a,b}/[1-2]"]);
+    assert_eq!(urls("/{a\\,b,c}"), ["/a,b", "/c"]);
+    assert_eq!(urls("http://[::1]:80/[1-2]"),
+               ["http://[::1]:80/1", "http://[::1]:80/2"]);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(UrlGlob::parse("/{a,b").unwrap_err(), GlobError::Unbalanced(1));
+    assert_eq!(UrlGlob::parse("/a]").unwrap_err(), GlobError::Unbalanced(2));
+    assert_eq!(UrlGlob::parse("/{a,[1-2]}").unwrap_err(),
+               GlobError::Nested(4));
+    assert_eq!(UrlGlob::parse("/[3-1]").unwrap_err(),
+               GlobError::InvalidRange(1));
+    assert_eq!(UrlGlob::parse("/[a-9]").unwrap_err(),
+               GlobError::InvalidRange(1));
+    assert_eq!(UrlGlob::parse("/[1-2:0]").unwrap_err(),
+               GlobError::InvalidRange(1));
+    let huge = "/[1-4294967296][1-4294967296]";
+    assert_eq!(UrlGlob::parse(huge).unwrap_err(), GlobError::TooLarge);
+    assert_eq!(UrlGlob::parse("/[0-18446744073709551615]").unwrap_err(),
+               GlobError::TooLarge);
+    assert_eq!(UrlGlob::parse("/[1-18446744073709551615]").unwrap().len(),
+               18446744073709551615);
+}
+
+#[test]
+fn output_name() {
+    let glob = t!(UrlGlob::parse("http://{one,two}.example.com/[1-2]"));
+    let names = glob.iter()
+                    .map(|m| m.output_name("#2-#1#3.#.txt"))
+                    .collect::<Vec<_>>();
+    assert_eq!(names, ["1-one#3.#.txt", "2-one#3.#.txt",
+                       "1-two#3.#.txt", "2-two#3.#.txt"]);
+}
+
+#[test]
+fn expand_request() {
+    let mut request = Request::post("http://example.com/{a,b}");
+    request.header("X-Test", "1");
+    let requests = t!(request.expand_glob());
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].0.values(), ["b"]);
+    assert_eq!(requests[1].1.url(), "http://example.com/b");
+    assert_eq!(requests[1].1.method(), "POST");
+    assert!(requests[1].1.to_curl_command(false).contains("X-Test: 1"));
+}
@@ -0,0 +1,24 @@
+extern crate curl;
+
+use curl::InitFlags;
+use curl::easy::Easy;
+
+// Cleaning up libcurl is global and irreversible, so this is the only test in
+// this binary.
+#[test]
+fn init_and_cleanup() {
+    let mut flags = InitFlags::new();
+    flags.ssl(true).win32(true);
+    assert_eq!(flags, InitFlags::all());
+    assert!(curl::init_with(&flags).is_ok());
+    assert!(curl::init_with(&InitFlags::new()).is_ok());
+    curl::init();
+    drop(Easy::new());
+
+    unsafe {
+        curl::cleanup();
+        curl::cleanup();
+    }
+    let err = curl::init_with(&flags).unwrap_err();
+    assert!(err.is_failed_init());
+}
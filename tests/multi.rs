@@ -8,6 +8,7 @@ use std::io::{Read, Cursor};
 use std::time::Duration;
 
 use curl::easy::{Easy, List};
+use curl::head_cache::HeadCache;
 use curl::multi::Multi;
 
 macro_rules! t {
@@ -79,6 +80,52 @@ Accept: */*\r\n\
     assert_eq!(done, 2);
 }
 
+#[test]
+fn connection_limits() {
+    let mut m = Multi::new();
+    t!(m.pipelining(false, true));
+    t!(m.max_concurrent_streams(10));
+    t!(m.max_connects(1));
+    t!(m.max_host_connections(1));
+    t!(m.max_total_connections(1));
+
+    let s1 = Server::new();
+    s1.receive("\
+GET / HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+Accept: */*\r\n\
+\r\n");
+    s1.send("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+
+    let s2 = Server::new();
+    s2.receive("\
+GET / HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+Accept: */*\r\n\
+\r\n");
+    s2.send("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+
+    let mut e1 = Easy::new();
+    t!(e1.url(&s1.url("/")));
+    t!(e1.http_pipewait(true));
+    let _e1 = t!(m.add(e1));
+    let mut e2 = Easy::new();
+    t!(e2.url(&s2.url("/")));
+    t!(e2.http_pipewait(true));
+    let _e2 = t!(m.add(e2));
+
+    while t!(m.perform()) > 0 {
+        t!(m.wait(Duration::from_secs(1)));
+    }
+
+    let mut done = 0;
+    m.messages(|msg| {
+        msg.result().unwrap().unwrap();
+        done += 1;
+    });
+    assert_eq!(done, 2);
+}
+
 #[test]
 fn upload_lots() {
     use curl::multi::{Socket, SocketEvents, Events};
@@ -221,3 +268,38 @@ HTTP/1.1 200 OK\r\n\
     let mut e = t!(m.remove(e));
     assert_eq!(t!(e.response_code()), 200);
 }
+
+#[test]
+fn head_cache() {
+    let s1 = Server::new();
+    s1.receive("\
+HEAD / HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+Accept: */*\r\n\
+\r\n");
+    s1.send("HTTP/1.1 200 OK\r\nETag: \"a\"\r\nContent-Length: 5\r\n\r\n");
+
+    let s2 = Server::new();
+    s2.receive("\
+HEAD / HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+Accept: */*\r\n\
+\r\n");
+    s2.send("HTTP/1.1 200 OK\r\nETag: \"b\"\r\nContent-Length: 5\r\n\r\n");
+
+    let saved = format!("{}\t200\t5\t\"a\"\t-\n", s1.url("/"));
+    let mut cache = t!(HeadCache::load(Cursor::new(saved)));
+    let diff = t!(cache.refresh(&[s1.url("/"), s2.url("/")]));
+    assert!(diff.failed().is_empty());
+    assert_eq!(diff.unchanged(), &[s1.url("/")]);
+    assert_eq!(diff.changed(), vec![&s2.url("/")[..]]);
+
+    let meta = cache.get(&s2.url("/")).unwrap();
+    assert_eq!(meta.etag(), Some("\"b\""));
+    assert_eq!(meta.content_length(), Some(5));
+
+    let mut out = Vec::new();
+    t!(cache.save(&mut out));
+    let cache = t!(HeadCache::load(Cursor::new(out)));
+    assert_eq!(cache.get(&s2.url("/")), Some(meta));
+}
@@ -0,0 +1,172 @@
+extern crate curl;
+
+use std::fs::{self, File};
+use std::env;
+use std::io::{self, Read, Write};
+use std::process;
+use std::time::Duration;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+use curl::easy::Easy;
+use curl::sink::{self, Algorithm, CountSink, DigestSink, Hasher, Md5, Sha256};
+use curl::sink::TeeSink;
+
+use server::Server;
+mod server;
+
+fn handle() -> Easy {
+    let mut e = Easy::new();
+    t!(e.timeout(Duration::new(20, 0)));
+    return e
+}
+
+fn sha256(chunks: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    sink::to_hex(&hasher.finish())
+}
+
+#[test]
+fn sha256_vectors() {
+    assert_eq!(sha256(&[]),
+               "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(sha256(&[b"abcdbcdecdefdefgefghfghighijhijk",
+                        b"ijkljklmklmnlmnomnopnopq"]),
+               "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    let a = vec![b'a'; 1000];
+    let chunks = (0..1000).map(|_| &a[..]).collect::<Vec<_>>();
+    assert_eq!(sha256(&chunks),
+               "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+}
+
+fn md5(chunks: &[&[u8]]) -> String {
+    let mut hasher = Md5::new();
+    for chunk in chunks {
+        hasher.update(chunk);
+    }
+    sink::to_hex(&hasher.finish())
+}
+
+#[test]
+fn md5_vectors() {
+    assert_eq!(md5(&[]), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(md5(&[b"message ", b"digest"]),
+               "f96b697d7cb7938d525a2f31aaf161d0");
+    assert_eq!(md5(&[b"1234567890123456789012345678901234567890",
+                     b"1234567890123456789012345678901234567890"]),
+               "57edf4a22be3c955ac49da2e2107b67a");
+}
+
+#[test]
+fn hasher() {
+    let mut hasher = Hasher::new(Algorithm::Md5);
+    assert_eq!(hasher.algorithm(), Algorithm::Md5);
+    hasher.update(b"abc");
+    assert_eq!(sink::to_hex(&hasher.finish()),
+               "900150983cd24fb0d6963f7d28e17f72");
+
+    let mut hasher = Hasher::new(Algorithm::Sha256);
+    hasher.update(b"abc");
+    assert_eq!(sink::to_hex(&hasher.finish()), sha256(&[b"abc"]));
+}
+
+#[test]
+fn count_and_digest() {
+    let s = Server::new();
+    s.receive("\
+GET / HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+Accept: */*\r\n\
+\r\n");
+    s.send("HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabc");
+
+    let count = CountSink::new();
+    let digest = DigestSink::new();
+    let mut h = handle();
+    t!(h.url(&s.url("/")));
+    {
+        let mut transfer = h.transfer();
+        t!(transfer.write_function(|data| {
+            let _ = count.write(data);
+            digest.write(data)
+        }));
+        t!(transfer.perform());
+    }
+    assert_eq!(count.count(), 3);
+    assert_eq!(digest.count(), 3);
+    assert_eq!(digest.hex_digest(),
+               "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+}
+
+/// A writer which fails once it has been given more than `limit` bytes.
+struct Full {
+    limit: usize,
+}
+
+impl Write for Full {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() > self.limit {
+            return Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+        }
+        self.limit -= data.len();
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn tee() {
+    let s = Server::new();
+    s.receive("\
+GET / HTTP/1.1\r\n\
+Host: 127.0.0.1:$PORT\r\n\
+Accept: */*\r\n\
+\r\n");
+    s.send("HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabc");
+
+    let path = env::temp_dir().join(format!("curl-rust-tee-{}", process::id()));
+    let digest = DigestSink::new();
+    let mut tee = TeeSink::new();
+    tee.writer(t!(File::create(&path)))
+       .writer(digest.clone())
+       .keep_body();
+    let mut h = handle();
+    t!(h.url(&s.url("/")));
+    {
+        let mut transfer = h.transfer();
+        t!(transfer.write_function(|data| tee.write(data)));
+        t!(transfer.perform());
+    }
+    t!(tee.flush());
+    assert_eq!(tee.count(), 3);
+    assert_eq!(tee.take_body(), b"abc");
+    assert_eq!(tee.take_body(), b"");
+    assert_eq!(digest.hex_digest(), sha256(&[b"abc"]));
+    let mut contents = Vec::new();
+    t!(t!(File::open(&path)).read_to_end(&mut contents));
+    assert_eq!(contents, b"abc");
+    t!(fs::remove_file(&path));
+    assert!(tee.take_error().is_none());
+
+    let count = CountSink::new();
+    let mut tee = TeeSink::new();
+    tee.writer(Full { limit: 2 }).writer(count.clone());
+    assert!(tee.write(b"a").is_ok());
+    assert_eq!(tee.write(b"bc").ok(), Some(0));
+    assert_eq!(tee.write(b"d").ok(), Some(0));
+    assert_eq!(tee.count(), 1);
+    assert_eq!(count.count(), 1);
+    assert_eq!(t!(tee.take_error().ok_or("no error")).to_string(),
+               "disk full");
+}
@@ -0,0 +1,167 @@
+extern crate curl;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use curl::easy::Easy;
+use curl::sse::EventStream;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {:?}", stringify!($e), e),
+    })
+}
+
+/// A server which answers each connection with the next of a list of
+/// responses and then closes it, recording the request line and the
+/// `Last-Event-ID` header of each request.
+struct StreamServer {
+    url: String,
+    ids: Arc<Mutex<Vec<Option<String>>>>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StreamServer {
+    fn new(responses: Vec<String>) -> StreamServer {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let url = format!("http://{}/events", t!(listener.local_addr()));
+        let ids = Arc::new(Mutex::new(Vec::new()));
+        let seen = ids.clone();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let lines = requests.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (socket, _) = t!(listener.accept());
+                let mut reader = BufReader::new(t!(socket.try_clone()));
+                let mut id = None;
+                let mut request = String::new();
+                t!(reader.read_line(&mut request));
+                lines.lock().unwrap().push(request.trim().to_string());
+                loop {
+                    let mut line = String::new();
+                    t!(reader.read_line(&mut line));
+                    if line == "\r\n" || line.is_empty() {
+                        break
+                    }
+                    if line.starts_with("Last-Event-ID: ") {
+                        id = Some(line[15..].trim().to_string());
+                    }
+                }
+                seen.lock().unwrap().push(id);
+                let mut socket = socket;
+                t!(socket.write_all(response.as_bytes()));
+            }
+        });
+        StreamServer { url: url, ids: ids, requests: requests }
+    }
+}
+
+fn handle() -> Easy {
+    let mut e = Easy::new();
+    t!(e.timeout(Duration::new(20, 0)));
+    return e
+}
+
+fn stream(body: &str) -> String {
+    format!("HTTP/1.1 200 OK\r\n\
+             Content-Type: text/event-stream; charset=utf-8\r\n\
+             Connection: close\r\n\
+             \r\n\
+             {}", body)
+}
+
+#[test]
+fn reconnects() {
+    let first = stream("\u{feff}: hello\r\n\
+                        retry: 10\r\n\
+                        data: one\r\n\
+                        data: two\r\n\
+                        id: 1\r\n\
+                        \r\n\
+                        event: update\n\
+                        data:three\n\
+                        \n\
+                        data: lost");
+    let second = stream("id: 2\rdata: four\r\r");
+    let done = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n";
+    let s = StreamServer::new(vec![first, second, done.to_string()]);
+
+    let mut events = Vec::new();
+    let mut h = handle();
+    t!(EventStream::new(&s.url)
+           .last_event_id("0")
+           .run(&mut h, |event| {
+               events.push((event.event().to_string(),
+                            event.data().to_string(),
+                            event.id().map(|s| s.to_string())));
+               true
+           }));
+    let owned = |event: &str, data: &str, id: &str| {
+        (event.to_string(), data.to_string(), Some(id.to_string()))
+    };
+    assert_eq!(events, [owned("message", "one\ntwo", "1"),
+                        owned("update", "three", "1"),
+                        owned("message", "four", "2")]);
+    assert_eq!(*s.ids.lock().unwrap(),
+               [Some("0".to_string()), Some("1".to_string()),
+                Some("2".to_string())]);
+}
+
+#[test]
+fn cancel_and_limits() {
+    let body = stream("data: a\n\ndata: b\n\n");
+    let s = StreamServer::new(vec![body]);
+    let mut seen = Vec::new();
+    let mut h = handle();
+    t!(EventStream::new(&s.url).run(&mut h, |event| {
+        seen.push(event.data().to_string());
+        false
+    }));
+    assert_eq!(seen, ["a"]);
+
+    let body = stream("data: a\n\n");
+    let s = StreamServer::new(vec![body]);
+    let mut count = 0;
+    let mut stream = EventStream::new(&s.url);
+    stream.reconnect_delay(Duration::from_millis(10)).max_reconnects(1);
+    let err = stream.run(&mut h, |_| { count += 1; true }).unwrap_err();
+    assert!(err.is_couldnt_connect() || err.is_recv_error() ||
+            err.is_got_nothing(), "{:?}", err);
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn resets_method() {
+    let s = StreamServer::new(vec![stream("data: a\n\n")]);
+    let mut h = handle();
+    t!(h.custom_request("DELETE"));
+    t!(h.upload(true));
+    t!(EventStream::new(&s.url).run(&mut h, |_| false));
+    assert_eq!(*s.requests.lock().unwrap(), ["GET /events HTTP/1.1"]);
+}
+
+#[test]
+fn rejected_responses() {
+    let s = StreamServer::new(vec!["HTTP/1.1 200 OK\r\n\
+                                    Content-Type: text/plain\r\n\
+                                    Connection: close\r\n\
+                                    \r\n\
+                                    data: a\n\n".to_string()]);
+    let mut h = handle();
+    let err = EventStream::new(&s.url).run(&mut h, |_| true).unwrap_err();
+    assert!(err.is_write_error());
+    assert_eq!(err.extra_description(),
+               Some("response is not an event stream"));
+
+    let s = StreamServer::new(vec!["HTTP/1.1 503 Unavailable\r\n\
+                                    Content-Length: 0\r\n\
+                                    Connection: close\r\n\
+                                    \r\n".to_string()]);
+    let err = EventStream::new(&s.url).run(&mut h, |_| true).unwrap_err();
+    assert!(err.is_http_returned_error());
+    assert_eq!(err.status(), Some(503));
+}